@@ -0,0 +1,99 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+use wda::BasicAutomation;
+use wda::GeckoDriver;
+use wda::WdaError;
+use wda::WdcError;
+use wda::WebDrvAstn;
+
+pub type BackendResult<T> = core::result::Result<T, WdaError>;
+
+///
+/// Browser operations that components rely on.
+///
+/// Errors are reported as Wda errors, so that components handle them in the
+/// same way regardless of the backend, e.g. a timeout is expected to be
+/// `WdaError::WdcFail(WdcError::BadDrvCmd("timeout", ..))`.
+pub trait BrowserBackend {
+    ///
+    /// Navigate to `url`, block until the page is loaded.
+    fn go_url(&self, url: &str) -> BackendResult<()>;
+
+    ///
+    /// Evaluate `script` synchronously, the returned value is JSON-encoded.
+    fn eval(&self, script: &str, args: Vec<&str>) -> BackendResult<String>;
+
+    ///
+    /// Evaluate `script` asynchronously, the returned value is JSON-encoded.
+    fn eval_async(&self, script: &str, args: Vec<&str>) -> BackendResult<String>;
+
+    fn get_url(&self) -> BackendResult<String>;
+
+    ///
+    /// Source of current page, `None` if it is saved to `save_to`.
+    fn page_src(&self, save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>>;
+}
+
+impl BrowserBackend for WebDrvAstn<GeckoDriver> {
+    fn go_url(&self, url: &str) -> BackendResult<()> {
+        BasicAutomation::go_url(self, url).map(|_| ())
+    }
+
+    fn eval(&self, script: &str, args: Vec<&str>) -> BackendResult<String> {
+        BasicAutomation::eval(self, script, args)
+    }
+
+    fn eval_async(&self, script: &str, args: Vec<&str>) -> BackendResult<String> {
+        BasicAutomation::eval_async(self, script, args)
+    }
+
+    fn get_url(&self) -> BackendResult<String> {
+        BasicAutomation::get_url(self)
+    }
+
+    fn page_src(&self, save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+        BasicAutomation::page_src(self, save_to)
+    }
+}
+
+///
+/// No browser at all, every operation is rejected. Used when mafa is
+/// offline, where only stored results are served.
+#[derive(Debug, Default)]
+pub struct Offline;
+
+fn offline_rejected() -> WdaError {
+    WdaError::WdcFail(WdcError::BadDrvCmd(
+        "offline".to_string(),
+        "no browser is available in offline mode".to_string(),
+    ))
+}
+
+impl BrowserBackend for Offline {
+    fn go_url(&self, _url: &str) -> BackendResult<()> {
+        Err(offline_rejected())
+    }
+
+    fn eval(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+        Err(offline_rejected())
+    }
+
+    fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+        Err(offline_rejected())
+    }
+
+    fn get_url(&self) -> BackendResult<String> {
+        Err(offline_rejected())
+    }
+
+    fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+        Err(offline_rejected())
+    }
+}
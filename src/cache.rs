@@ -0,0 +1,1150 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Management of components' caches.
+//!
+//! A cache is a list of paths locating the interested element of a
+//! website, from `document.body` down through child nodes' indexes. gtrans
+//! and camd use one path (`Upath`), twtl uses a pair of paths (`UlPath`),
+//! the upper one locating the timeline, the lower one locating a tweet
+//! inside it. Entries are tried in order, see [`rank`].
+//!
+//! Cache files are versioned. v1 is one bare entry per line, ended by a
+//! `-` line, v2 is a JSON object whose entries carry their own metadata:
+//!
+//! ```text
+//! {"sver":"v2","entries":[{"path":[11,1,1,3,3],"learned":1697500000,
+//!   "probe":"local","ok":3,"fail":1,"streak":0,"last_ok":1697600000}]}
+//! ```
+//!
+//! v1 files, whether left by older mafa or fetched from the remote
+//! repository, are migrated to v2 once they are loaded.
+//!
+//! A remote repository provides one file for each component. If a manifest
+//! URL is given, they are verified against a [`MANIFEST_NAME`] file in
+//! `sha256sum` format fetched from there.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use clap::Arg as ClapArg;
+use clap::ArgMatches as ClapArgMatches;
+use clap::Command as ClapCommand;
+use sha2::Digest;
+use sha2::Sha256;
+use wda::WdaError;
+use wda::WdcError;
+
+use crate::backend::BrowserBackend;
+use crate::error::MafaError;
+use crate::error::Result;
+use crate::ev_ntf::EurKind;
+use crate::mafadata::secs_since_epoch;
+use crate::mafadata::MafaData;
+use crate::OutputFormat;
+
+///
+/// every component having a cache
+pub const CACHE_IDS: [&str; 3] = ["gtrans", "camd", "twtl"];
+
+///
+/// structure version of cache files written by this mafa
+pub const CACHE_SVER: &str = "v2";
+
+///
+/// entries failing this many times in a row are dropped
+pub const PRUNE_AFTER_FAILS: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheEntry {
+    Upath(Vec<u8>),
+    UlPath { upper: Vec<u8>, lower: Vec<u8> },
+}
+
+impl CacheEntry {
+    ///
+    /// one line of the cache file of `cache_id`
+    pub fn from_line(cache_id: &str, line: &str) -> Result<Self> {
+        let value = serde_json::from_str::<serde_json::Value>(line)
+            .map_err(|_| MafaError::CacheCorrupted)?;
+
+        Self::from_value(cache_id, value)
+    }
+
+    fn from_value(cache_id: &str, value: serde_json::Value) -> Result<Self> {
+        if cache_id == "twtl" {
+            let deser = serde_json::from_value::<Vec<Vec<u8>>>(value)
+                .map_err(|_| MafaError::CacheCorrupted)?;
+            if deser.len() != 2 {
+                return Err(MafaError::CacheCorrupted);
+            }
+            let mut deser = deser.into_iter();
+            let upper = deser.next().expect("buggy");
+            let lower = deser.next().expect("buggy");
+
+            Ok(CacheEntry::UlPath { upper, lower })
+        } else {
+            let deser =
+                serde_json::from_value::<Vec<u8>>(value).map_err(|_| MafaError::CacheCorrupted)?;
+            if deser.is_empty() {
+                return Err(MafaError::CacheCorrupted);
+            }
+
+            Ok(CacheEntry::Upath(deser))
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        match self {
+            CacheEntry::Upath(upath) => serde_json::to_string(upath).expect("buggy"),
+            CacheEntry::UlPath { upper, lower } => {
+                serde_json::to_string(&[upper, lower]).expect("buggy")
+            }
+        }
+    }
+
+    fn to_value(&self) -> serde_json::Value {
+        serde_json::from_str(&self.to_line()).expect("buggy")
+    }
+
+    pub fn into_upath(self) -> Result<Vec<u8>> {
+        match self {
+            CacheEntry::Upath(upath) => Ok(upath),
+            _ => Err(MafaError::CacheCorrupted),
+        }
+    }
+
+    pub fn into_ulpath(self) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            CacheEntry::UlPath { upper, lower } => Ok((upper, lower)),
+            _ => Err(MafaError::CacheCorrupted),
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            CacheEntry::Upath(upath) => serde_json::json!({ "upath": upath }),
+            CacheEntry::UlPath { upper, lower } => {
+                serde_json::json!({ "upper": upper, "lower": lower })
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CacheEntry::Upath(upath) => {
+                format!("upath {} ({} steps)", self.to_line(), upath.len())
+            }
+            CacheEntry::UlPath { upper, lower } => format!(
+                "upper {} ({} steps), lower {} ({} steps)",
+                serde_json::to_string(upper).expect("buggy"),
+                upper.len(),
+                serde_json::to_string(lower).expect("buggy"),
+                lower.len()
+            ),
+        }
+    }
+}
+
+///
+/// An entry along with what is known about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheRecord {
+    pub entry: CacheEntry,
+    ///
+    /// seconds since epoch, 0 if unknown
+    pub learned: u64,
+    ///
+    /// where the entry came from, "local", "remote", "import", "v1" for
+    /// migrated ones, or the words probed when rebuilding
+    pub probe: String,
+    pub n_ok: u32,
+    pub n_fail: u32,
+    ///
+    /// failures since the last success
+    pub n_fail_streak: u32,
+    ///
+    /// seconds since epoch, 0 if never succeeded
+    pub last_ok: u64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct RawRecord {
+    path: serde_json::Value,
+    #[serde(default)]
+    learned: u64,
+    #[serde(default)]
+    probe: String,
+    #[serde(default)]
+    ok: u32,
+    #[serde(default)]
+    fail: u32,
+    #[serde(default)]
+    streak: u32,
+    #[serde(default)]
+    last_ok: u64,
+}
+
+///
+/// entries are `RawRecord`s, or `serde_json::Value`s before being told
+/// from v1 ones
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct RawCache<T> {
+    sver: String,
+    entries: Vec<T>,
+}
+
+impl CacheRecord {
+    pub fn new(entry: CacheEntry, probe: &str) -> Self {
+        CacheRecord {
+            entry,
+            learned: secs_since_epoch(),
+            probe: probe.to_string(),
+            n_ok: 0,
+            n_fail: 0,
+            n_fail_streak: 0,
+            last_ok: 0,
+        }
+    }
+
+    ///
+    /// `value` is either a v2 record, or a bare v1 entry, which is given
+    /// `v1_probe` and `v1_learned`.
+    fn from_value(
+        cache_id: &str,
+        value: serde_json::Value,
+        v1_probe: &str,
+        v1_learned: u64,
+    ) -> Result<Self> {
+        if !value.is_object() {
+            let entry = CacheEntry::from_value(cache_id, value)?;
+            let mut record = CacheRecord::new(entry, v1_probe);
+            record.learned = v1_learned;
+            return Ok(record);
+        }
+
+        let raw =
+            serde_json::from_value::<RawRecord>(value).map_err(|_| MafaError::CacheCorrupted)?;
+
+        Ok(CacheRecord {
+            entry: CacheEntry::from_value(cache_id, raw.path)?,
+            learned: raw.learned,
+            probe: raw.probe,
+            n_ok: raw.ok,
+            n_fail: raw.fail,
+            n_fail_streak: raw.streak,
+            last_ok: raw.last_ok,
+        })
+    }
+
+    fn to_raw(&self) -> RawRecord {
+        RawRecord {
+            path: self.entry.to_value(),
+            learned: self.learned,
+            probe: self.probe.clone(),
+            ok: self.n_ok,
+            fail: self.n_fail,
+            streak: self.n_fail_streak,
+            last_ok: self.last_ok,
+        }
+    }
+}
+
+fn is_v1(rawdata: &str) -> bool {
+    !rawdata.trim_start().starts_with('{')
+}
+
+///
+/// All records of a cache file of either version, entries of a v1 file are
+/// given `v1_probe` and `v1_learned`.
+pub fn decode(
+    cache_id: &str,
+    rawdata: &str,
+    v1_probe: &str,
+    v1_learned: u64,
+) -> Result<Vec<CacheRecord>> {
+    if is_v1(rawdata) {
+        return rawdata
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && *line != "-")
+            .map(|line| {
+                let value = serde_json::from_str(line).map_err(|_| MafaError::CacheCorrupted)?;
+                CacheRecord::from_value(cache_id, value, v1_probe, v1_learned)
+            })
+            .collect();
+    }
+
+    let raw = serde_json::from_str::<RawCache<serde_json::Value>>(rawdata)
+        .map_err(|_| MafaError::CacheCorrupted)?;
+    if raw.sver != CACHE_SVER {
+        return Err(MafaError::CacheCorrupted);
+    }
+
+    raw.entries
+        .into_iter()
+        .map(|value| CacheRecord::from_value(cache_id, value, v1_probe, v1_learned))
+        .collect()
+}
+
+pub fn encode(records: &[CacheRecord]) -> String {
+    serde_json::to_string(&RawCache {
+        sver: CACHE_SVER.to_string(),
+        entries: records.iter().map(|r| r.to_raw()).collect(),
+    })
+    .expect("buggy")
+}
+
+///
+/// Order records by how likely they work: the ones failed fewer times in a
+/// row first, then the ones succeeded more recently, ties keep their order.
+/// Entries failed [`PRUNE_AFTER_FAILS`] times in a row are dropped, but the
+/// best one is always kept.
+pub fn rank(records: &mut Vec<CacheRecord>) {
+    records.sort_by(|a, b| {
+        a.n_fail_streak
+            .cmp(&b.n_fail_streak)
+            .then(b.last_ok.cmp(&a.last_ok))
+    });
+
+    let n_keep = records
+        .iter()
+        .position(|r| r.n_fail_streak >= PRUNE_AFTER_FAILS)
+        .unwrap_or(records.len())
+        .max(1);
+    records.truncate(n_keep);
+}
+
+///
+/// Records of cache_id in the order to be tried, the file is rewritten if
+/// it is v1 or not ranked yet.
+pub fn load(mafad: &MafaData, cache_id: &str) -> Result<Vec<CacheRecord>> {
+    let mut loaded = None;
+
+    mafad.cache_modify(cache_id, |rawdata| {
+        let rawdata =
+            rawdata.ok_or_else(|| MafaError::MafaDataCacheNotFound(cache_id.to_string()))?;
+        let records = decode(cache_id, &rawdata, "v1", 0)?;
+        let mut ranked = records.clone();
+        rank(&mut ranked);
+        let rewritten = if is_v1(&rawdata) || ranked != records {
+            Some(encode(&ranked))
+        } else {
+            None
+        };
+        loaded = Some(ranked);
+
+        Ok(rewritten)
+    })?;
+
+    loaded.ok_or(MafaError::Bug("cache not loaded"))
+}
+
+///
+/// Replace cache_id with `rawdata` of either version.
+pub fn init(mafad: &MafaData, cache_id: &str, rawdata: &str, probe: &str) -> Result<()> {
+    let records = decode(cache_id, rawdata, probe, secs_since_epoch())?;
+
+    mafad.init_cache(cache_id, &encode(&records))
+}
+
+///
+/// Same as [`init`], but only if cache_id does not exist yet.
+pub fn try_init(mafad: &MafaData, cache_id: &str, rawdata: &str, probe: &str) -> Result<()> {
+    let records = decode(cache_id, rawdata, probe, secs_since_epoch())?;
+
+    mafad.try_init_cache(cache_id, &encode(&records))
+}
+
+///
+/// Put a newly learned entry first, an identical existing one is replaced,
+/// along with its counts.
+pub fn learn(mafad: &MafaData, cache_id: &str, entry: CacheEntry, probe: &str) -> Result<()> {
+    mafad.cache_modify(cache_id, |rawdata| {
+        let mut records = match rawdata {
+            Some(rawdata) => decode(cache_id, &rawdata, "v1", 0)?,
+            None => vec![],
+        };
+        records.retain(|r| r.entry != entry);
+        records.insert(0, CacheRecord::new(entry, probe));
+
+        Ok(Some(encode(&records)))
+    })
+}
+
+///
+/// Count a success or failure of `entry` and rank again, nothing is done
+/// if it is not in cache_id, e.g. it is given by callers rather than
+/// loaded.
+pub fn record_outcome(
+    mafad: &MafaData,
+    cache_id: &str,
+    entry: &CacheEntry,
+    is_ok: bool,
+) -> Result<()> {
+    mafad.cache_modify(cache_id, |rawdata| {
+        let rawdata = match rawdata {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let mut records = decode(cache_id, &rawdata, "v1", 0)?;
+        let record = match records.iter_mut().find(|r| r.entry == *entry) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        if is_ok {
+            record.n_ok = record.n_ok.saturating_add(1);
+            record.n_fail_streak = 0;
+            record.last_ok = secs_since_epoch();
+        } else {
+            record.n_fail = record.n_fail.saturating_add(1);
+            record.n_fail_streak = record.n_fail_streak.saturating_add(1);
+        }
+        rank(&mut records);
+
+        Ok(Some(encode(&records)))
+    })
+}
+
+///
+/// checksums of remote cache files, as output by `sha256sum`
+pub const MANIFEST_NAME: &str = "SHA256SUMS";
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|v| format!("{:02x}", v))
+        .collect()
+}
+
+///
+/// Whether `base` is a remote repository mafa is able to fetch from.
+pub fn is_valid_remote(base: &str) -> bool {
+    ["file://", "http://", "https://"]
+        .iter()
+        .any(|scheme| base.starts_with(scheme) && base.len() > scheme.len())
+}
+
+fn fetch_url(url: &str, via_browser: &impl Fn(&str) -> Result<String>) -> Result<String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        std::fs::read_to_string(path).map_err(|_err_io| {
+            dbgg!((url, _err_io));
+            MafaError::RemoteCacheNotReachable(url.to_string())
+        })
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        via_browser(url)
+    } else {
+        Err(MafaError::InvalidCacheRemote)
+    }
+}
+
+///
+/// Fetch `url` through `browser`, as the raw bytes of the response rather
+/// than the text the page renders, so that it hashes the same as the
+/// file it is listed as.
+pub fn fetch_via_browser(browser: &impl BrowserBackend, url: &str) -> Result<String> {
+    let wda_err = |err_wda: WdaError| {
+        if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_wda {
+            MafaError::WebDrvCmdRejected(err, msg)
+        } else {
+            MafaError::UnexpectedWda(err_wda)
+        }
+    };
+
+    // same origin as the response requested
+    browser.go_url(url).map_err(wda_err)?;
+
+    let jsin = "var send_back=arguments[arguments.length-1];fetch(location.href,{cache:'no-store'}).then(function(r){return r.ok?r.arrayBuffer():null}).then(function(b){send_back(b===null?null:Array.from(new Uint8Array(b)))}).catch(function(){send_back(null)});";
+    let jsout = browser.eval_async(jsin, vec![]).map_err(wda_err)?;
+
+    // the returned value is JSON-encoded
+    let rawdata = serde_json::from_str::<Option<Vec<u8>>>(&jsout)
+        .map_err(|_| MafaError::RemoteCacheRejected(url.to_string()))?
+        .ok_or_else(|| MafaError::RemoteCacheNotReachable(url.to_string()))?;
+
+    dbgg!(rawdata.len());
+
+    String::from_utf8(rawdata).map_err(|_| MafaError::RemoteCacheRejected(url.to_string()))
+}
+
+///
+/// (checksum, file name) of every line of `manifest`, as output by
+/// `sha256sum`
+fn parse_manifest(manifest: &str) -> Vec<(String, &str)> {
+    manifest
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let sum = parts.next()?;
+            let name = parts.next()?.trim_start_matches('*');
+            if sum.len() != 64 || !sum.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            Some((sum.to_lowercase(), name))
+        })
+        .collect()
+}
+
+///
+/// `rawdata`, fetched from `url`, must have at least one entry, and be
+/// listed in `manifest` with the same checksum if there is one.
+fn verify_remote(url: &str, cache_id: &str, rawdata: &str, manifest: Option<&str>) -> Result<()> {
+    let rejected = || MafaError::RemoteCacheRejected(url.to_string());
+
+    if let Some(manifest) = manifest {
+        let expected = parse_manifest(manifest)
+            .into_iter()
+            .find(|(_, name)| *name == cache_id)
+            .map(|(sum, _)| sum)
+            .ok_or_else(rejected)?;
+
+        if sha256_hex(rawdata.as_bytes()) != expected {
+            return Err(rejected());
+        }
+    }
+
+    match decode(cache_id, rawdata, "remote", 0) {
+        Ok(records) if !records.is_empty() => Ok(()),
+        _ => Err(rejected()),
+    }
+}
+
+///
+/// Fetch cache_id from the remote repository at `base`, verified against
+/// the manifest at `manifest_url` unless it is empty. The manifest is not
+/// taken from `base`, so that a repository is unable to vouch for itself.
+/// `http` and `https` ones are fetched through `via_browser`.
+pub fn fetch_remote(
+    base: &str,
+    manifest_url: &str,
+    cache_id: &str,
+    via_browser: impl Fn(&str) -> Result<String>,
+) -> Result<String> {
+    if !is_valid_remote(base) || !(manifest_url.is_empty() || is_valid_remote(manifest_url)) {
+        return Err(MafaError::InvalidCacheRemote);
+    }
+    let base = base.trim_end_matches('/');
+
+    let manifest = if manifest_url.is_empty() {
+        None
+    } else {
+        match fetch_url(manifest_url, &via_browser) {
+            Ok(v) if !parse_manifest(&v).is_empty() => Some(v),
+            _ => return Err(MafaError::RemoteManifestMissing(manifest_url.to_string())),
+        }
+    };
+
+    let data_url = format!("{}/{}", base, cache_id);
+    let rawdata = fetch_url(&data_url, &via_browser)?;
+    verify_remote(&data_url, cache_id, &rawdata, manifest.as_deref())?;
+
+    Ok(rawdata)
+}
+
+fn read_records(mafad: &MafaData, cache_id: &str) -> Result<Option<Vec<CacheRecord>>> {
+    match mafad.cache_read(cache_id)? {
+        Some(rawdata) => Ok(Some(decode(cache_id, &rawdata, "v1", 0)?)),
+        None => Ok(None),
+    }
+}
+
+fn fmt_age(ts: u64) -> String {
+    if ts == 0 {
+        return "unknown".to_string();
+    }
+
+    let secs = secs_since_epoch().saturating_sub(ts);
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
+fn list(mafad: &MafaData, output: OutputFormat) -> Result<String> {
+    let mut all_item = vec![];
+    for cache_id in CACHE_IDS {
+        let n_entries = match read_records(mafad, cache_id) {
+            Ok(Some(records)) => Some(records.len()),
+            Ok(None) => None,
+            Err(MafaError::CacheCorrupted) => Some(0),
+            Err(err) => return Err(err),
+        };
+        all_item.push((cache_id, n_entries));
+    }
+
+    if let OutputFormat::Json = output {
+        let json_items = all_item
+            .iter()
+            .map(|(cache_id, n_entries)| {
+                serde_json::json!({
+                    "cache": cache_id,
+                    "path": mafad.pathto_cache(cache_id),
+                    "exist": n_entries.is_some(),
+                    "entries": n_entries.unwrap_or(0),
+                })
+            })
+            .collect::<Vec<serde_json::Value>>();
+
+        return Ok(serde_json::Value::Array(json_items).to_string());
+    }
+
+    let mut s = String::new();
+    for (cache_id, n_entries) in all_item {
+        match n_entries {
+            Some(n) => {
+                s += &format!(
+                    "{:<7} {} entries ({})\n",
+                    cache_id,
+                    n,
+                    mafad.pathto_cache(cache_id).display()
+                )
+            }
+            None => s += &format!("{:<7} not built\n", cache_id),
+        }
+    }
+    s.pop(); // trailing newline
+
+    Ok(s)
+}
+
+fn show(mafad: &MafaData, cache_id: &str, output: OutputFormat) -> Result<String> {
+    let records = read_records(mafad, cache_id)?
+        .ok_or_else(|| MafaError::MafaDataCacheNotFound(cache_id.to_string()))?;
+    let last_used = records
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.last_ok > 0)
+        .max_by_key(|(_, r)| r.last_ok)
+        .map(|(i, _)| i);
+
+    if let OutputFormat::Json = output {
+        let json_entries = records
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let mut v = r.entry.to_json();
+                v["learned"] = r.learned.into();
+                v["probe"] = r.probe.as_str().into();
+                v["ok"] = r.n_ok.into();
+                v["fail"] = r.n_fail.into();
+                v["streak"] = r.n_fail_streak.into();
+                v["last_ok"] = r.last_ok.into();
+                v["last_used"] = (last_used == Some(i)).into();
+                v
+            })
+            .collect::<Vec<serde_json::Value>>();
+
+        return Ok(serde_json::json!({
+            "cache": cache_id,
+            "path": mafad.pathto_cache(cache_id),
+            "entries": json_entries,
+        })
+        .to_string());
+    }
+
+    let mut s = format!(
+        "[{}] {} entries ({})\n",
+        cache_id,
+        records.len(),
+        mafad.pathto_cache(cache_id).display()
+    );
+    for (i, r) in records.iter().enumerate() {
+        s += &format!("  #{} {}", i + 1, r.entry.describe());
+        if last_used == Some(i) {
+            s += " <- last used";
+        }
+        s += &format!(
+            "\n     learned {} by {}, {} ok, {} failed",
+            fmt_age(r.learned),
+            r.probe,
+            r.n_ok,
+            r.n_fail
+        );
+        if r.n_fail_streak > 0 {
+            s += &format!(" ({} in a row)", r.n_fail_streak);
+        }
+        s += "\n";
+    }
+    s.pop(); // trailing newline
+
+    Ok(s)
+}
+
+fn clear(mafad: &MafaData, cache_id: Option<&str>) -> Result<String> {
+    let all_id = match cache_id {
+        Some(cache_id) => vec![cache_id],
+        None => CACHE_IDS.to_vec(),
+    };
+
+    for cache_id in all_id.iter() {
+        mafad.cache_remove(cache_id)?;
+    }
+
+    Ok(format!("cleared: {}", all_id.join(" ")))
+}
+
+///
+/// The exported file is a JSON object, keys are components, values are
+/// their v2 records in order.
+fn export(mafad: &MafaData, to: &str) -> Result<String> {
+    let mut all_cache = BTreeMap::new();
+    for cache_id in CACHE_IDS {
+        if let Some(records) = read_records(mafad, cache_id)? {
+            let raws = records
+                .iter()
+                .map(|r| r.to_raw())
+                .collect::<Vec<RawRecord>>();
+            all_cache.insert(cache_id, raws);
+        }
+    }
+
+    let n_caches = all_cache.len();
+    let wbuf = serde_json::to_string(&all_cache).map_err(MafaError::Json)?;
+    std::fs::write(to, wbuf).map_err(|err_io| MafaError::InvalidCacheFile(to.into(), err_io))?;
+
+    Ok(format!("exported {} caches to {}", n_caches, to))
+}
+
+///
+/// Bare entries, as exported by mafa using v1 caches, are accepted as well.
+/// Errors are of kind `InvalidData`.
+fn parse_exported(rawdata: &str) -> io::Result<BTreeMap<String, Vec<CacheRecord>>> {
+    let json_root = serde_json::from_str::<BTreeMap<String, Vec<serde_json::Value>>>(rawdata)?;
+
+    let now = secs_since_epoch();
+    let mut all_cache = BTreeMap::new();
+    for (cache_id, values) in json_root {
+        if !CACHE_IDS.contains(&cache_id.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown cache `{}`", cache_id),
+            ));
+        }
+        let records = values
+            .into_iter()
+            .map(|value| CacheRecord::from_value(&cache_id, value, "import", now))
+            .collect::<Result<Vec<CacheRecord>>>()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        all_cache.insert(cache_id, records);
+    }
+
+    Ok(all_cache)
+}
+
+///
+/// All caches in the file are validated before any of them replaces the
+/// existing one.
+fn import(mafad: &MafaData, from: &str) -> Result<String> {
+    let all_cache = std::fs::read_to_string(from)
+        .and_then(|rawdata| parse_exported(&rawdata))
+        .map_err(|err_io| MafaError::InvalidCacheFile(from.into(), err_io))?;
+
+    for (cache_id, records) in all_cache.iter() {
+        mafad.init_cache(cache_id, &encode(records))?;
+    }
+
+    Ok(format!(
+        "imported: {}",
+        all_cache
+            .keys()
+            .map(|k| k.as_str())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    ))
+}
+
+///
+/// Whether the requested operation needs a browser, only rebuilding does.
+pub fn needs_browser(ca_matched: &ClapArgMatches) -> bool {
+    matches!(ca_matched.subcommand(), Some(("rebuild", _)))
+}
+
+///
+/// Component to rebuild, if rebuilding is requested.
+pub fn rebuild_id(ca_matched: &ClapArgMatches) -> Option<&str> {
+    match ca_matched.subcommand() {
+        Some(("rebuild", sub_m)) => sub_m
+            .get_one::<String>(opts::Component::id())
+            .map(|v| v.as_str()),
+        _ => None,
+    }
+}
+
+///
+/// Handle every operation but rebuilding, see [`needs_browser`].
+pub fn handle(
+    mafad: &MafaData,
+    ca_matched: &ClapArgMatches,
+    output: OutputFormat,
+) -> Result<(EurKind, String)> {
+    let cache_id_of = |sub_m: &ClapArgMatches| {
+        sub_m
+            .get_one::<String>(opts::Component::id())
+            .map(|v| v.to_string())
+    };
+    let file_of = |sub_m: &ClapArgMatches| {
+        sub_m
+            .get_one::<String>(opts::File::id())
+            .map(|v| v.to_string())
+            .ok_or(MafaError::Bug("cache file not given"))
+    };
+
+    match ca_matched.subcommand() {
+        Some(("list", _)) => Ok((EurKind::CacheList, list(mafad, output)?)),
+        Some(("show", sub_m)) => {
+            let cache_id = cache_id_of(sub_m).ok_or(MafaError::Bug("cache id not given"))?;
+            Ok((EurKind::CacheShow, show(mafad, &cache_id, output)?))
+        }
+        Some(("clear", sub_m)) => Ok((
+            EurKind::CacheChanged,
+            clear(mafad, cache_id_of(sub_m).as_deref())?,
+        )),
+        Some(("export", sub_m)) => Ok((EurKind::CacheChanged, export(mafad, &file_of(sub_m)?)?)),
+        Some(("import", sub_m)) => Ok((EurKind::CacheChanged, import(mafad, &file_of(sub_m)?)?)),
+        _ => Err(MafaError::Bug("cache subcommand not handled")),
+    }
+}
+
+// opts //
+
+pub mod opts {
+    pub struct Component;
+    impl Component {
+        #[inline]
+        pub fn id() -> &'static str {
+            "COMPONENT"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "The component whose cache is operated"
+        }
+    }
+
+    pub struct File;
+    impl File {
+        #[inline]
+        pub fn id() -> &'static str {
+            "FILE"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "The file to export caches to, or import caches from"
+        }
+    }
+}
+
+pub fn get_cmd() -> ClapCommand {
+    let opt_comp = |required: bool| {
+        type O = opts::Component;
+        ClapArg::new(O::id())
+            .required(required)
+            .value_parser(CACHE_IDS)
+            .help(O::helper())
+    };
+
+    let opt_file = {
+        type O = opts::File;
+        ClapArg::new(O::id()).required(true).help(O::helper())
+    };
+
+    ClapCommand::new("cache")
+        .about("Inspect and manage components' caches")
+        .long_about(format!(
+            "Inspect and manage components' caches
+
+Caches tell components where the interested data is located on websites,
+they are stored under mafa's data directory, one file for each component.
+Entries that worked recently are tried first, entries failing {} times in
+a row are dropped. A broken cache can be rebuilt by visiting the website
+freshly, which is identical to running the component with --cache NO.",
+            PRUNE_AFTER_FAILS
+        ))
+        .subcommand_required(true)
+        .subcommand(ClapCommand::new("list").about("List all caches and their number of entries"))
+        .subcommand(
+            ClapCommand::new("show")
+                .about("Print entries of a cache, marking the one used last time")
+                .arg(opt_comp(true)),
+        )
+        .subcommand(
+            ClapCommand::new("rebuild")
+                .about("Rebuild a cache by visiting the website")
+                .arg(opt_comp(true)),
+        )
+        .subcommand(
+            ClapCommand::new("clear")
+                .about("Remove a cache, or all caches if no component given")
+                .arg(opt_comp(false)),
+        )
+        .subcommand(
+            ClapCommand::new("export")
+                .about("Export all caches into a JSON file")
+                .arg(opt_file.clone()),
+        )
+        .subcommand(
+            ClapCommand::new("import")
+                .about("Replace caches by the ones in a file exported before")
+                .arg(opt_file),
+        )
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[test]
+    fn decode_1() {
+        let records = decode(
+            "gtrans",
+            "[4,0,1,0,1,0,1,1,2,1,1,9,0,3,0,0,1]\n[4,0,1,0,1,0,1,1,2,1,1,9,0,2,0,0,1]\n-",
+            "local",
+            123,
+        )
+        .expect("buggy");
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[1].entry,
+            CacheEntry::Upath(vec![4, 0, 1, 0, 1, 0, 1, 1, 2, 1, 1, 9, 0, 2, 0, 0, 1])
+        );
+        assert_eq!(records[1].probe, "local");
+        assert_eq!(records[1].learned, 123);
+        assert_eq!((records[1].n_ok, records[1].n_fail), (0, 0));
+
+        let records = decode("twtl", "[[2,0,0,2],[0,0,1]]\n-", "v1", 0).expect("buggy");
+        assert_eq!(
+            records[0].entry,
+            CacheEntry::UlPath {
+                upper: vec![2, 0, 0, 2],
+                lower: vec![0, 0, 1]
+            }
+        );
+        assert_eq!(records[0].entry.to_line(), "[[2,0,0,2],[0,0,1]]");
+    }
+
+    #[test]
+    fn decode_2() {
+        assert!(decode("camd", "[]\n-", "v1", 0).is_err());
+        assert!(decode("camd", "[11,1,\n-", "v1", 0).is_err());
+        assert!(decode("twtl", "[11,1,1,3,3]\n-", "v1", 0).is_err());
+        assert!(decode("twtl", "[[1],[2],[3]]\n-", "v1", 0).is_err());
+        assert_eq!(decode("camd", "-", "v1", 0).expect("buggy"), vec![]);
+
+        assert!(decode("camd", r#"{"sver":"v3","entries":[]}"#, "v1", 0).is_err());
+        assert!(decode("camd", r#"{"sver":"v2","entries":[{"path":[]}]}"#, "v1", 0).is_err());
+    }
+
+    #[test]
+    fn encode_1() {
+        let mut record = CacheRecord::new(CacheEntry::Upath(vec![11, 1, 1, 3, 3]), "local");
+        record.learned = 100;
+        record.n_ok = 3;
+        record.n_fail = 1;
+        record.last_ok = 200;
+
+        let encoded = encode(&[record.clone()]);
+        assert_eq!(
+            encoded,
+            r#"{"sver":"v2","entries":[{"path":[11,1,1,3,3],"learned":100,"probe":"local","ok":3,"fail":1,"streak":0,"last_ok":200}]}"#
+        );
+        assert_eq!(
+            decode("camd", &encoded, "v1", 0).expect("buggy"),
+            vec![record]
+        );
+
+        // migrated v1 is stable
+        let migrated = encode(&decode("camd", "[11,1,1,3,3]\n-", "v1", 0).expect("buggy"));
+        assert_eq!(
+            decode("camd", &migrated, "v1", 0).expect("buggy")[0].probe,
+            "v1"
+        );
+    }
+
+    #[test]
+    fn rank_1() {
+        let record_of = |n: u8, streak: u32, last_ok: u64| {
+            let mut record = CacheRecord::new(CacheEntry::Upath(vec![n]), "local");
+            record.n_fail_streak = streak;
+            record.last_ok = last_ok;
+            record
+        };
+        let order_of = |records: &[CacheRecord]| {
+            records
+                .iter()
+                .map(|r| r.entry.to_line())
+                .collect::<Vec<_>>()
+        };
+
+        // recently succeeded first, never succeeded ones keep their order
+        let mut records = vec![
+            record_of(1, 0, 0),
+            record_of(2, 0, 200),
+            record_of(3, 0, 0),
+            record_of(4, 0, 300),
+        ];
+        rank(&mut records);
+        assert_eq!(order_of(&records), vec!["[4]", "[2]", "[1]", "[3]"]);
+
+        // failing ones last, repeatedly failing ones dropped
+        let mut records = vec![
+            record_of(1, 1, 300),
+            record_of(2, PRUNE_AFTER_FAILS, 400),
+            record_of(3, 0, 100),
+        ];
+        rank(&mut records);
+        assert_eq!(order_of(&records), vec!["[3]", "[1]"]);
+
+        // the best one is kept anyway
+        let mut records = vec![
+            record_of(1, PRUNE_AFTER_FAILS + 1, 300),
+            record_of(2, PRUNE_AFTER_FAILS, 100),
+        ];
+        rank(&mut records);
+        assert_eq!(order_of(&records), vec!["[2]"]);
+    }
+
+    #[test]
+    fn sha256_hex_1() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    fn remote_dir(tag: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mafa-utst-{}-{}", tag, std::process::id()));
+        std::fs::create_dir_all(&dir).expect("buggy");
+        let camd = "[11,1,1,3,3]\n-";
+        std::fs::write(dir.join("camd"), camd).expect("buggy");
+        std::fs::write(dir.join("twtl"), "[[2,0],[0,1]]\n-").expect("buggy");
+        std::fs::write(
+            dir.join(MANIFEST_NAME),
+            format!(
+                "{}  camd\n{}  twtl\n",
+                sha256_hex(camd.as_bytes()),
+                "0".repeat(64)
+            ),
+        )
+        .expect("buggy");
+
+        dir
+    }
+
+    #[test]
+    fn fetch_remote_1() {
+        let dir = remote_dir("remote1");
+        let base = format!("file://{}", dir.display());
+        let manifest = format!("{}/{}", base, MANIFEST_NAME);
+        let no_browser = |_: &str| -> Result<String> { Err(MafaError::Bug("no browser")) };
+
+        assert_eq!(
+            fetch_remote(&base, &manifest, "camd", no_browser).expect("buggy"),
+            "[11,1,1,3,3]\n-"
+        );
+        assert!(matches!(
+            fetch_remote(&base, &manifest, "twtl", no_browser),
+            Err(MafaError::RemoteCacheRejected(_))
+        ));
+        assert!(matches!(
+            fetch_remote(&base, &manifest, "gtrans", no_browser),
+            Err(MafaError::RemoteCacheNotReachable(_))
+        ));
+        assert!(matches!(
+            fetch_remote("ftp://example.com", &manifest, "camd", no_browser),
+            Err(MafaError::InvalidCacheRemote)
+        ));
+        // the repository's own manifest is not consulted
+        assert!(matches!(
+            fetch_remote(&base, &format!("{}/nope", base), "camd", no_browser),
+            Err(MafaError::RemoteManifestMissing(_))
+        ));
+        // without a manifest, only well-formedness is checked, e.g., the
+        // manifest itself is not a cache
+        assert_eq!(
+            fetch_remote(&base, "", "camd", no_browser).expect("buggy"),
+            "[11,1,1,3,3]\n-"
+        );
+        assert!(matches!(
+            fetch_remote(&base, "", MANIFEST_NAME, no_browser),
+            Err(MafaError::RemoteCacheRejected(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).expect("buggy");
+    }
+
+    #[test]
+    fn fetch_remote_2() {
+        let dir = remote_dir("remote2");
+        let manifest = format!("file://{}/{}", dir.display(), MANIFEST_NAME);
+
+        // a local mirror, fetched through the browser
+        let srv_dir = dir.clone();
+        let via_browser = move |url: &str| -> Result<String> {
+            let name = url
+                .strip_prefix("http://127.0.0.1:8000/mirror/")
+                .expect("buggy");
+            Ok(std::fs::read_to_string(srv_dir.join(name)).expect("buggy"))
+        };
+        assert_eq!(
+            fetch_remote(
+                "http://127.0.0.1:8000/mirror/",
+                &manifest,
+                "camd",
+                &via_browser
+            )
+            .expect("buggy"),
+            "[11,1,1,3,3]\n-"
+        );
+
+        // a page that is not a manifest, e.g., 404 of the browser
+        assert!(matches!(
+            fetch_remote(
+                "http://127.0.0.1:8000/mirror/",
+                "https://example.com/SHA256SUMS",
+                "camd",
+                |_| Ok("404: Not Found".to_string())
+            ),
+            Err(MafaError::RemoteManifestMissing(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).expect("buggy");
+    }
+
+    #[test]
+    fn parse_exported_1() {
+        let all_cache = parse_exported(
+            r#"{"camd":[[11,1,1,3,3]],"twtl":[{"path":[[2,0],[0,1]],"probe":"local","ok":2}]}"#,
+        )
+        .expect("buggy");
+        assert_eq!(
+            all_cache["camd"][0].entry,
+            CacheEntry::Upath(vec![11, 1, 1, 3, 3])
+        );
+        assert_eq!(all_cache["camd"][0].probe, "import");
+        assert_eq!(all_cache["twtl"][0].probe, "local");
+        assert_eq!(all_cache["twtl"][0].n_ok, 2);
+
+        assert!(parse_exported(r#"{"camd":[[]]}"#).is_err());
+        assert!(parse_exported(r#"{"unknown":[[1]]}"#).is_err());
+        assert!(parse_exported("not json").is_err());
+    }
+}
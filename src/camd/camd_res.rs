@@ -12,6 +12,7 @@ use unicode_width::UnicodeWidthStr;
 
 use crate::comm;
 use crate::error::Result;
+use crate::theme;
 use crate::MafaError;
 
 const LABELS: [&str; 67] = [
@@ -84,10 +85,11 @@ const LABELS: [&str; 67] = [
     "[as form of address]",
 ];
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
 pub struct DefaultExpl<'a> {
     is_interme: bool,
     is_busi: bool,
+    #[serde(serialize_with = "ser_pronun")]
     pronun: &'a str,
     expls: Vec<Expl<'a>>,
 }
@@ -96,21 +98,20 @@ impl DefaultExpl<'_> {
     fn pretty_print(&self, nocolor: bool, asciiful: bool, wrap_width: usize) -> Result<String> {
         let mut output = String::default();
 
+        let theme = theme::current(nocolor);
+        let bar = if asciiful { "---" } else { "───" };
+
         let mut part_hdl = String::default();
         if self.is_interme {
-            part_hdl += if nocolor { "" } else { "\x1b[31;1m" };
-            part_hdl += if asciiful { "---" } else { "───" };
-            part_hdl += " I N T E R M E D I A T E ";
-            part_hdl += if asciiful { "---" } else { "───" };
-            part_hdl += if nocolor { "" } else { "\x1b[0m" };
+            part_hdl += &theme
+                .section
+                .paint(&format!("{} I N T E R M E D I A T E {}", bar, bar));
             part_hdl += "\n";
         }
         if self.is_busi {
-            part_hdl += if nocolor { "" } else { "\x1b[31;1m" };
-            part_hdl += if asciiful { "---" } else { "───" };
-            part_hdl += " B U S I N E S S ";
-            part_hdl += if asciiful { "---" } else { "───" };
-            part_hdl += if nocolor { "" } else { "\x1b[0m" };
+            part_hdl += &theme
+                .section
+                .paint(&format!("{} B U S I N E S S {}", bar, bar));
             part_hdl += "\n";
         }
         part_hdl += "\n";
@@ -137,31 +138,24 @@ impl DefaultExpl<'_> {
         let extracted = Self::extract_pronun(s);
         let mut ret = String::default();
 
-        let prefix_us = if nocolor {
-            "/US "
-        } else {
-            "/\x1b[36;1mUS\x1b[0m "
-        };
-        let prefix_uk = if nocolor {
-            "/UK "
-        } else {
-            "/\x1b[35;1mUK\x1b[0m "
-        };
+        let theme = theme::current(nocolor);
+        let prefix_us = format!("/{} ", theme.pronun_us.paint("US"));
+        let prefix_uk = format!("/{} ", theme.pronun_uk.paint("UK"));
 
         match extracted {
             (Some(us), Some(uk), None) => {
-                ret += prefix_us;
+                ret += &prefix_us;
                 ret += &us[1..];
                 ret += "  ";
-                ret += prefix_uk;
+                ret += &prefix_uk;
                 ret += &uk[1..];
             }
             (Some(us), None, None) => {
-                ret += prefix_us;
+                ret += &prefix_us;
                 ret += &us[1..];
             }
             (None, Some(uk), None) => {
-                ret += prefix_uk;
+                ret += &prefix_uk;
                 ret += &uk[1..];
             }
             (None, None, Some(unknown)) => {
@@ -215,17 +209,16 @@ impl DefaultExpl<'_> {
 //     expls: Vec<Expl<'a>>,
 // }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
 pub struct RealExamp<'a>(Vec<Examp<'a>>);
 impl RealExamp<'_> {
     fn pretty_print(&self, nocolor: bool, asciiful: bool, wrap_width: usize) -> Result<String> {
         let mut output = String::default();
 
-        output += if nocolor { "" } else { "\x1b[31;1m" };
-        output += if asciiful { "---" } else { "───" };
-        output += " E X A M P L E S ";
-        output += if asciiful { "---" } else { "───" };
-        output += if nocolor { "" } else { "\x1b[0m" };
+        let bar = if asciiful { "---" } else { "───" };
+        output += &theme::current(nocolor)
+            .section
+            .paint(&format!("{} E X A M P L E S {}", bar, bar));
         output += "\n";
         output += "\n";
 
@@ -238,10 +231,13 @@ impl RealExamp<'_> {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
 struct Expl<'a> {
+    #[serde(serialize_with = "ser_readable_opt")]
     nv_cate: Option<&'a str>,
+    #[serde(serialize_with = "ser_readable")]
     meaning: &'a str,
+    #[serde(serialize_with = "ser_readable_seq")]
     usages: Vec<&'a str>,
 }
 impl Expl<'_> {
@@ -252,9 +248,9 @@ impl Expl<'_> {
         let mut part_meaning = "".to_string();
         part_meaning += if asciiful { "* " } else { "✪ " };
         let w_leading = UnicodeWidthStr::width(part_meaning.as_str());
-        part_meaning += if nocolor { "" } else { "\x1b[1m" };
-        part_meaning += &comm::make_readable(self.meaning);
-        part_meaning += if nocolor { "" } else { "\x1b[0m" };
+        part_meaning += &theme::current(nocolor)
+            .emphasis
+            .paint(&comm::make_readable(self.meaning));
         let mut wrapper = bwrap::EasyWrapper::new(&part_meaning, wrap_width - w_leading).unwrap();
         let txt_leading = comm::replicate(" ", w_leading);
         let wrapped_part_meaning = wrapper
@@ -296,8 +292,9 @@ impl Expl<'_> {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
 struct Examp<'a> {
+    #[serde(serialize_with = "ser_readable")]
     usage: &'a str,
     from: &'a str,
 }
@@ -308,9 +305,7 @@ impl Examp<'_> {
         let mut part_a_usage = String::default();
         part_a_usage += "- ";
         let w_leading = UnicodeWidthStr::width(part_a_usage.as_str());
-        part_a_usage += if nocolor { "" } else { "\x1b[1m" };
-        part_a_usage += self.from;
-        part_a_usage += if nocolor { "" } else { "\x1b[0m" };
+        part_a_usage += &theme::current(nocolor).emphasis.paint(self.from);
         part_a_usage += ": ";
         part_a_usage += &comm::make_readable(self.usage);
 
@@ -555,17 +550,10 @@ impl<'a> LevelExpained<'a> {
             LevelExpained::DefaultKind(expl, s) => {
                 let pres = expl.pretty_print(nocolor, asciiful, wrap_width)?;
                 if pres.trim().len() == 0 {
-                    output += if nocolor { "" } else { "\x1b[31;1m" };
-                    output += "\n<UNSUPPORTED>\n";
-                    output += if nocolor { "" } else { "\x1b[0m" };
-
-                    output += if nocolor { "" } else { "\x1b[33m" };
-                    output += &comm::make_printable(s);
-                    output += if nocolor { "" } else { "\x1b[0m" };
-
-                    output += if nocolor { "" } else { "\x1b[31;1m" };
-                    output += "\n<UNSUPPORTED>\n";
-                    output += if nocolor { "" } else { "\x1b[0m" };
+                    let theme = theme::current(nocolor);
+                    output += &theme.error.paint("\n<UNSUPPORTED>\n");
+                    output += &theme.note.paint(&comm::make_printable(s));
+                    output += &theme.error.paint("\n<UNSUPPORTED>\n");
                 } else {
                     output += &pres;
                 }
@@ -579,9 +567,47 @@ impl<'a> LevelExpained<'a> {
     }
 }
 
+impl serde::Serialize for LevelExpained<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        match self {
+            LevelExpained::DefaultKind(expl, _) => {
+                let mut st = serializer.serialize_struct("level", 2)?;
+                st.serialize_field("kind", "default")?;
+                st.serialize_field("explained", expl)?;
+                st.end()
+            }
+            LevelExpained::RealExampKind(examps) => {
+                let mut st = serializer.serialize_struct("level", 2)?;
+                st.serialize_field("kind", "examples")?;
+                st.serialize_field("examples", examps)?;
+                st.end()
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default, PartialEq)]
 pub struct CamdResult<'w, 's>(&'w str, Vec<LevelExpained<'s>>);
 
+impl serde::Serialize for CamdResult<'_, '_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut st = serializer.serialize_struct("result", 2)?;
+        st.serialize_field("word", self.0)?;
+        st.serialize_field("levels", &self.1)?;
+        st.end()
+    }
+}
+
 impl<'w, 's> CamdResult<'w, 's> {
     pub fn from_str(word: &'w str, s: &'s str) -> Result<Self> {
         let bytes = s.as_bytes();
@@ -637,10 +663,11 @@ impl<'w, 's> CamdResult<'w, 's> {
             format!(" RESULT │")
         };
 
+        let theme = theme::current(nocolor);
         let header_part_colorful = if asciiful {
-            format!(" \x1b[36;1mRESULT\x1b[0m |")
+            format!(" {} |", theme.header.paint("RESULT"))
         } else {
-            format!(" \x1b[36;1mRESULT\x1b[0m │")
+            format!(" {} │", theme.header.paint("RESULT"))
         };
 
         // dbgg!(&header_part);
@@ -662,11 +689,7 @@ impl<'w, 's> CamdResult<'w, 's> {
         output += line_tail_comp.0;
         output += "\n";
 
-        output += if nocolor {
-            &header_part
-        } else {
-            &header_part_colorful
-        };
+        output += &header_part_colorful;
         output += "\n";
 
         // bottom line
@@ -701,6 +724,57 @@ impl<'w, 's> CamdResult<'w, 's> {
     }
 }
 
+// serde helpers //
+
+fn ser_readable<S: serde::Serializer>(
+    s: &&str,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&comm::make_readable(s))
+}
+
+fn ser_readable_opt<S: serde::Serializer>(
+    s: &Option<&str>,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    match s {
+        Some(v) => serializer.serialize_some(&comm::make_readable(v)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[allow(clippy::ptr_arg)]
+fn ser_readable_seq<S: serde::Serializer>(
+    s: &Vec<&str>,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(s.iter().map(|v| comm::make_readable(v)))
+}
+
+///
+/// pronunciations are serialized with slashes stripped, e.g.
+/// `{"us":"ˈhæp.i","uk":null,"unknown":null}`.
+fn ser_pronun<S: serde::Serializer>(
+    s: &&str,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    #[derive(serde::Serialize)]
+    struct Pronun<'a> {
+        us: Option<&'a str>,
+        uk: Option<&'a str>,
+        unknown: Option<&'a str>,
+    }
+
+    let (us, uk, unknown) = DefaultExpl::extract_pronun(s);
+    let pronun = Pronun {
+        us: us.map(|v| v.trim_matches('/')),
+        uk: uk.map(|v| v.trim_matches('/')),
+        unknown: unknown.filter(|v| !v.trim().is_empty()),
+    };
+
+    serde::Serialize::serialize(&pronun, serializer)
+}
+
 #[cfg(test)]
 mod tst {
     use super::*;
@@ -1267,4 +1341,47 @@ mod tst {
             (None, Some("/wɝːld/"), None)
         );
     }
+
+    #[test]
+    fn json_1() {
+        let expl = DefaultExpl {
+            pronun: "US  /wɝːld/ UK  /wɜːld/",
+            expls: vec![Expl {
+                nv_cate: None,
+                meaning: "the earth and all the people, places, and things on it:",
+                usages: vec![r#"He said \"hello\" to the world."#],
+            }],
+            ..Default::default()
+        };
+        let camd_res = CamdResult(
+            "world",
+            vec![
+                LevelExpained::DefaultKind(expl, ""),
+                LevelExpained::RealExampKind(RealExamp(vec![Examp {
+                    usage: "The world is big.",
+                    from: "From NPR",
+                }])),
+            ],
+        );
+
+        assert_eq!(
+            serde_json::to_string(&camd_res).expect("bug"),
+            r#"{"word":"world","levels":[{"kind":"default","explained":{"is_interme":false,"is_busi":false,"pronun":{"us":"wɝːld","uk":"wɜːld","unknown":null},"expls":[{"nv_cate":null,"meaning":"the earth and all the people, places, and things on it:","usages":["He said \"hello\" to the world."]}]}},{"kind":"examples","examples":[{"usage":"The world is big.","from":"From NPR"}]}]}"#
+        );
+    }
+
+    #[test]
+    fn xml_1() {
+        let camd_res = CamdResult(
+            "world",
+            vec![LevelExpained::RealExampKind(RealExamp(vec![Examp {
+                usage: "The world is big.",
+                from: "From NPR",
+            }]))],
+        );
+
+        let xml = quick_xml::se::to_string_with_root("result", &camd_res).expect("bug");
+        assert!(xml.starts_with("<result><word>world</word><levels><kind>examples</kind>"));
+        assert!(xml.contains("<usage>The world is big.</usage><from>From NPR</from>"));
+    }
 }
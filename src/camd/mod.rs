@@ -8,37 +8,41 @@
 //
 
 use std::borrow::Cow;
+use std::fs::OpenOptions;
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::thread::sleep;
 use std::time::Duration;
 use unicode_width::UnicodeWidthStr;
 
-use wda::BasicAutomation;
 use wda::WdaError;
-use wda::WdaSett;
-use wda::WebDrvAstn;
-
-use wda::GeckoDriver;
 use wda::WdcError;
 
 use crate::error::CacheRebuildFailKind;
 use crate::error::MafaError;
 use crate::error::Result;
 
+use crate::backend::BrowserBackend;
 use crate::mafadata::MafaData;
 use crate::MafaClient;
 
+use crate::cache;
+use crate::cache::CacheEntry;
+use crate::cancel;
+use crate::retry;
+
 use crate::ev_ntf::Category;
 use crate::ev_ntf::EurKind;
 use crate::ev_ntf::EventNotifier;
 use crate::ev_ntf::MafaEvent;
 
 use crate::MafaInput;
+use crate::OutputFormat;
 
 use crate::comm;
 use crate::comm::CacheMechanism;
+use crate::config;
 
 mod camd_res;
 use camd_res::CamdResult;
@@ -48,12 +52,42 @@ use clap::ArgAction as ClapArgAction;
 use clap::ArgMatches as ClapArgMatches;
 use clap::Command as ClapCommand;
 
+#[derive(Debug, Default, Copy, Clone)]
+pub enum SaveFormat {
+    #[default]
+    Json,
+    Xml,
+}
+
+impl SaveFormat {
+    fn from_str(s: &str) -> Self {
+        match s {
+            "JSON" | "json" => Self::Json,
+            "XML" | "xml" => Self::Xml,
+            _ => Self::Json,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct CamdInput {
     words: String,
+    save_to: Option<PathBuf>,
+    save_format: Option<SaveFormat>,
 }
 
 impl CamdInput {
+    ///
+    /// Key of the stored result, words differing only in whitespaces and
+    /// cases share a result.
+    pub fn result_key(&self) -> String {
+        self.words
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase()
+    }
+
     pub fn from_ca_matched(ca_matched: &ClapArgMatches) -> Result<Self> {
         let mut camd_in = CamdInput::default();
 
@@ -71,6 +105,16 @@ impl CamdInput {
             camd_in.words = words;
         }
 
+        // save-format
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::SaveFormat::id()) {
+            camd_in.save_format = Some(SaveFormat::from_str(optval));
+        }
+
+        // save-to
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::SaveTo::id()) {
+            camd_in.save_to = Some(ensure_save_to(optval, camd_in.save_format)?);
+        }
+
         dbgg!(&camd_in);
 
         Ok(camd_in)
@@ -133,6 +177,72 @@ $ mafa Camd thank you"#;
             String::from_utf8_lossy(&af_buf[0..rl]).to_string()
         }
     }
+
+    pub struct SaveTo;
+    impl SaveTo {
+        #[inline]
+        pub fn id() -> &'static str {
+            "SAVE_TO"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "save-to"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "The file to save the definitions"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"The file to save the definitions
+
+Any path is allowed as long as it is a valid path on disk, and file extension is not significant. Saved data includes all levels of the definitions, i.e., pronunciations, meanings, usages and real examples.
+
+NOTE: in case of any non-existing directory found, this will fallback to "./camd-saved.<SAVE_FORMAT>"."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct SaveFormat;
+    impl SaveFormat {
+        #[inline]
+        pub fn id() -> &'static str {
+            "SAVE_FORMAT"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "save-format"
+        }
+        #[inline]
+        pub fn def_val() -> &'static str {
+            "json"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Format of saved definitions"
+        }
+        #[inline]
+        pub fn long_helper() -> &'static str {
+            r#"Format of saved definitions
+
+Available values are: json, xml."#
+        }
+    }
 }
 
 pub fn get_cmd() -> ClapCommand {
@@ -145,9 +255,38 @@ pub fn get_cmd() -> ClapCommand {
             .long_help(O::long_helper())
     };
 
+    let opt_saveto = {
+        type O = opts::SaveTo;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("camd"),
+            O::longopt(),
+        )
+    };
+
+    let opt_saveformat = {
+        type O = opts::SaveFormat;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("camd"),
+            O::longopt(),
+        )
+    };
+
     let cmd_camd = ClapCommand::new("camd")
         .about("Word definition by Cambridge Dictionary")
-        .arg(opt_words);
+        .arg(opt_words)
+        .arg(opt_saveto)
+        .arg(opt_saveformat);
 
     cmd_camd
 }
@@ -155,41 +294,35 @@ pub fn get_cmd() -> ClapCommand {
 #[derive(Debug, Default)]
 pub struct Upath(Vec<u8>);
 
-#[derive(Debug, Default)]
-struct UpathCache(Vec<Upath>);
-
-impl UpathCache {
-    fn from_pbuf(pb: PathBuf) -> Result<Self> {
-        let rawdata = std::fs::read_to_string(&pb).unwrap(); // FIXME: no unwrap
-
-        let mut ret = vec![];
-
-        let bytes = rawdata.as_bytes();
+fn ensure_save_to(s: &str, save_fmt: Option<SaveFormat>) -> Result<PathBuf> {
+    let pbuf = PathBuf::from(s);
 
-        let mut previ;
-        let mut curri = 0;
+    let try_open = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&pbuf);
 
-        let mut caches = Vec::<&[u8]>::new();
+    let default_saved = match save_fmt.unwrap_or_default() {
+        SaveFormat::Json => "camd-saved.json",
+        SaveFormat::Xml => "camd-saved.xml",
+    };
 
-        for i in 0..bytes.len() {
-            if bytes[i] == 0xa {
-                previ = if curri == 0 { curri } else { curri + 1 };
-                curri = i;
-                caches.push(&bytes[previ..curri]);
-            }
+    match try_open {
+        Ok(_) => {
+            dbgmsg!("path good, whether file exist or not");
+            Ok(pbuf)
         }
-
-        for ele in caches {
-            let deser = serde_json::from_slice::<Vec<u8>>(ele).expect("buggy");
-            if deser.len() == 0 {
-                return Err(MafaError::CacheCorrupted);
+        Err(err_io) => match err_io.kind() {
+            std::io::ErrorKind::NotFound => {
+                dbgmsg!("path bad");
+                Ok(PathBuf::from(default_saved))
             }
-            let mut nr = Upath::default();
-            nr.0 = deser;
-            ret.push(nr);
-        }
-
-        Ok(Self(ret))
+            _ => {
+                dbgmsg!("io bad {:?}", err_io);
+                Err(MafaError::Io(pbuf, err_io))
+            }
+        },
     }
 }
 
@@ -201,8 +334,13 @@ fn is_valid_words(v: &str) -> bool {
     }
 }
 
-impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
-    fn upath_locate(&self, words: &str, expl: &str, wait_before_extract: u64) -> Result<Vec<u8>> {
+impl<'a, 'b, 'c, B: BrowserBackend> MafaClient<'a, 'b, 'c, CamdInput, Upath, B> {
+    fn upath_locate(
+        &self,
+        words: &str,
+        expl: &str,
+        wait_before_extract: Duration,
+    ) -> Result<Vec<u8>> {
         let url = format!(
             "https://dictionary.cambridge.org/us/dictionary/english/{}",
             words
@@ -217,7 +355,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
             }
         }
 
-        sleep(Duration::from_millis(wait_before_extract));
+        cancel::sleep(wait_before_extract)?;
 
         // script/camd-upath.js, CHECKED
         let js_in="console.log=function(){};function locate_elem(e){var o=[];function l(e,n,t){let c=e.childNodes.length;for(let d=0;d<c;d++){let c=e.childNodes[d];if(c.innerText&&c.innerText==n){console.log('yes',c);o=[...t,d]}else{l(c,n,[...t,d])}}}let n=e;l(document.body,n,[]);console.log(o);let t=o.map((()=>document.body));console.log(t);for(let e=0;e<o.length;e++){for(let l=0;l<o[e].length;l++){t[e]=t[e].childNodes[o[e][l]]}}return o}return locate_elem(arguments[0]);";
@@ -245,7 +383,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
     fn notify(&self, ev: MafaEvent) -> Result<()> {
         self.ntf
             .lock()
-            .map_err(|_| MafaError::BugFound(7890))?
+            .map_err(|_| MafaError::Bug("lock poisoned"))?
             .notify(ev);
 
         Ok(())
@@ -253,21 +391,23 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
 
     fn rebuild_internal(&mut self, is_rebuild: bool) -> Result<()> {
         if !is_rebuild {
-            let caches_from_files = UpathCache::from_pbuf(self.mafad.pathto_exist_cache("camd")?)?;
-            self.caches = caches_from_files.0;
+            self.caches = cache::load(self.mafad, "camd")?
+                .into_iter()
+                .map(|record| record.entry.into_upath().map(Upath))
+                .collect::<Result<Vec<Upath>>>()?;
             return Ok(());
         }
 
         let mut upath1: Option<Vec<u8>> = None;
         let mut upath2: Option<Vec<u8>> = None;
 
-        // let mut time_before = 50; // test purpose
-        let mut time_before = 500; // in millis
+        let mut attempts = self.input.retry.apply(retry::CACHE_BUILD).begin();
+
+        while attempts.remain() {
+            cancel::check()?;
 
-        // let mut try_times = 3; // test purpose
-        let mut try_times = 5;
+            let time_before = attempts.delay();
 
-        while try_times > 0 {
             if upath1.is_none() {
                 match self.upath_locate(
                     "hello",
@@ -329,13 +469,12 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
                     cate: Category::Camd,
                     is_fin: false,
                 })?;
-                try_times -= 1;
-                time_before += time_before;
-                dbgmsg!("need retry {} {}", try_times, time_before);
+                attempts.backoff();
+                dbgmsg!("need retry {} {:?}", attempts.left(), time_before);
             }
         }
 
-        dbgg!(&try_times);
+        dbgg!(&attempts);
 
         // all must be present
         if upath1.is_none() || upath2.is_none() {
@@ -368,54 +507,18 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
             res
         };
 
-        let u_part = serde_json::to_string(&upath1[0..matched_len]).unwrap();
-        let comb = format!("{}\n", u_part);
-        dbgg!(&comb);
-
-        self.mafad
-            .cache_append("camd", &comb, &format!("{}-", &comb))?;
+        cache::learn(
+            self.mafad,
+            "camd",
+            CacheEntry::Upath(Vec::from(&upath1[0..matched_len])),
+            "hello world",
+        )?;
 
         self.caches.push(Upath(Vec::from(&upath1[0..matched_len])));
 
         Ok(())
     }
 
-    fn cache_on_gh(&self, url: &str) -> Result<String> {
-        match self.wda.go_url(url) {
-            Ok(_) => {}
-            Err(err_navi) => {
-                if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_navi {
-                    dbgg!(123);
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_navi));
-                }
-            }
-        }
-
-        let jsout: String;
-        let jsin = "return document.getElementsByTagName('pre')[0].innerText;";
-
-        match self.wda.eval(&jsin, vec![]) {
-            Ok(ret) => jsout = ret,
-            Err(err_eval) => {
-                if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_eval {
-                    dbgg!(123);
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_eval));
-                }
-            }
-        }
-
-        // let jsout = jsout.replace("\"", "").replace("\\n", "\n");
-        let jsout = jsout.replace('"', "").replace("\\n", "\n");
-
-        dbgg!(&jsout);
-
-        Ok(jsout)
-    }
-
     ///
     /// Try to rebuild cache regarding cache mechanism, built cache will be
     /// put in dedicated file on disk, typically inside $HOME/.mafa. Default to
@@ -424,12 +527,16 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
         let mut is_rebuild = false;
 
         if let CacheMechanism::Remote = self.input.cachm {
-            let remote_data = self
-                .cache_on_gh("https://raw.githubusercontent.com/micl2e2/mafa-cache/master/camd")?;
-
-            self.mafad.init_cache("camd", &remote_data)?;
+            let remote_data = cache::fetch_remote(
+                &self.input.cache_remote,
+                &self.input.cache_manifest,
+                "camd",
+                |url| cache::fetch_via_browser(self.wda, url),
+            )?;
+
+            cache::init(self.mafad, "camd", &remote_data, "remote")?;
         } else if let CacheMechanism::Local = self.input.cachm {
-            self.mafad.try_init_cache("camd", "[11,1,1,3,3]\n-")?;
+            cache::try_init(self.mafad, "camd", "[11,1,1,3,3]\n-", "local")?;
         } else if let CacheMechanism::No = self.input.cachm {
             is_rebuild = true;
         }
@@ -454,43 +561,119 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
     ///
     /// Returned `String` is pretty-printed.
     pub fn handle(&mut self, pred_caches: Option<Vec<Vec<u8>>>) -> Result<(EurKind, String)> {
-        // caches
-        if pred_caches.is_none() {
-            self.try_rebuild_cache()?;
-        } else {
-            let pred_caches = pred_caches.ok_or(MafaError::BugFound(4567))?;
-            pred_caches
-                .iter()
-                .for_each(|v| self.caches.push(Upath(v.clone())));
-        }
-        if self.caches.len() == 0 {
-            panic!("buggy");
+        let key = self.sub_input.result_key();
+        let stored = self.stored_result("camd", &key)?;
+
+        // caches, a stored result needs none
+        if stored.is_none() && !self.input.offline {
+            if pred_caches.is_none() {
+                self.try_rebuild_cache()?;
+            } else {
+                let pred_caches = pred_caches.ok_or(MafaError::Bug("caches not loaded"))?;
+                pred_caches
+                    .iter()
+                    .for_each(|v| self.caches.push(Upath(v.clone())));
+            }
+            if self.caches.is_empty() {
+                panic!("buggy");
+            }
         }
 
-        self.notify(MafaEvent::FetchResult {
-            cate: Category::Camd,
-            is_fin: false,
-        })?;
-        let explained = self.fetch(&self.sub_input.words)?;
-        self.notify(MafaEvent::FetchResult {
-            cate: Category::Camd,
-            is_fin: true,
-        })?;
+        let explained = match stored {
+            Some(v) => v,
+            None => {
+                self.notify(MafaEvent::FetchResult {
+                    cate: Category::Camd,
+                    is_fin: false,
+                })?;
+                let explained = self.fetch(&self.sub_input.words)?;
+                self.notify(MafaEvent::FetchResult {
+                    cate: Category::Camd,
+                    is_fin: true,
+                })?;
+
+                self.store_result("camd", &key, &explained);
+
+                explained
+            }
+        };
 
         dbgg!((&self.sub_input.words, &explained));
         let camd_res = CamdResult::from_str(&self.sub_input.words, &explained)?;
         dbgg!(&camd_res);
 
+        self.try_save_result(&camd_res)?;
+
+        if let OutputFormat::Json = self.input.output {
+            return Ok((
+                EurKind::CamdResult,
+                serde_json::to_string(&camd_res).map_err(MafaError::Json)?,
+            ));
+        }
+
         Ok((
             EurKind::CamdResult,
             camd_res.pretty_print(
                 self.input.nocolor,
                 self.input.ascii,
-                self.input.wrap_width.into(),
+                self.input.wrap_width_now().into(),
             )?,
         ))
     }
 
+    fn try_save_result(&self, camd_res: &CamdResult) -> Result<()> {
+        let save_to = match &self.sub_input.save_to {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let wbuf = match self.sub_input.save_format.unwrap_or_default() {
+            SaveFormat::Json => serde_json::to_string(camd_res).map_err(MafaError::Json)?,
+            SaveFormat::Xml => quick_xml::se::to_string_with_root("result", camd_res)
+                .map_err(|_| MafaError::Bug("result not encodable as XML"))?,
+        };
+
+        let mut outf = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(save_to)
+            .map_err(|err_io| MafaError::Io(PathBuf::from(save_to), err_io))?;
+        outf.write_all(wbuf.as_bytes())
+            .map_err(|err_io| MafaError::Io(PathBuf::from(save_to), err_io))?;
+
+        Ok(())
+    }
+
+    ///
+    /// Rebuild the cache by visiting the website, regardless of the cache
+    /// mechanism, the rebuilt entry goes first. Returns the number of
+    /// entries in use.
+    pub fn rebuild_cache(&mut self) -> Result<usize> {
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Camd,
+            is_fin: false,
+        })?;
+        self.rebuild_internal(true)?;
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Camd,
+            is_fin: true,
+        })?;
+
+        self.rebuild_internal(false)?;
+
+        Ok(self.caches.len())
+    }
+
+    ///
+    /// not being recorded is not fatal
+    fn record_outcome(&self, upaths_i: usize, is_ok: bool) {
+        let entry = CacheEntry::Upath(self.caches[upaths_i].0.clone());
+        if let Err(_err_rec) = cache::record_outcome(self.mafad, "camd", &entry, is_ok) {
+            dbgg!(_err_rec);
+        }
+    }
+
     fn fetch(&self, words: &str) -> Result<String> {
         let url = format!(
             "https://dictionary.cambridge.org/us/dictionary/english/{}",
@@ -508,15 +691,14 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
 
         let mut is_url_reached = false;
 
-        // try_times = go_url + eval_js
-        let mut try_times = 5; // sufficient to let try again succeed
-
-        // let mut wait_before = 500;
-        let mut wait_before = 10; // time to wait after navi but before eval
+        // attempts = go_url + eval_js
+        let mut attempts = self.input.retry.apply(retry::CAMD_FETCH).begin();
 
         let mut expl_res = "".to_string();
 
-        while try_times > 0 {
+        while attempts.remain() {
+            cancel::check()?;
+
             if let Err(err_navi) = self.wda.go_url(&url) {
                 if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_navi {
                     if err.contains("timeout") {
@@ -524,7 +706,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
                             cate: Category::Camd,
                             is_fin: false,
                         })?;
-                        try_times -= 1;
+                        attempts.fail();
                         continue;
                     } else {
                         return Err(MafaError::WebDrvCmdRejected(err, msg));
@@ -543,17 +725,19 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
             let upath_curr = &self.caches[upaths_i].0;
             let arg0 = serde_json::to_string(&upath_curr[..]).unwrap();
 
-            sleep(Duration::from_millis(wait_before));
+            cancel::sleep(attempts.delay())?;
 
             match self.wda.eval_async(&jsin_getres, vec![&arg0]) {
                 Ok(retstr) => {
                     expl_res = retstr;
+                    self.record_outcome(upaths_i, true);
                     break; // we done
                 }
 
                 Err(err_eval) => {
                     if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_eval {
                         if err.contains("timeout") {
+                            self.record_outcome(upaths_i, false);
                             upaths_i += 1;
                             if upaths_i < upaths_len {
                                 self.notify(MafaEvent::TryNextCache {
@@ -577,9 +761,8 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, CamdInput, Upath> {
                 }
             }
 
-            try_times -= 1;
-            wait_before += wait_before;
-            dbgmsg!("need retry {} {}", try_times, wait_before);
+            attempts.backoff();
+            dbgmsg!("need retry {}", attempts.left());
         }
 
         self.notify(MafaEvent::TryNextCache {
@@ -634,4 +817,28 @@ mod utst_merged {
             Err(_) => assert!(false),
         }
     }
+
+    #[test]
+    fn save_1() {
+        let matched = crate::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "camd",
+                "--save-format",
+                "xml",
+                "--save-to",
+                "/path/not/existing/words.xml",
+                "hello",
+            ])
+            .expect("buggy");
+
+        match matched.subcommand() {
+            Some(("camd", sub_m)) => {
+                let camd_in = CamdInput::from_ca_matched(sub_m).expect("must ok");
+                assert!(matches!(camd_in.save_format, Some(SaveFormat::Xml)));
+                assert_eq!(camd_in.save_to, Some(PathBuf::from("camd-saved.xml")));
+            }
+            _ => panic!("buggy"),
+        }
+    }
 }
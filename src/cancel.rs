@@ -0,0 +1,115 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Cancelling the request in flight with Ctrl-C.
+//!
+//! Interactive mode turns SIGINT into a flag instead of dying, along with
+//! the browser. Retry loops and long waits check the flag, and give up
+//! with [`MafaError::Cancelled`] once it is set.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::error::MafaError;
+use crate::error::Result;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+// how often a cancellable sleep looks at the flag
+const CHECK_PERIOD: Duration = Duration::from_millis(100);
+
+extern "C" fn on_sigint(_signum: libc::c_int) {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+///
+/// Let processes spawned afterwards, i.e., the browser and its driver,
+/// ignore Ctrl-C. An ignored signal stays ignored across `exec`, while a
+/// handled one does not, so this must precede the spawning.
+pub fn shield_children() {
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+    }
+}
+
+///
+/// Turn Ctrl-C into the cancellation flag. Interrupted system calls are
+/// restarted, so that requests sent to the browser are not broken.
+pub fn install() {
+    unsafe {
+        let mut act: libc::sigaction = std::mem::zeroed();
+        act.sa_sigaction = on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        act.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut act.sa_mask);
+        libc::sigaction(libc::SIGINT, &act, std::ptr::null_mut());
+    }
+}
+
+///
+/// Forget Ctrl-C pressed so far, called before each request.
+pub fn reset() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        Err(MafaError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// Same as `std::thread::sleep`, but wakes up early on cancellation.
+pub fn sleep(dur: Duration) -> Result<()> {
+    let until = Instant::now() + dur;
+
+    loop {
+        check()?;
+
+        let now = Instant::now();
+        if now >= until {
+            return Ok(());
+        }
+        std::thread::sleep(CHECK_PERIOD.min(until - now));
+    }
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[test]
+    fn sleep_1() {
+        reset();
+        assert!(sleep(Duration::from_millis(10)).is_ok());
+
+        let since = Instant::now();
+        let th = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            CANCELLED.store(true, Ordering::SeqCst);
+        });
+        assert!(matches!(
+            sleep(Duration::from_secs(60)),
+            Err(MafaError::Cancelled)
+        ));
+        assert!(since.elapsed() < Duration::from_secs(5));
+        th.join().expect("buggy");
+
+        reset();
+        assert!(check().is_ok());
+    }
+}
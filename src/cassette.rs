@@ -0,0 +1,361 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Record and replay of browser interactions.
+//!
+//! A cassette is a directory holding `cassette.jsonl`, each line of which
+//! is one interaction with the browser, i.e., the operation, the URL
+//! navigated to or the arguments passed to the script, and the raw string
+//! returned. Replaying a cassette serves the recorded strings in the same
+//! order, without any browser.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use wda::WdaError;
+use wda::WdcError;
+
+use crate::backend::BackendResult;
+use crate::backend::BrowserBackend;
+use crate::error::MafaError;
+use crate::error::Result;
+
+const CASSETTE_FILE: &str = "cassette.jsonl";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Interaction {
+    op: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ret: Option<String>,
+    ///
+    /// (error, message) of the rejected command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rejected: Option<(String, String)>,
+}
+
+impl Interaction {
+    fn new(op: &str, url: Option<&str>, args: &[&str]) -> Self {
+        Interaction {
+            op: op.to_string(),
+            url: url.map(|v| v.to_string()),
+            args: args.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn set_outcome<T>(&mut self, outcome: &BackendResult<T>, f: impl Fn(&T) -> Option<String>) {
+        match outcome {
+            Ok(v) => self.ret = f(v),
+            Err(WdaError::WdcFail(WdcError::BadDrvCmd(err, msg))) => {
+                self.rejected = Some((err.clone(), msg.clone()))
+            }
+            Err(err_other) => {
+                self.rejected = Some(("unexpected".to_string(), format!("{:?}", err_other)))
+            }
+        }
+    }
+
+    fn outcome(&self) -> BackendResult<Option<String>> {
+        match &self.rejected {
+            Some((err, msg)) => Err(WdaError::WdcFail(WdcError::BadDrvCmd(
+                err.clone(),
+                msg.clone(),
+            ))),
+            None => Ok(self.ret.clone()),
+        }
+    }
+}
+
+fn replay_rejected(msg: String) -> WdaError {
+    WdaError::WdcFail(WdcError::BadDrvCmd("replay mismatched".to_string(), msg))
+}
+
+///
+/// Forwards every operation to the inner backend, and appends the
+/// interaction to the cassette, which starts empty on every recording.
+#[derive(Debug)]
+pub struct Recorder<'w, B> {
+    inner: &'w B,
+    outf: Mutex<File>,
+}
+
+impl<'w, B: BrowserBackend> Recorder<'w, B> {
+    pub fn new(inner: &'w B, dir: &str) -> Result<Self> {
+        let pbuf = PathBuf::from(dir);
+        std::fs::create_dir_all(&pbuf)
+            .map_err(|err_io| MafaError::InvalidCassetteDir(pbuf.clone(), err_io))?;
+
+        let pbuf = pbuf.join(CASSETTE_FILE);
+        let outf = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&pbuf)
+            .map_err(|err_io| MafaError::InvalidCassetteDir(pbuf, err_io))?;
+
+        Ok(Recorder {
+            inner,
+            outf: Mutex::new(outf),
+        })
+    }
+
+    fn record(&self, intr: &Interaction) {
+        // recording is best-effort, it never affects the result
+        if let Ok(mut outf) = self.outf.lock() {
+            if let Ok(line) = serde_json::to_string(intr) {
+                let _ = writeln!(outf, "{}", line);
+            }
+        }
+    }
+}
+
+impl<B: BrowserBackend> BrowserBackend for Recorder<'_, B> {
+    fn go_url(&self, url: &str) -> BackendResult<()> {
+        let ret = self.inner.go_url(url);
+        let mut intr = Interaction::new("go_url", Some(url), &[]);
+        intr.set_outcome(&ret, |_| None);
+        self.record(&intr);
+        ret
+    }
+
+    fn eval(&self, script: &str, args: Vec<&str>) -> BackendResult<String> {
+        let mut intr = Interaction::new("eval", None, &args);
+        let ret = self.inner.eval(script, args);
+        intr.set_outcome(&ret, |v| Some(v.clone()));
+        self.record(&intr);
+        ret
+    }
+
+    fn eval_async(&self, script: &str, args: Vec<&str>) -> BackendResult<String> {
+        let mut intr = Interaction::new("eval_async", None, &args);
+        let ret = self.inner.eval_async(script, args);
+        intr.set_outcome(&ret, |v| Some(v.clone()));
+        self.record(&intr);
+        ret
+    }
+
+    fn get_url(&self) -> BackendResult<String> {
+        let ret = self.inner.get_url();
+        let mut intr = Interaction::new("get_url", None, &[]);
+        intr.set_outcome(&ret, |v| Some(v.clone()));
+        self.record(&intr);
+        ret
+    }
+
+    fn page_src(&self, save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+        let ret = self.inner.page_src(save_to);
+        let mut intr = Interaction::new("page_src", None, &[]);
+        intr.set_outcome(&ret, |v| {
+            v.as_ref()
+                .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+        });
+        self.record(&intr);
+        ret
+    }
+}
+
+///
+/// Serves interactions of a cassette in recorded order. Any operation not
+/// matching the recorded one is rejected.
+#[derive(Debug)]
+pub struct Replayer {
+    intrs: Vec<Interaction>,
+    curr: Mutex<usize>,
+}
+
+impl Replayer {
+    pub fn new(dir: &str) -> Result<Self> {
+        let pbuf = PathBuf::from(dir).join(CASSETTE_FILE);
+        let rawdata = std::fs::read_to_string(&pbuf)
+            .map_err(|err_io| MafaError::InvalidCassetteDir(pbuf.clone(), err_io))?;
+
+        let mut intrs = vec![];
+        for line in rawdata.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let intr = serde_json::from_str::<Interaction>(line)
+                .map_err(|err_json| MafaError::InvalidCassetteDir(pbuf.clone(), err_json.into()))?;
+            intrs.push(intr);
+        }
+
+        Ok(Replayer {
+            intrs,
+            curr: Mutex::new(0),
+        })
+    }
+
+    fn next(&self, op: &str, url: Option<&str>) -> BackendResult<Option<String>> {
+        let mut curr = self
+            .curr
+            .lock()
+            .map_err(|_| replay_rejected("cassette poisoned".to_string()))?;
+
+        let intr = self
+            .intrs
+            .get(*curr)
+            .ok_or_else(|| replay_rejected(format!("cassette exhausted, expecting {}", op)))?;
+
+        if intr.op != op || (url.is_some() && intr.url.as_deref() != url) {
+            return Err(replay_rejected(format!(
+                "recorded {} {:?}, expecting {} {:?}",
+                intr.op, intr.url, op, url
+            )));
+        }
+
+        *curr += 1;
+
+        intr.outcome()
+    }
+}
+
+impl BrowserBackend for Replayer {
+    fn go_url(&self, url: &str) -> BackendResult<()> {
+        self.next("go_url", Some(url)).map(|_| ())
+    }
+
+    fn eval(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+        self.next("eval", None).map(|v| v.unwrap_or_default())
+    }
+
+    fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+        self.next("eval_async", None).map(|v| v.unwrap_or_default())
+    }
+
+    fn get_url(&self) -> BackendResult<String> {
+        self.next("get_url", None).map(|v| v.unwrap_or_default())
+    }
+
+    fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+        self.next("page_src", None)
+            .map(|may_v| may_v.map(|v| v.into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Canned;
+
+    impl BrowserBackend for Canned {
+        fn go_url(&self, url: &str) -> BackendResult<()> {
+            if url.contains("timeout") {
+                Err(WdaError::WdcFail(WdcError::BadDrvCmd(
+                    "timeout".to_string(),
+                    "page load".to_string(),
+                )))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn eval(&self, _script: &str, args: Vec<&str>) -> BackendResult<String> {
+            Ok(format!("\"{}\"", args.join(",")))
+        }
+
+        fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok("\"twtl_v1\\n...\"".to_string())
+        }
+
+        fn get_url(&self) -> BackendResult<String> {
+            Ok("https://example.com/".to_string())
+        }
+
+        fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+            Ok(Some(b"<html></html>".to_vec()))
+        }
+    }
+
+    #[test]
+    fn record_replay_1() {
+        let dir = std::env::temp_dir().join("mafa-cassette-record-replay-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_str().expect("bug");
+
+        {
+            let rec = Recorder::new(&Canned, dir).expect("bug");
+            assert!(rec.go_url("https://example.com/").is_ok());
+            assert_eq!(rec.eval("", vec!["a", "b"]).expect("bug"), "\"a,b\"");
+            assert_eq!(
+                rec.eval_async("", vec![]).expect("bug"),
+                "\"twtl_v1\\n...\""
+            );
+            assert!(rec.go_url("https://example.com/timeout").is_err());
+            assert_eq!(
+                rec.page_src(None).expect("bug"),
+                Some(b"<html></html>".to_vec())
+            );
+        }
+
+        let rep = Replayer::new(dir).expect("bug");
+        assert!(rep.go_url("https://example.com/").is_ok());
+        assert_eq!(rep.eval("", vec![]).expect("bug"), "\"a,b\"");
+        assert_eq!(
+            rep.eval_async("", vec![]).expect("bug"),
+            "\"twtl_v1\\n...\""
+        );
+        match rep.go_url("https://example.com/timeout") {
+            Err(WdaError::WdcFail(WdcError::BadDrvCmd(err, _))) => assert_eq!(err, "timeout"),
+            _ => panic!("buggy"),
+        }
+        assert_eq!(
+            rep.page_src(None).expect("bug"),
+            Some(b"<html></html>".to_vec())
+        );
+        // exhausted
+        assert!(rep.get_url().is_err());
+    }
+
+    #[test]
+    fn replay_mismatched_1() {
+        let dir = std::env::temp_dir().join("mafa-cassette-replay-mismatched-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_str().expect("bug");
+
+        {
+            let rec = Recorder::new(&Canned, dir).expect("bug");
+            assert!(rec.go_url("https://example.com/a").is_ok());
+        }
+
+        let rep = Replayer::new(dir).expect("bug");
+        assert!(rep.go_url("https://example.com/b").is_err());
+        assert!(rep.go_url("https://example.com/a").is_ok());
+    }
+
+    #[test]
+    fn record_twice_1() {
+        let dir = std::env::temp_dir().join("mafa-cassette-record-twice-1");
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_str().expect("bug");
+
+        {
+            let rec = Recorder::new(&Canned, dir).expect("bug");
+            assert!(rec.go_url("https://example.com/first").is_ok());
+        }
+        {
+            let rec = Recorder::new(&Canned, dir).expect("bug");
+            assert!(rec.go_url("https://example.com/second").is_ok());
+        }
+
+        // only the latest recording is served
+        let rep = Replayer::new(dir).expect("bug");
+        assert!(rep.go_url("https://example.com/second").is_ok());
+        assert!(rep.get_url().is_err());
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Persistent defaults of options.
+//!
+//! The config file is `config.toml` under `$MAFA_HOME/v1` if it is set,
+//! otherwise under `~/.mafa/v1` if that directory exists, otherwise under
+//! `$XDG_CONFIG_HOME/mafa/v1`, or `~/.mafa/v1` if it is unset.
+//! Top-level keys are global options, tables are components, keys are
+//! options' long names:
+//!
+//! ```toml
+//! socks5 = "127.0.0.1:1080"
+//! wrap-width = 100
+//!
+//! [gtrans]
+//! tl = "fr"
+//!
+//! [twtl]
+//! ntweets = 20
+//! ```
+//!
+//! Every option can also be supplied by an environment variable, named
+//! after its component and long name, e.g., `MAFA_SOCKS5`, `MAFA_GTRANS_TL`.
+//! The precedence is: config < environment < command line.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+
+use clap::builder::BoolishValueParser;
+use clap::parser::ValueSource;
+use clap::Arg as ClapArg;
+use clap::ArgMatches as ClapArgMatches;
+use clap::Command as ClapCommand;
+use once_cell::sync::OnceCell;
+
+use crate::error::MafaError;
+use crate::error::Result;
+use crate::mafadata::MafaData;
+use crate::OutputFormat;
+
+#[derive(Debug, Default)]
+pub struct MafaConfig {
+    global: BTreeMap<String, String>,
+    comps: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl MafaConfig {
+    ///
+    /// a missing config file is identical to an empty one
+    pub fn load(mafad: &MafaData) -> Result<Self> {
+        let pbuf = mafad.pathto_config();
+
+        match std::fs::read_to_string(&pbuf) {
+            Ok(rawdata) => {
+                Self::from_str(&rawdata).map_err(|err_io| MafaError::InvalidConfig(pbuf, err_io))
+            }
+            Err(err_io) if err_io.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err_io) => Err(MafaError::InvalidConfig(pbuf, err_io)),
+        }
+    }
+
+    ///
+    /// `comp` is None for global options
+    pub fn get(&self, comp: Option<&str>, longopt: &str) -> Option<&str> {
+        match comp {
+            Some(comp) => self.comps.get(comp)?.get(longopt),
+            None => self.global.get(longopt),
+        }
+        .map(|v| v.as_str())
+    }
+}
+
+impl FromStr for MafaConfig {
+    // of kind `InvalidData`, wrapped with the path in the same way as
+    // errors of reading the file
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let root = s
+            .parse::<toml::Value>()
+            .map_err(|err_toml| io::Error::new(io::ErrorKind::InvalidData, err_toml))?;
+        let root = root
+            .as_table()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a table"))?;
+
+        let mut cfg = MafaConfig::default();
+
+        for (k, v) in root {
+            if let toml::Value::Table(comp_tbl) = v {
+                let comp = cfg.comps.entry(k.clone()).or_default();
+                for (comp_k, comp_v) in comp_tbl {
+                    comp.insert(comp_k.clone(), scalar_to_string(comp_k, comp_v)?);
+                }
+            } else {
+                cfg.global.insert(k.clone(), scalar_to_string(k, v)?);
+            }
+        }
+
+        Ok(cfg)
+    }
+}
+
+fn scalar_to_string(k: &str, v: &toml::Value) -> io::Result<String> {
+    match v {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("value of `{}` is not a scalar", k),
+        )),
+    }
+}
+
+static INSTALLED: OnceCell<MafaConfig> = OnceCell::new();
+
+///
+/// Make `cfg` the source of defaults of all commands built afterwards,
+/// only the first installed one takes effect.
+pub fn install(cfg: MafaConfig) {
+    let _ = INSTALLED.set(cfg);
+}
+
+pub fn env_name(comp: Option<&str>, longopt: &str) -> String {
+    let mut s = String::from("MAFA_");
+    if let Some(comp) = comp {
+        s += comp;
+        s += "_";
+    }
+    s += longopt;
+
+    s.to_uppercase().replace('-', "_")
+}
+
+fn apply(cfg: Option<&MafaConfig>, arg: ClapArg, comp: Option<&str>, longopt: &str) -> ClapArg {
+    let arg = arg.env(env_name(comp, longopt));
+
+    match cfg.and_then(|cfg| cfg.get(comp, longopt)) {
+        Some(val) => arg.default_value(val.to_string()),
+        None => arg,
+    }
+}
+
+///
+/// Let the option be overridable by the installed config and the
+/// environment.
+pub(crate) fn with_defaults(arg: ClapArg, comp: Option<&str>, longopt: &str) -> ClapArg {
+    apply(INSTALLED.get(), arg, comp, longopt)
+}
+
+///
+/// Same as [`with_defaults`], for flags, which accept "true", "yes", "1",
+/// "false", "no", "0", etc.
+pub(crate) fn with_flag_defaults(arg: ClapArg, comp: Option<&str>, longopt: &str) -> ClapArg {
+    with_defaults(arg.value_parser(BoolishValueParser::new()), comp, longopt)
+}
+
+#[derive(Debug, serde::Serialize)]
+struct EffectiveVal {
+    value: String,
+    source: &'static str,
+}
+
+fn effective_of(
+    cfg: Option<&MafaConfig>,
+    cmd: &ClapCommand,
+    comp: Option<&str>,
+    ca_matched: Option<&ClapArgMatches>,
+) -> Vec<(String, EffectiveVal)> {
+    let mut all_val = vec![];
+
+    for arg in cmd.get_arguments() {
+        let longopt = match arg.get_long() {
+            Some(v) => v,
+            None => continue, // positional, help, version
+        };
+        if longopt == "help" || longopt == "version" {
+            continue;
+        }
+
+        let in_cfg = cfg.and_then(|cfg| cfg.get(comp, longopt));
+        let in_env = arg
+            .get_env()
+            .and_then(std::env::var_os)
+            .map(|v| v.to_string_lossy().to_string());
+        let in_cli = ca_matched
+            .filter(|m| m.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+            .and_then(|m| m.get_raw(arg.get_id().as_str()))
+            .map(|vals| {
+                vals.map(|v| v.to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            });
+        let in_def = match arg.get_default_values().first() {
+            Some(v) => Some(v.to_string_lossy().to_string()),
+            None if !arg.get_action().takes_values() => Some("false".to_string()),
+            None => None,
+        };
+
+        let effval = if let Some(value) = in_cli {
+            EffectiveVal {
+                value,
+                source: "cli",
+            }
+        } else if let Some(value) = in_env {
+            EffectiveVal {
+                value,
+                source: "env",
+            }
+        } else if let Some(value) = in_cfg {
+            EffectiveVal {
+                value: value.to_string(),
+                source: "config",
+            }
+        } else if let Some(value) = in_def {
+            EffectiveVal {
+                value,
+                source: "default",
+            }
+        } else {
+            continue;
+        };
+
+        all_val.push((longopt.to_string(), effval));
+    }
+
+    all_val
+}
+
+///
+/// Effective values of all options, along with where they come from.
+pub fn show(ca_matched: &ClapArgMatches, output: OutputFormat) -> String {
+    let cfg = INSTALLED.get();
+    let cmd_mafa = crate::get_cmd();
+
+    let mut all_sect = vec![("mafa", effective_of(cfg, &cmd_mafa, None, Some(ca_matched)))];
+    for cmd_comp in cmd_mafa.get_subcommands() {
+        let comp = cmd_comp.get_name();
+        if comp == "i" || comp == "config" || comp == "cache" || comp == "doctor" {
+            continue;
+        }
+        all_sect.push((comp, effective_of(cfg, cmd_comp, Some(comp), None)));
+    }
+
+    if let OutputFormat::Json = output {
+        let mut json_root = serde_json::Map::new();
+        for (sect, all_val) in all_sect {
+            let mut json_sect = serde_json::Map::new();
+            for (k, v) in all_val {
+                json_sect.insert(k, serde_json::to_value(v).expect("buggy"));
+            }
+            json_root.insert(sect.to_string(), serde_json::Value::Object(json_sect));
+        }
+
+        return serde_json::Value::Object(json_root).to_string();
+    }
+
+    let mut s = String::new();
+    for (i, (sect, all_val)) in all_sect.iter().enumerate() {
+        if i > 0 {
+            s += "\n";
+        }
+        s += &format!("[{}]\n", sect);
+        for (k, v) in all_val {
+            s += &format!("{} = {:?} ({})\n", k, v.value, v.source);
+        }
+    }
+    s.pop(); // trailing newline
+
+    s
+}
+
+pub fn get_cmd() -> ClapCommand {
+    ClapCommand::new("config")
+        .about("Manage persistent options")
+        .long_about(
+            "Manage persistent options
+
+Options' defaults can be set in config.toml under mafa's data directory,
+or by environment variables such as MAFA_SOCKS5 and MAFA_GTRANS_TL. The
+precedence is: config < environment < command line.",
+        )
+        .subcommand_required(true)
+        .subcommand(
+            ClapCommand::new("show")
+                .about("Print effective values of all options and their sources"),
+        )
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[test]
+    fn from_str_1() {
+        let cfg = MafaConfig::from_str(
+            r#"
+socks5 = "127.0.0.1:1080"
+wrap-width = 100
+gui = true
+
+[gtrans]
+tl = "fr"
+
+[twtl]
+ntweets = 20
+"#,
+        )
+        .expect("buggy");
+
+        assert_eq!(cfg.get(None, "socks5"), Some("127.0.0.1:1080"));
+        assert_eq!(cfg.get(None, "wrap-width"), Some("100"));
+        assert_eq!(cfg.get(None, "gui"), Some("true"));
+        assert_eq!(cfg.get(Some("gtrans"), "tl"), Some("fr"));
+        assert_eq!(cfg.get(Some("gtrans"), "sl"), None);
+        assert_eq!(cfg.get(Some("twtl"), "ntweets"), Some("20"));
+        assert_eq!(cfg.get(None, "tl"), None);
+    }
+
+    #[test]
+    fn from_str_2() {
+        assert!(MafaConfig::from_str("socks5 = ").is_err());
+        assert!(MafaConfig::from_str("socks5 = [1, 2]").is_err());
+        assert!(MafaConfig::from_str("").is_ok());
+    }
+
+    #[test]
+    fn env_name_1() {
+        assert_eq!(env_name(None, "socks5"), "MAFA_SOCKS5");
+        assert_eq!(env_name(None, "wrap-width"), "MAFA_WRAP_WIDTH");
+        assert_eq!(env_name(Some("gtrans"), "tl"), "MAFA_GTRANS_TL");
+    }
+
+    #[test]
+    fn precedence_1() {
+        let cfg = MafaConfig::from_str(
+            r#"
+[utstcfg]
+aa = "config"
+bb = "config"
+cc = "config"
+ff = "yes"
+"#,
+        )
+        .expect("buggy");
+
+        // clap reads environment when building args
+        std::env::set_var("MAFA_UTSTCFG_BB", "env");
+        std::env::set_var("MAFA_UTSTCFG_CC", "env");
+
+        let cmd = ClapCommand::new("utstcfg");
+        let cmd = ["aa", "bb", "cc", "dd"].iter().fold(cmd, |cmd, longopt| {
+            cmd.arg(apply(
+                Some(&cfg),
+                ClapArg::new(*longopt)
+                    .long(*longopt)
+                    .default_value("default"),
+                Some("utstcfg"),
+                longopt,
+            ))
+        });
+        let cmd = cmd.arg(apply(
+            Some(&cfg),
+            ClapArg::new("ff")
+                .long("ff")
+                .action(clap::ArgAction::SetTrue)
+                .value_parser(BoolishValueParser::new()),
+            Some("utstcfg"),
+            "ff",
+        ));
+
+        let m = cmd
+            .try_get_matches_from(["utstcfg", "--cc", "cli"])
+            .expect("buggy");
+
+        assert_eq!(m.get_one::<String>("aa").expect("buggy"), "config");
+        assert_eq!(m.get_one::<String>("bb").expect("buggy"), "env");
+        assert_eq!(m.get_one::<String>("cc").expect("buggy"), "cli");
+        assert_eq!(m.get_one::<String>("dd").expect("buggy"), "default");
+        assert!(m.get_flag("ff"));
+    }
+}
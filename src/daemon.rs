@@ -0,0 +1,530 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Daemon mode.
+//!
+//! The daemon keeps one browser alive and serves components' requests over
+//! a Unix domain socket in mafa's data directory, one request at a time.
+//! A request is the command line arguments of a normal invocation, the
+//! response carries everything the invocation would print, and its exit
+//! code.
+//!
+//! Options affecting the browser itself, e.g., --socks5, are fixed once the
+//! daemon started, requests with different ones are refused and handled
+//! locally by the requester.
+//!
+//! Caches loaded by one request are held for the following ones, see
+//! [`HeldCaches`].
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::net::Shutdown;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use std::time::SystemTime;
+
+use clap::Command as ClapCommand;
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use crate::cache;
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use crate::cache::CacheEntry;
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use crate::comm::CacheMechanism;
+use crate::error::MafaError;
+use crate::error::Result;
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+use crate::mafadata::MafaData;
+use crate::MafaInput;
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct DaemonRequest {
+    pub args: Vec<String>,
+    ///
+    /// relative paths in args are relative to this
+    #[serde(default)]
+    pub cwd: String,
+    ///
+    /// MAFA_* environment variables of the requester
+    #[serde(default)]
+    pub envs: Vec<(String, String)>,
+}
+
+impl DaemonRequest {
+    ///
+    /// `args` excludes the program name
+    pub fn from_current(args: Vec<String>) -> Self {
+        DaemonRequest {
+            args,
+            cwd: std::env::current_dir()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            envs: mafa_envs(),
+        }
+    }
+}
+
+fn mafa_envs() -> Vec<(String, String)> {
+    std::env::vars()
+        .filter(|(k, _)| k.starts_with("MAFA_"))
+        .collect()
+}
+
+///
+/// Run `f` under the working directory and MAFA_* environment variables of
+/// the requester, restore daemon's own afterwards.
+pub fn within_requester<T>(req: &DaemonRequest, f: impl FnOnce() -> T) -> T {
+    let cwd_saved = std::env::current_dir().ok();
+    let envs_saved = mafa_envs();
+
+    if !req.cwd.is_empty() {
+        let _ = std::env::set_current_dir(&req.cwd);
+    }
+    for (k, _) in envs_saved.iter() {
+        std::env::remove_var(k);
+    }
+    for (k, v) in req.envs.iter() {
+        std::env::set_var(k, v);
+    }
+
+    let ret = f();
+
+    for (k, _) in req.envs.iter() {
+        std::env::remove_var(k);
+    }
+    for (k, v) in envs_saved.iter() {
+        std::env::set_var(k, v);
+    }
+    if let Some(cwd) = cwd_saved {
+        let _ = std::env::set_current_dir(cwd);
+    }
+
+    ret
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct DaemonResponse {
+    pub code: u8,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    ///
+    /// the reason if the request is not served
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refused: Option<String>,
+}
+
+impl DaemonResponse {
+    pub fn refuse(reason: &str) -> Self {
+        DaemonResponse {
+            refused: Some(reason.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// Whether the browser initialized for `daemon_in` is identical to the
+/// one `req_in` would initialize.
+pub fn is_browser_compatible(daemon_in: &MafaInput, req_in: &MafaInput) -> bool {
+    daemon_in.gui == req_in.gui
+        && daemon_in.socks5 == req_in.socks5
+        && daemon_in.tout_page_load == req_in.tout_page_load
+        && daemon_in.tout_script == req_in.tout_script
+        && daemon_in.use_profile == req_in.use_profile
+}
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+#[derive(Debug)]
+struct Held {
+    mtime: SystemTime,
+    entries: Vec<CacheEntry>,
+}
+
+///
+/// Components' caches loaded by earlier requests. A cache is loaded again
+/// only if its file has been changed by others since, e.g., by `mafa cache
+/// rebuild`, or the last request using it failed.
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+#[derive(Debug, Default)]
+pub struct HeldCaches {
+    all: HashMap<String, Held>,
+}
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+impl HeldCaches {
+    fn mtime_of(mafad: &MafaData, cache_id: &str) -> Option<SystemTime> {
+        std::fs::metadata(mafad.pathto_cache(cache_id))
+            .and_then(|v| v.modified())
+            .ok()
+    }
+
+    ///
+    /// Entries of cache_id for a request with `req_in`, None if the
+    /// component should load the cache on its own, i.e., the cache
+    /// mechanism is not local, or the cache is not usable yet.
+    fn entries(
+        &mut self,
+        mafad: &MafaData,
+        req_in: &MafaInput,
+        cache_id: &str,
+    ) -> Option<Vec<CacheEntry>> {
+        if !matches!(req_in.cachm, CacheMechanism::Local) {
+            self.all.remove(cache_id);
+            return None;
+        }
+
+        let mtime = Self::mtime_of(mafad, cache_id)?;
+        if let Some(held) = self.all.get(cache_id) {
+            if held.mtime == mtime {
+                return Some(held.entries.clone());
+            }
+        }
+
+        let entries = cache::load(mafad, cache_id)
+            .ok()?
+            .into_iter()
+            .map(|v| v.entry)
+            .collect::<Vec<CacheEntry>>();
+        if entries.is_empty() {
+            return None;
+        }
+        // loading may have rewritten it
+        let mtime = Self::mtime_of(mafad, cache_id)?;
+        self.all.insert(
+            cache_id.to_string(),
+            Held {
+                mtime,
+                entries: entries.clone(),
+            },
+        );
+
+        Some(entries)
+    }
+
+    ///
+    /// for gtrans and camd
+    pub fn upaths(
+        &mut self,
+        mafad: &MafaData,
+        req_in: &MafaInput,
+        cache_id: &str,
+    ) -> Option<Vec<Vec<u8>>> {
+        self.entries(mafad, req_in, cache_id)?
+            .into_iter()
+            .map(|v| v.into_upath())
+            .collect::<Result<Vec<Vec<u8>>>>()
+            .ok()
+    }
+
+    ///
+    /// for twtl, the upper path goes first
+    pub fn ulpaths(
+        &mut self,
+        mafad: &MafaData,
+        req_in: &MafaInput,
+        cache_id: &str,
+    ) -> Option<Vec<Vec<Vec<u8>>>> {
+        self.entries(mafad, req_in, cache_id)?
+            .into_iter()
+            .map(|v| v.into_ulpath().map(|(upper, lower)| vec![upper, lower]))
+            .collect::<Result<Vec<Vec<Vec<u8>>>>>()
+            .ok()
+    }
+
+    ///
+    /// Called once a request of cache_id is done. The component's own
+    /// updates to the file, i.e., outcomes of entries, are taken as the
+    /// held ones, unless the request failed.
+    pub fn settle(&mut self, mafad: &MafaData, cache_id: &str, is_ok: bool) {
+        match (is_ok, Self::mtime_of(mafad, cache_id)) {
+            (true, Some(mtime)) => {
+                if let Some(held) = self.all.get_mut(cache_id) {
+                    held.mtime = mtime;
+                }
+            }
+            _ => {
+                self.all.remove(cache_id);
+            }
+        }
+    }
+}
+
+///
+/// Forward `req` to the running daemon. None if there is no daemon, or
+/// the daemon refused, then the request should be handled locally.
+pub fn forward(sock: &Path, req: &DaemonRequest) -> Option<DaemonResponse> {
+    let mut stream = UnixStream::connect(sock).ok()?;
+
+    let req = serde_json::to_string(req).ok()?;
+    stream.write_all(req.as_bytes()).ok()?;
+    stream.shutdown(Shutdown::Write).ok()?;
+
+    let mut rawresp = String::new();
+    stream.read_to_string(&mut rawresp).ok()?;
+    let resp = serde_json::from_str::<DaemonResponse>(&rawresp).ok()?;
+
+    dbgg!(&resp.refused);
+
+    if resp.refused.is_some() {
+        None
+    } else {
+        Some(resp)
+    }
+}
+
+///
+/// Serve requests on `sock` forever, a stale socket file left by a
+/// previous daemon is replaced.
+pub fn serve<F>(sock: &Path, mut handler: F) -> Result<()>
+where
+    F: FnMut(DaemonRequest) -> DaemonResponse,
+{
+    if sock.exists() {
+        if UnixStream::connect(sock).is_ok() {
+            return Err(MafaError::InvalidDaemonSocket);
+        }
+        std::fs::remove_file(sock).map_err(|err_io| MafaError::Io(sock.to_path_buf(), err_io))?;
+    }
+
+    let listener =
+        UnixListener::bind(sock).map_err(|err_io| MafaError::Io(sock.to_path_buf(), err_io))?;
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(v) => v,
+            Err(_err_io) => {
+                dbgg!(_err_io);
+                continue;
+            }
+        };
+
+        let mut rawreq = String::new();
+        let resp = match stream.read_to_string(&mut rawreq) {
+            Ok(_) => match serde_json::from_str::<DaemonRequest>(&rawreq) {
+                Ok(req) => handler(req),
+                Err(_) => DaemonResponse::refuse("malformed request"),
+            },
+            Err(_) => DaemonResponse::refuse("unreadable request"),
+        };
+
+        // the requester may have gone, nothing to do then
+        if let Ok(rawresp) = serde_json::to_string(&resp) {
+            let _ = stream.write_all(rawresp.as_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+struct Redirected {
+    fd: libc::c_int,
+    saved: libc::c_int,
+    file: File,
+}
+
+impl Redirected {
+    fn new(fd: libc::c_int) -> Result<Self> {
+        let file = tempfile_for(fd)?;
+
+        let saved = unsafe { libc::dup(fd) };
+        if saved < 0 {
+            return Err(MafaError::Io(
+                PathBuf::from(format!("/dev/fd/{}", fd)),
+                std::io::Error::last_os_error(),
+            ));
+        }
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            let err_io = std::io::Error::last_os_error();
+            unsafe { libc::close(saved) };
+            return Err(MafaError::Io(
+                PathBuf::from(format!("/dev/fd/{}", fd)),
+                err_io,
+            ));
+        }
+
+        Ok(Redirected { fd, saved, file })
+    }
+
+    fn restore(mut self) -> String {
+        unsafe {
+            libc::dup2(self.saved, self.fd);
+            libc::close(self.saved);
+        }
+
+        let mut buf = vec![];
+        let _ = self.file.rewind();
+        let _ = self.file.read_to_end(&mut buf);
+
+        String::from_utf8_lossy(&buf).to_string()
+    }
+}
+
+fn tempfile_for(fd: libc::c_int) -> Result<File> {
+    let pbuf = std::env::temp_dir().join(format!("mafa-daemon-{}-{}", std::process::id(), fd));
+
+    let file = File::options()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(&pbuf)
+        .map_err(|err_io| MafaError::Io(pbuf.clone(), err_io))?;
+    // only the opened one is needed
+    let _ = std::fs::remove_file(&pbuf);
+
+    Ok(file)
+}
+
+///
+/// Run `f`, with everything it prints to standard output and standard error
+/// captured.
+pub fn capture<T>(f: impl FnOnce() -> T) -> Result<(T, String, String)> {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+
+    let rd_out = Redirected::new(libc::STDOUT_FILENO)?;
+    let rd_err = match Redirected::new(libc::STDERR_FILENO) {
+        Ok(v) => v,
+        Err(err) => {
+            rd_out.restore();
+            return Err(err);
+        }
+    };
+
+    let ret = f();
+
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+
+    let stderr = rd_err.restore();
+    let stdout = rd_out.restore();
+
+    Ok((ret, stdout, stderr))
+}
+
+pub fn get_cmd() -> ClapCommand {
+    ClapCommand::new("daemon")
+        .about("Keep the browser running and serve other invocations")
+        .long_about(
+            "Keep the browser running and serve other invocations
+
+While the daemon is running, components invoked in other shells are
+forwarded to it transparently, saving the time of launching the browser
+and loading caches, their output and exit codes remain the same. Global
+options affecting the browser, such as --socks5, --gui, --profile and
+timeouts, are taken when the daemon starts, invocations with different
+ones are handled locally.
+
+Use --no-daemon to always handle invocations locally.",
+        )
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[test]
+    fn forward_1() {
+        let sock = std::env::temp_dir().join("mafa-daemon-forward-1.sock");
+        let _ = std::fs::remove_file(&sock);
+
+        // no daemon
+        assert!(forward(&sock, &DaemonRequest::default()).is_none());
+
+        let sock_srv = sock.clone();
+        std::thread::spawn(move || {
+            serve(&sock_srv, |req| {
+                let args = req.args;
+                if args.is_empty() {
+                    return DaemonResponse::refuse("nothing requested");
+                }
+                DaemonResponse {
+                    code: args.len() as u8,
+                    stdout: args.join(" "),
+                    stderr: "".to_string(),
+                    refused: None,
+                }
+            })
+            .expect("buggy");
+        });
+
+        let mut resp = None;
+        for _ in 0..50 {
+            resp = forward(
+                &sock,
+                &DaemonRequest::from_current(vec!["gtrans".to_string(), "hello".to_string()]),
+            );
+            if resp.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let resp = resp.expect("buggy");
+        assert_eq!(resp.code, 2);
+        assert_eq!(resp.stdout, "gtrans hello");
+
+        // refused
+        assert!(forward(&sock, &DaemonRequest::default()).is_none());
+
+        // occupied
+        match serve(&sock, |_| DaemonResponse::default()) {
+            Err(MafaError::InvalidDaemonSocket) => {}
+            _ => panic!("buggy"),
+        }
+    }
+
+    #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+    #[test]
+    fn held_caches_1() {
+        let dir = std::env::temp_dir().join(format!("mafa-utst-held-{}", std::process::id()));
+        let mafad =
+            MafaData::init_from(|k| (k == "MAFA_HOME").then(|| dir.clone())).expect("buggy");
+        let cache_id = "utst-held-caches-1";
+
+        let mut held = HeldCaches::default();
+        let mut req_in = MafaInput::default();
+
+        // not built yet
+        assert_eq!(held.upaths(&mafad, &req_in, cache_id), None);
+
+        cache::init(&mafad, cache_id, "[1,2]\n[3]\n-", "local").expect("buggy");
+        let upaths = Some(vec![vec![1, 2], vec![3]]);
+        assert_eq!(held.upaths(&mafad, &req_in, cache_id), upaths);
+        held.settle(&mafad, cache_id, true);
+        assert_eq!(held.upaths(&mafad, &req_in, cache_id), upaths);
+
+        // changed by others, its mtime set apart as both writes may fall
+        // within the same tick
+        cache::init(&mafad, cache_id, "[4]\n-", "local").expect("buggy");
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(mafad.pathto_cache(cache_id))
+            .and_then(|f| f.set_modified(later))
+            .expect("buggy");
+        assert_eq!(held.upaths(&mafad, &req_in, cache_id), Some(vec![vec![4]]));
+
+        req_in.cachm = CacheMechanism::No;
+        assert_eq!(held.upaths(&mafad, &req_in, cache_id), None);
+        assert!(held.all.is_empty());
+
+        std::fs::remove_dir_all(&dir).expect("buggy");
+    }
+}
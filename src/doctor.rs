@@ -0,0 +1,566 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Diagnosis of the environment mafa runs in, i.e., `mafa doctor`.
+//!
+//! Each check passes, fails, or is skipped when there is nothing to check
+//! or it depends on a failed one, or is left unverified when mafa cannot
+//! tell. Failed checks carry a hint on how to fix them. Checks on local data go first, the browser is launched last, for
+//! it is the slowest one.
+
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::time::Duration;
+
+use clap::Command as ClapCommand;
+use wda::WdaError;
+
+use crate::error::exit_code;
+use crate::error::MafaError;
+use crate::ev_ntf::ff_bin_hint;
+use crate::mafadata::MafaData;
+use crate::theme;
+use crate::MafaInput;
+use crate::OutputFormat;
+
+///
+/// how long a lock may be held before it is reported
+const LOCK_PATIENCE: Duration = Duration::from_secs(1);
+
+///
+/// for both connecting to and hearing from the proxy
+const SOCKS5_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+    Unverified,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+            Status::Skip => "skip",
+            Status::Unverified => "unverified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    ///
+    /// how to fix it, empty unless failed
+    pub hint: String,
+    ///
+    /// exit code of mafa if this is the first failed check
+    #[serde(skip)]
+    pub code: u8,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Pass,
+            detail: detail.into(),
+            hint: String::new(),
+            code: exit_code::OK,
+        }
+    }
+
+    fn skip(name: &str, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Skip,
+            detail: detail.into(),
+            hint: String::new(),
+            code: exit_code::OK,
+        }
+    }
+
+    fn unverified(name: &str, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Unverified,
+            detail: detail.into(),
+            hint: String::new(),
+            code: exit_code::OK,
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, hint: impl Into<String>, code: u8) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: hint.into(),
+            code,
+        }
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|v| v.status != Status::Fail)
+    }
+
+    ///
+    /// that of the first failed check, 0 if none failed
+    pub fn exit_code(&self) -> u8 {
+        self.checks
+            .iter()
+            .find(|v| v.status == Status::Fail)
+            .map(|v| v.code)
+            .unwrap_or(exit_code::OK)
+    }
+
+    pub fn to_output(&self, output: OutputFormat, nocolor: bool) -> String {
+        if let OutputFormat::Json = output {
+            return serde_json::json!({
+                "healthy": self.is_healthy(),
+                "checks": self.checks,
+            })
+            .to_string();
+        }
+
+        let theme = theme::current(nocolor);
+        let name_width = self.checks.iter().map(|v| v.name.len()).max().unwrap_or(0);
+        let status_width = self
+            .checks
+            .iter()
+            .map(|v| v.status.as_str().len())
+            .max()
+            .unwrap_or(0);
+
+        let mut s = String::new();
+        for check in self.checks.iter() {
+            let status = match check.status {
+                Status::Pass => theme.tip.paint("pass"),
+                Status::Fail => theme.error.paint("fail"),
+                Status::Skip | Status::Unverified => theme.note.paint(check.status.as_str()),
+            };
+            // painted ones are padded by hand, escapes are not columns
+            s += &format!(
+                "[{}]{:pad$} {:<w$}  {}\n",
+                status,
+                "",
+                check.name,
+                check.detail,
+                pad = status_width - check.status.as_str().len(),
+                w = name_width
+            );
+            if !check.hint.is_empty() {
+                s += &format!(
+                    "{:indent$}{}: {}\n",
+                    "",
+                    theme.tip.paint("hint"),
+                    check.hint,
+                    indent = status_width + 3
+                );
+            }
+        }
+        s.pop(); // trailing newline
+
+        s
+    }
+}
+
+fn check_dirs(mafad: &MafaData) -> Check {
+    let name = "data directories";
+
+    for dir in mafad.all_dirs() {
+        let probe = dir.join(format!(".doctor{}", std::process::id()));
+        let written = std::fs::write(&probe, b"").and_then(|_| std::fs::remove_file(&probe));
+        if let Err(err_io) = written {
+            return Check::fail(
+                name,
+                format!("{} is not writable: {}", dir.display(), err_io),
+                "Fix the permissions of it, or set MAFA_HOME to a writable directory",
+                exit_code::LOCAL_DATA,
+            );
+        }
+    }
+
+    let all_dirs = mafad.all_dirs();
+    Check::pass(name, format!("{} is writable", all_dirs[0].display()))
+}
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+fn check_caches(mafad: &MafaData) -> Vec<Check> {
+    use crate::cache;
+
+    let mut checks = vec![];
+    for cache_id in cache::CACHE_IDS {
+        let name = format!("cache {}", cache_id);
+        // read only, unlike cache::load which may migrate it
+        let loaded = mafad.cache_read(cache_id).and_then(|v| match v {
+            Some(rawdata) => cache::decode(cache_id, &rawdata, "v1", 0).map(Some),
+            None => Ok(None),
+        });
+        checks.push(match loaded {
+            Ok(Some(records)) => Check::pass(&name, format!("{} entries", records.len())),
+            Ok(None) => Check::skip(&name, "not built yet, it will be on first use"),
+            Err(err_cache) => Check::fail(
+                &name,
+                format!("{}: {}", mafad.pathto_cache(cache_id).display(), err_cache),
+                format!("Run `mafa cache rebuild {}`", cache_id),
+                err_cache.exit_code(),
+            ),
+        });
+    }
+
+    checks
+}
+
+fn check_locks(mafad: &MafaData) -> Check {
+    let name = "lock files";
+
+    match mafad.held_locks(LOCK_PATIENCE) {
+        Ok(held) if !held.is_empty() => Check::fail(
+            name,
+            format!(
+                "held by other processes: {}",
+                held.iter()
+                    .map(|v| v.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            "Wait for other mafa processes to finish, or kill the stuck one, \
+             `fuser <lock file>` tells which process it is",
+            exit_code::LOCAL_DATA,
+        ),
+        Ok(_) => Check::pass(name, "none held"),
+        Err(err_lock) => Check::fail(
+            name,
+            err_lock.to_string(),
+            "Fix the permissions of mafa's data directory",
+            err_lock.exit_code(),
+        ),
+    }
+}
+
+///
+/// Greet the proxy at `addr` as a SOCKS5 client offering no
+/// authentication, the reason if it does not answer as a SOCKS5 server
+/// accepting that.
+fn socks5_greet(addr: &str, timeout: Duration) -> std::result::Result<(), String> {
+    let sock_addr = addr
+        .to_socket_addrs()
+        .map_err(|err_io| format!("cannot resolve {}: {}", addr, err_io))?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", addr))?;
+
+    let mut stream = TcpStream::connect_timeout(&sock_addr, timeout)
+        .map_err(|err_io| format!("cannot connect to {}: {}", addr, err_io))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|err_io| err_io.to_string())?;
+
+    // version 5, one method, no authentication
+    stream
+        .write_all(&[0x05, 0x01, 0x00])
+        .map_err(|err_io| format!("cannot greet {}: {}", addr, err_io))?;
+    let mut reply = [0u8; 2];
+    stream
+        .read_exact(&mut reply)
+        .map_err(|err_io| format!("no reply from {}: {}", addr, err_io))?;
+
+    match reply {
+        [0x05, 0x00] => Ok(()),
+        [0x05, _] => Err(format!("{} requires authentication", addr)),
+        _ => Err(format!("{} is not a SOCKS5 proxy", addr)),
+    }
+}
+
+fn check_socks5(mafa_in: &MafaInput) -> Check {
+    let name = "socks5 proxy";
+
+    if mafa_in.socks5.is_empty() {
+        return Check::skip(name, "not in use");
+    }
+
+    match socks5_greet(&mafa_in.socks5, SOCKS5_TIMEOUT) {
+        Ok(_) => Check::pass(name, format!("{} is reachable", mafa_in.socks5)),
+        Err(reason) => Check::fail(
+            name,
+            reason,
+            "Start the proxy, or correct --socks5, note that only proxies \
+             without authentication are supported",
+            exit_code::NETWORK,
+        ),
+    }
+}
+
+///
+/// first `bin` found in PATH
+fn which(bin: &str) -> Option<std::path::PathBuf> {
+    let all_path = std::env::var_os("PATH")?;
+    std::env::split_paths(&all_path)
+        .map(|dir| dir.join(bin))
+        .find(|pbuf| Path::new(pbuf).is_file())
+}
+
+///
+/// Checks on Firefox, geckodriver and the profile, by launching the
+/// browser just as other subcommands do.
+fn check_browser(mafa_in: &MafaInput) -> Vec<Check> {
+    let (n_ff, n_gd, n_prof) = ("firefox", "geckodriver", "profile");
+
+    // geckodriver may find one elsewhere, which mafa cannot tell
+    let ff_check = match ["firefox", "firefox-esr"].iter().find_map(|bin| which(bin)) {
+        Some(pbuf) => Check::pass(n_ff, format!("{} found", pbuf.display())),
+        None => Check::unverified(n_ff, "not in PATH, geckodriver may find one elsewhere"),
+    };
+    let gd_found = which("geckodriver")
+        .map(|v| format!("{} works", v.display()))
+        .unwrap_or(String::from("works"));
+
+    let wda_inst = match crate::init_wda(mafa_in) {
+        Ok(v) => v,
+        Err(MafaError::FirefoxNotFound) => {
+            return vec![
+                Check::fail(n_ff, "not found", ff_bin_hint(), exit_code::BROWSER_MISSING),
+                Check::skip(n_gd, "needs firefox"),
+                Check::skip(n_prof, "needs firefox"),
+            ];
+        }
+        Err(MafaError::InvalidUseProfile) => {
+            return vec![
+                ff_check.clone(),
+                Check::pass(n_gd, gd_found),
+                Check::fail(
+                    n_prof,
+                    format!("{} does not exist", mafa_in.use_profile),
+                    "Create it with Firefox's profile manager (`firefox -P`), \
+                     or drop --use-profile to use a temporary one",
+                    exit_code::INVALID_INPUT,
+                ),
+            ];
+        }
+        Err(MafaError::InvalidSocks5Proxy) => {
+            return vec![
+                ff_check.clone(),
+                Check::fail(
+                    n_gd,
+                    format!("{} rejected as a proxy", mafa_in.socks5),
+                    "Give --socks5 as HOST:PORT",
+                    exit_code::INVALID_INPUT,
+                ),
+                Check::skip(n_prof, "needs geckodriver"),
+            ];
+        }
+        Err(MafaError::UnexpectedWda(WdaError::FetchWebDriver(reason))) => {
+            return vec![
+                ff_check.clone(),
+                Check::fail(
+                    n_gd,
+                    format!("cannot be downloaded: {}", reason),
+                    "Check the network connection, or install geckodriver into PATH",
+                    exit_code::NETWORK,
+                ),
+                Check::skip(n_prof, "needs geckodriver"),
+            ];
+        }
+        Err(err_wda) => {
+            return vec![
+                Check::skip(n_ff, "needs geckodriver"),
+                Check::fail(
+                    n_gd,
+                    format!("browser session failed to start: {}", err_wda),
+                    "Make sure no other Firefox is using the same profile, \
+                     and try updating Firefox",
+                    err_wda.exit_code(),
+                ),
+                Check::skip(n_prof, "needs geckodriver"),
+            ];
+        }
+    };
+
+    let prof = match wda_inst.existing_profiles() {
+        Ok(profiles) if !mafa_in.use_profile.is_empty() => Check::pass(
+            n_prof,
+            format!("{} among {:?}", mafa_in.use_profile, profiles),
+        ),
+        Ok(profiles) => Check::pass(
+            n_prof,
+            format!("a temporary one is used, existing: {:?}", profiles),
+        ),
+        Err(err_wda) => Check::fail(
+            n_prof,
+            format!("cannot list profiles: {:?}", err_wda),
+            "Make sure Firefox's profiles.ini is readable",
+            exit_code::BROWSER_FAILED,
+        ),
+    };
+
+    vec![ff_check, Check::pass(n_gd, gd_found), prof]
+}
+
+fn check_config(mafad: &MafaData, cfg_loaded: Result<(), &MafaError>) -> Check {
+    let name = "config file";
+    let pbuf = mafad.pathto_config();
+
+    match cfg_loaded {
+        Ok(_) if pbuf.is_file() => Check::pass(name, format!("{} is valid", pbuf.display())),
+        Ok(_) => Check::skip(name, format!("{} not created", pbuf.display())),
+        Err(err_cfg) => Check::fail(
+            name,
+            format!("{}: {}", pbuf.display(), err_cfg),
+            "Fix it, or move it away to go with the defaults",
+            err_cfg.exit_code(),
+        ),
+    }
+}
+
+///
+/// `mafad` and `cfg_loaded` are how the data directory and the config
+/// were set up, checks depending on a failed one are skipped.
+pub fn run(
+    mafad: Result<&MafaData, &MafaError>,
+    cfg_loaded: Result<(), &MafaError>,
+    mafa_in: &MafaInput,
+) -> Report {
+    let mut report = Report::default();
+
+    match mafad {
+        Ok(mafad) => {
+            report.checks.push(check_dirs(mafad));
+            report.checks.push(check_config(mafad, cfg_loaded));
+            #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+            report.checks.extend(check_caches(mafad));
+            report.checks.push(check_locks(mafad));
+        }
+        Err(err_data) => {
+            report.checks.push(Check::fail(
+                "data directories",
+                err_data.to_string(),
+                "Fix the permissions of it, or set MAFA_HOME to a writable directory",
+                err_data.exit_code(),
+            ));
+            report
+                .checks
+                .push(Check::skip("config file", "needs data directories"));
+            #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+            report
+                .checks
+                .push(Check::skip("caches", "needs data directories"));
+            report
+                .checks
+                .push(Check::skip("lock files", "needs data directories"));
+        }
+    }
+    report.checks.push(check_socks5(mafa_in));
+    report.checks.extend(check_browser(mafa_in));
+
+    report
+}
+
+pub fn get_cmd() -> ClapCommand {
+    ClapCommand::new("doctor")
+        .about("Check the environment mafa runs in")
+        .long_about(
+            "Check the environment mafa runs in
+
+Data directories, the config file, caches, lock files, the SOCKS5 proxy
+given by --socks5, Firefox, geckodriver and the profile given by
+--use-profile are checked in turn, each failed check comes with a hint on
+how to fix it. The exit code is that of the first failed check, 0 if all
+passed.",
+        )
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn serve_once(reply: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("buggy");
+        let addr = listener.local_addr().expect("buggy").to_string();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("buggy");
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).expect("buggy");
+            assert_eq!(greeting, [0x05, 0x01, 0x00]);
+            stream.write_all(reply).expect("buggy");
+        });
+
+        addr
+    }
+
+    #[test]
+    fn socks5_greet_1() {
+        let timeout = Duration::from_secs(5);
+        assert!(socks5_greet(&serve_once(&[0x05, 0x00]), timeout).is_ok());
+        assert!(socks5_greet(&serve_once(&[0x05, 0xff]), timeout)
+            .expect_err("buggy")
+            .contains("authentication"));
+        assert!(socks5_greet(&serve_once(b"HT"), timeout)
+            .expect_err("buggy")
+            .contains("not a SOCKS5"));
+    }
+
+    #[test]
+    fn report_1() {
+        let mut report = Report::default();
+        report.checks.push(Check::pass("aa", "fine"));
+        report.checks.push(Check::skip("bbbb", "nothing"));
+        assert!(report.is_healthy());
+        assert_eq!(report.exit_code(), 0);
+        assert_eq!(
+            report.to_output(OutputFormat::Text, true),
+            "[pass] aa    fine\n[skip] bbbb  nothing"
+        );
+
+        report
+            .checks
+            .push(Check::fail("c", "broken", "fix it", exit_code::NETWORK));
+        report
+            .checks
+            .push(Check::fail("d", "broken", "fix it", exit_code::LOCAL_DATA));
+        assert!(!report.is_healthy());
+        assert_eq!(report.exit_code(), exit_code::NETWORK);
+        assert!(report
+            .to_output(OutputFormat::Text, true)
+            .ends_with("[fail] d     broken\n       hint: fix it"));
+
+        let json =
+            serde_json::from_str::<serde_json::Value>(&report.to_output(OutputFormat::Json, true))
+                .expect("buggy");
+        assert_eq!(json["healthy"], false);
+        assert_eq!(json["checks"][2]["status"], "fail");
+        assert_eq!(json["checks"][2]["hint"], "fix it");
+
+        let mut report = Report::default();
+        report.checks.push(Check::pass("aa", "fine"));
+        report.checks.push(Check::unverified("b", "unknown"));
+        assert!(report.is_healthy());
+        assert_eq!(
+            report.to_output(OutputFormat::Text, true),
+            "[pass]       aa  fine\n[unverified] b   unknown"
+        );
+    }
+}
@@ -7,13 +7,24 @@
 // with the license.
 //
 
+use core::fmt;
+use std::io;
+use std::path::PathBuf;
+
 use clap::builder::StyledStr;
 use wda::WdaError;
 
 #[derive(Debug)]
 pub enum MafaError {
-    Buggy,
-    BugFound(u16),
+    ///
+    /// something mafa relies on does not hold, which is a bug of mafa
+    Bug(&'static str),
+    ///
+    /// reading or writing the path failed
+    Io(PathBuf, io::Error),
+    ///
+    /// data mafa produced is not encodable as JSON
+    Json(serde_json::Error),
     //
     ClapMatchError(StyledStr),
     //
@@ -26,6 +37,31 @@ pub enum MafaError {
     InvalidTwitterUsername,
     InvalidNumTweets,
     InvalidWrapWidth,
+    ///
+    /// with the file, "-" for standard input, and why it is not readable
+    InvalidFromFile(PathBuf, io::Error),
+    InvalidResultTtl,
+    ///
+    /// with the option's long name
+    InvalidRetryPolicy(&'static str),
+    ///
+    /// with the cassette directory or file, and why it is not usable
+    InvalidCassetteDir(PathBuf, io::Error),
+    InvalidEventLog(PathBuf, io::Error),
+    InvalidElapTrace(PathBuf, io::Error),
+    ///
+    /// with the config file, and why it is not valid, e.g., a TOML error
+    InvalidConfig(PathBuf, io::Error),
+    InvalidTheme(String),
+    InvalidDataDir,
+    InvalidDaemonSocket,
+    InvalidListenAddr,
+    ///
+    /// with the file exported to or imported from, and why it is not
+    /// usable
+    InvalidCacheFile(PathBuf, io::Error),
+    InvalidCacheRemote,
+    ResultNotStored,
     //
     WebDrvCmdRejected(String, String),
     UnexpectedWda(WdaError),
@@ -47,10 +83,28 @@ pub enum MafaError {
     CamdLevelNotRecoginized(u8),
     CacheCorrupted,
     //
-    MafaDataCacheNotFound,
+    ///
+    /// with the id of the cache, i.e., the component it belongs to
+    MafaDataCacheNotFound(String),
+    ///
+    /// another mafa holds the lock of a cache for too long
+    CacheLockTimeout,
+    ///
+    /// with the URL not reachable
+    RemoteCacheNotReachable(String),
+    ///
+    /// with the URL whose data does not match the checksum in the
+    /// manifest, or has no entry
+    RemoteCacheRejected(String),
+    ///
+    /// with the URL of the manifest, not reachable or listing nothing
+    RemoteManifestMissing(String),
     //
     RequireLogin,
     MustGui,
+    ///
+    /// by Ctrl-C in interactive mode
+    Cancelled,
 }
 
 #[derive(Debug)]
@@ -61,4 +115,322 @@ pub enum CacheRebuildFailKind {
     UpathLenZero,
 }
 
+impl fmt::Display for MafaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MafaError::Bug(what) => write!(f, "bug found, {}, please report it", what),
+            MafaError::Io(pbuf, _) => write!(f, "{} is not accessible", pbuf.display()),
+            MafaError::Json(_) => write!(f, "data is not encodable as JSON"),
+            MafaError::ClapMatchError(ca_err) => write!(f, "{}", ca_err),
+            MafaError::InvalidTimeoutPageLoad => {
+                write!(f, "page load timeout is not a valid value")
+            }
+            MafaError::InvalidTimeoutScript => write!(f, "script timeout is not a valid value"),
+            MafaError::InvalidSocks5Proxy => write!(f, "socks5 proxy is not a valid value"),
+            MafaError::InvalidSourceLang => write!(f, "invalid source language"),
+            MafaError::InvalidTargetLang => write!(f, "invalid target language"),
+            MafaError::InvalidWords => write!(f, "invalid words"),
+            MafaError::InvalidTwitterUsername => write!(f, "invalid twitter username"),
+            MafaError::InvalidNumTweets => write!(f, "invalid number of tweets"),
+            MafaError::InvalidWrapWidth => write!(f, "invalid wrap width"),
+            MafaError::InvalidFromFile(pbuf, _) => {
+                write!(f, "file to read words {} is not readable", pbuf.display())
+            }
+            MafaError::InvalidResultTtl => write!(f, "invalid result TTL"),
+            MafaError::InvalidRetryPolicy(longopt) => write!(f, "invalid value of --{}", longopt),
+            MafaError::InvalidCassetteDir(pbuf, _) => {
+                write!(f, "cassette {} is not usable", pbuf.display())
+            }
+            MafaError::InvalidEventLog(pbuf, _) => {
+                write!(f, "event log file {} is not writable", pbuf.display())
+            }
+            MafaError::InvalidElapTrace(pbuf, _) => {
+                write!(f, "elapsed trace file {} is not writable", pbuf.display())
+            }
+            MafaError::InvalidConfig(pbuf, _) => write!(
+                f,
+                "config file {} is not valid TOML or has non-scalar values",
+                pbuf.display()
+            ),
+            MafaError::InvalidTheme(role) => {
+                write!(f, "theme entry `{}` in config file is not valid", role)
+            }
+            MafaError::InvalidDataDir => {
+                write!(f, "data directory is not usable, try setting MAFA_HOME")
+            }
+            MafaError::InvalidDaemonSocket => {
+                write!(
+                    f,
+                    "daemon socket is used by another daemon, or not creatable"
+                )
+            }
+            MafaError::InvalidListenAddr => write!(f, "address to listen on is invalid or in use"),
+            MafaError::InvalidCacheFile(pbuf, _) => write!(
+                f,
+                "cache file {} is not accessible or not exported by mafa",
+                pbuf.display()
+            ),
+            MafaError::InvalidCacheRemote => write!(
+                f,
+                "invalid remote cache URL, expect file://, http:// or https://"
+            ),
+            MafaError::ResultNotStored => {
+                write!(f, "no stored result for the request, while offline")
+            }
+            MafaError::WebDrvCmdRejected(err, msg) => {
+                if msg.contains("neterror")
+                    && (msg.contains("dnsNotFound") || msg.contains("nssFailure"))
+                {
+                    write!(f, "internet connection failed")
+                } else if msg.contains("neterror") && msg.contains("proxyConnectFailure") {
+                    write!(f, "proxy connection failed")
+                } else if err == "script timeout" {
+                    write!(f, "script evaluation timeout")
+                } else {
+                    write!(f, "webdriver cmd rejected({},{})", err, msg)
+                }
+            }
+            MafaError::UnexpectedWda(err_wda) => write!(f, "unexpected wda error: {:?}", err_wda),
+            MafaError::InvalidUseProfile => write!(f, "invalid profile id"),
+            MafaError::FirefoxNotFound => write!(f, "Firefox not installed"),
+            MafaError::CacheRebuildFail(fk) => write!(f, "rebuild cache failed: {:?}", fk),
+            MafaError::CacheNotBuildable => write!(f, "cache not buildable"),
+            MafaError::AllCachesInvalid => write!(f, "all caches invalid"),
+            MafaError::DataFetchedNotReachable => write!(f, "website is not reachable"),
+            MafaError::UpathNotFound => write!(f, "element not found in the page"),
+            MafaError::UpathLenNotMatched | MafaError::UpathValNotMatched => {
+                write!(f, "page layout not matched with the cache")
+            }
+            MafaError::TweetNotRecoginized(n) => write!(f, "tweet not recognized({})", n),
+            MafaError::CamdLevelNotRecoginized(n) => {
+                write!(f, "word level not recognized({})", n)
+            }
+            MafaError::CacheCorrupted => write!(f, "cache corrupted"),
+            MafaError::MafaDataCacheNotFound(cache_id) => write!(
+                f,
+                "cache {} not built yet, try `mafa cache rebuild`",
+                cache_id
+            ),
+            MafaError::CacheLockTimeout => write!(
+                f,
+                "cache is locked by another mafa for too long, is it hung?"
+            ),
+            MafaError::RemoteCacheNotReachable(url) => write!(
+                f,
+                "remote cache {} is not reachable, check --cache-remote",
+                url
+            ),
+            MafaError::RemoteCacheRejected(url) => write!(
+                f,
+                "remote cache {} does not match its checksum, local cache kept",
+                url
+            ),
+            MafaError::RemoteManifestMissing(url) => write!(
+                f,
+                "remote cache manifest {} is missing, check --cache-manifest",
+                url
+            ),
+            MafaError::RequireLogin => write!(f, "login required"),
+            MafaError::MustGui => write!(f, "GUI mode required, try again with --gui option"),
+            MafaError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+///
+/// Exit codes of mafa, which are stable across versions, every error is
+/// mapped to one of them by [`MafaError::exit_code`].
+pub mod exit_code {
+    pub const OK: u8 = 0;
+    /// options, config values or files given are invalid
+    pub const INVALID_INPUT: u8 = 1;
+    /// the website requires logging in, possibly in GUI mode
+    pub const LOGIN_REQUIRED: u8 = 2;
+    /// Firefox is not installed
+    pub const BROWSER_MISSING: u8 = 3;
+    /// the website or the remote caches are not reachable, or timed out
+    pub const NETWORK: u8 = 4;
+    /// the website's layout changed, caches no longer match it
+    pub const LAYOUT_CHANGED: u8 = 5;
+    /// the data directory, local caches or stored results are not usable
+    pub const LOCAL_DATA: u8 = 6;
+    /// the browser failed otherwise
+    pub const BROWSER_FAILED: u8 = 7;
+    /// some entries of a batch failed, results of the others are printed
+    pub const PARTIAL: u8 = 8;
+    /// cancelled by Ctrl-C, as if killed by SIGINT
+    pub const CANCELLED: u8 = 130;
+    /// a bug of mafa
+    pub const BUG: u8 = 70;
+
+    pub(crate) const HELPER: &str = "Exit codes:
+  0   success
+  1   invalid input, e.g., options, config values or files given
+  2   login required, possibly in GUI mode
+  3   Firefox not installed
+  4   website or remote caches not reachable, or timed out
+  5   website's layout changed, caches no longer match it
+  6   data directory, local caches or stored results not usable
+  7   browser failed otherwise
+  8   some entries of a batch failed, the others succeeded
+  130 cancelled by Ctrl-C
+  70  bug of mafa";
+}
+
+impl MafaError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            MafaError::ClapMatchError(_)
+            | MafaError::InvalidTimeoutPageLoad
+            | MafaError::InvalidTimeoutScript
+            | MafaError::InvalidSocks5Proxy
+            | MafaError::InvalidSourceLang
+            | MafaError::InvalidTargetLang
+            | MafaError::InvalidWords
+            | MafaError::InvalidTwitterUsername
+            | MafaError::InvalidNumTweets
+            | MafaError::InvalidWrapWidth
+            | MafaError::InvalidFromFile(..)
+            | MafaError::InvalidResultTtl
+            | MafaError::InvalidRetryPolicy(_)
+            | MafaError::InvalidCassetteDir(..)
+            | MafaError::InvalidEventLog(..)
+            | MafaError::InvalidElapTrace(..)
+            | MafaError::InvalidConfig(..)
+            | MafaError::InvalidTheme(_)
+            | MafaError::InvalidListenAddr
+            | MafaError::InvalidCacheFile(..)
+            | MafaError::InvalidCacheRemote
+            | MafaError::InvalidUseProfile => exit_code::INVALID_INPUT,
+
+            MafaError::RequireLogin | MafaError::MustGui => exit_code::LOGIN_REQUIRED,
+
+            MafaError::FirefoxNotFound => exit_code::BROWSER_MISSING,
+
+            MafaError::WebDrvCmdRejected(err, msg)
+                if err.contains("timeout")
+                    || msg.contains("Timed out")
+                    || msg.contains("neterror") =>
+            {
+                exit_code::NETWORK
+            }
+            MafaError::DataFetchedNotReachable
+            | MafaError::RemoteCacheNotReachable(_)
+            | MafaError::RemoteCacheRejected(_)
+            | MafaError::RemoteManifestMissing(_) => exit_code::NETWORK,
+
+            MafaError::AllCachesInvalid
+            | MafaError::CacheRebuildFail(_)
+            | MafaError::CacheNotBuildable
+            | MafaError::UpathNotFound
+            | MafaError::UpathLenNotMatched
+            | MafaError::UpathValNotMatched
+            | MafaError::TweetNotRecoginized(_)
+            | MafaError::CamdLevelNotRecoginized(_) => exit_code::LAYOUT_CHANGED,
+
+            MafaError::Io(..)
+            | MafaError::InvalidDataDir
+            | MafaError::InvalidDaemonSocket
+            | MafaError::ResultNotStored
+            | MafaError::CacheCorrupted
+            | MafaError::MafaDataCacheNotFound(_)
+            | MafaError::CacheLockTimeout => exit_code::LOCAL_DATA,
+
+            MafaError::WebDrvCmdRejected(..) | MafaError::UnexpectedWda(_) => {
+                exit_code::BROWSER_FAILED
+            }
+
+            MafaError::Cancelled => exit_code::CANCELLED,
+
+            MafaError::Bug(_) | MafaError::Json(_) => exit_code::BUG,
+        }
+    }
+}
+
+impl std::error::Error for MafaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MafaError::Io(_, err_io)
+            | MafaError::InvalidFromFile(_, err_io)
+            | MafaError::InvalidCassetteDir(_, err_io)
+            | MafaError::InvalidEventLog(_, err_io)
+            | MafaError::InvalidElapTrace(_, err_io)
+            | MafaError::InvalidConfig(_, err_io)
+            | MafaError::InvalidCacheFile(_, err_io) => Some(err_io),
+            MafaError::Json(err_json) => Some(err_json),
+            _ => None,
+        }
+    }
+}
+
 pub type Result<T> = core::result::Result<T, MafaError>;
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn display_1() {
+        assert_eq!(
+            MafaError::RemoteCacheRejected("file:///tmp/gtrans".to_string()).to_string(),
+            "remote cache file:///tmp/gtrans does not match its checksum, local cache kept"
+        );
+        assert_eq!(
+            MafaError::WebDrvCmdRejected(
+                "unknown error".to_string(),
+                "about:neterror?e=dnsNotFound".to_string()
+            )
+            .to_string(),
+            "internet connection failed"
+        );
+    }
+
+    #[test]
+    fn exit_code_1() {
+        assert_eq!(MafaError::InvalidSourceLang.exit_code(), 1);
+        assert_eq!(MafaError::RequireLogin.exit_code(), 2);
+        assert_eq!(MafaError::MustGui.exit_code(), 2);
+        assert_eq!(MafaError::FirefoxNotFound.exit_code(), 3);
+        assert_eq!(
+            MafaError::WebDrvCmdRejected("timeout".to_string(), "".to_string()).exit_code(),
+            4
+        );
+        assert_eq!(MafaError::AllCachesInvalid.exit_code(), 5);
+        assert_eq!(
+            MafaError::CacheRebuildFail(CacheRebuildFailKind::UpathNotFound).exit_code(),
+            5
+        );
+        assert_eq!(MafaError::CacheLockTimeout.exit_code(), 6);
+        assert_eq!(
+            MafaError::WebDrvCmdRejected("unknown".to_string(), "".to_string()).exit_code(),
+            7
+        );
+        assert_eq!(MafaError::Cancelled.exit_code(), 130);
+        assert_eq!(MafaError::Bug("lock poisoned").exit_code(), 70);
+    }
+
+    #[test]
+    fn source_1() {
+        let err = MafaError::Io(
+            PathBuf::from("/nonexistent/mafa"),
+            io::Error::new(io::ErrorKind::NotFound, "gone"),
+        );
+        assert_eq!(err.to_string(), "/nonexistent/mafa is not accessible");
+        assert_eq!(err.source().expect("buggy").to_string(), "gone");
+
+        let err = MafaError::InvalidConfig(
+            PathBuf::from("/tmp/config.toml"),
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "value of `socks5` is not a scalar",
+            ),
+        );
+        assert_eq!(
+            err.source().expect("buggy").to_string(),
+            "value of `socks5` is not a scalar"
+        );
+
+        assert!(MafaError::AllCachesInvalid.source().is_none());
+    }
+}
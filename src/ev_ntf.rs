@@ -8,11 +8,19 @@
 //
 
 use core::time::Duration;
+use std::fs::File;
+use std::fs::OpenOptions;
 use std::io;
 use std::io::Write;
+use std::path::PathBuf;
 use std::time::Instant;
 
+use serde::Serialize;
+
 use crate::error::MafaError;
+use crate::error::Result;
+use crate::mafadata::secs_since_epoch;
+use crate::theme;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Category {
@@ -23,6 +31,24 @@ pub enum Category {
 }
 
 impl Category {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Category::Mafa => "mafa",
+            Category::Twtl => "twtl",
+            Category::Gtrans => "gtrans",
+            Category::Camd => "camd",
+        }
+    }
+
+    fn tid(&self) -> u32 {
+        match self {
+            Category::Mafa => 1,
+            Category::Twtl => 2,
+            Category::Gtrans => 3,
+            Category::Camd => 4,
+        }
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             Category::Mafa => "Mafa",
@@ -96,6 +122,44 @@ pub enum MafaEvent {
     },
 }
 
+impl MafaEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MafaEvent::Initialize { .. } => "Initialize",
+            MafaEvent::BuildCache { .. } => "BuildCache",
+            MafaEvent::FetchResult { .. } => "FetchResult",
+            MafaEvent::TryNextCache { .. } => "TryNextCache",
+            MafaEvent::CacheRetry { .. } => "CacheRetry",
+            MafaEvent::SrvTempUnavRetry { .. } => "SrvTempUnavRetry",
+            MafaEvent::ConnectTimeoutRetry { .. } => "ConnectTimeoutRetry",
+            MafaEvent::SimpleProgress { .. } => "SimpleProgress",
+            MafaEvent::FatalMafaError { .. } => "FatalMafaError",
+            MafaEvent::HandlerMissed { .. } => "HandlerMissed",
+            MafaEvent::ExactWhatRequest { .. } => "ExactWhatRequest",
+            MafaEvent::ExactUserRequest { .. } => "ExactUserRequest",
+            MafaEvent::WaitSecsMayInterrupt { .. } => "WaitSecsMayInterrupt",
+        }
+    }
+
+    pub fn cate(&self) -> Category {
+        match self {
+            MafaEvent::Initialize { cate, .. }
+            | MafaEvent::BuildCache { cate, .. }
+            | MafaEvent::FetchResult { cate, .. }
+            | MafaEvent::TryNextCache { cate, .. }
+            | MafaEvent::CacheRetry { cate, .. }
+            | MafaEvent::SrvTempUnavRetry { cate, .. }
+            | MafaEvent::ConnectTimeoutRetry { cate, .. }
+            | MafaEvent::SimpleProgress { cate, .. }
+            | MafaEvent::FatalMafaError { cate, .. }
+            | MafaEvent::HandlerMissed { cate, .. }
+            | MafaEvent::ExactWhatRequest { cate, .. }
+            | MafaEvent::ExactUserRequest { cate, .. }
+            | MafaEvent::WaitSecsMayInterrupt { cate, .. } => *cate,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum EurKind {
     NoSubCmd,
@@ -104,21 +168,94 @@ pub enum EurKind {
     TwtlResult,    /* twitter timeline result */
     TwtlTryLogin,  /* login/logout twitter account */
     GtransResult,  /* google translate translation result */
+    GtransPartial, /* batch translation result, some entries failed */
     GtransAllLang, /* list all supported lang */
     CamdResult,
+    ConfigShow,
+    DaemonStarted,
+    HttpdStarted,
+    CacheList,
+    CacheShow,
+    CacheChanged,
+    DoctorReport,
+}
+
+///
+/// A receiver of events, the notifier fans every event out to all sinks
+/// registered, in the order they were registered.
+///
+/// `prev` is the last event of the timeline before `ev`, if any, and
+/// `elap` is the time elapsed since the notifier was created. Finishing
+/// retries that have not retried are delivered, but never become `prev`,
+/// see [`EventNotifier::notify`].
+pub trait EventSink: Send {
+    fn accept(&mut self, ev: &MafaEvent, prev: Option<&MafaEvent>, elap: Duration);
 }
 
-#[derive(Debug)]
 pub struct EventNotifier {
-    smode: bool,
-    pub(crate) color: bool,
+    human: HumanSink,
+    sinks: Vec<Box<dyn EventSink>>,
     queue: Vec<EventDetail>,
     wall_clock: Instant,
 }
 
+impl core::fmt::Debug for EventNotifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EventNotifier")
+            .field("human", &self.human)
+            .field("sinks", &self.sinks.len())
+            .field("queue", &self.queue)
+            .field("wall_clock", &self.wall_clock)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 struct EventDetail(MafaEvent, Duration);
 
+///
+/// The built-in sink, which renders events as progress messages and
+/// errors for humans.
+#[derive(Debug)]
+pub struct HumanSink {
+    smode: bool,
+    jmode: bool,
+    color: bool,
+    progress: ProgressLine,
+}
+
+///
+/// The line of what is in progress, it is redrawn in place on a
+/// terminal, or printed as new lines every `PROGRESS_PERIOD` otherwise.
+#[derive(Debug)]
+struct ProgressLine {
+    is_tty: bool,
+    ///
+    /// (category, total) of the unfinished progress
+    of: Option<(Category, Option<u32>)>,
+    first: u32,
+    since: Duration,
+    curr: u32,
+    drawn: Duration,
+}
+
+const PROGRESS_PERIOD: Duration = Duration::from_secs(5);
+
+///
+/// Write every event as one JSON object per line.
+#[derive(Debug)]
+pub struct JsonLinesSink<W: Write + Send> {
+    w: W,
+}
+
+///
+/// Append every event as one line of text to a log file, prefixed with
+/// the seconds since the epoch.
+#[derive(Debug)]
+pub struct LogFileSink {
+    file: File,
+}
+
 macro_rules! print_not {
     ($cond:expr, $print_what:expr) => {
         if !$cond {
@@ -171,42 +308,158 @@ macro_rules! eprintln_not {
     };
 }
 
-impl EventNotifier {
+impl Default for HumanSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HumanSink {
     pub fn new() -> Self {
-        EventNotifier {
+        HumanSink {
             smode: false,
-            color: true,
-            queue: vec![],
-            wall_clock: Instant::now(),
+            jmode: false,
+            color: theme::color_wanted(),
+            progress: ProgressLine::new(unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1),
         }
     }
 
-    pub fn set_silent(&mut self) {
-        self.smode = true;
+    fn is_quiet(&self) -> bool {
+        self.smode || self.jmode
     }
 
-    pub fn set_nsilent(&mut self) {
-        self.smode = false;
+    ///
+    /// `is_cont` tells if this continues the unfinished progress, which
+    /// may not be the previous event, a new progress starts otherwise.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_progress(
+        &mut self,
+        cate: Category,
+        label: &str,
+        curr: u32,
+        total: Option<u32>,
+        is_fin: bool,
+        is_cont: bool,
+        prev: Option<&MafaEvent>,
+        elap: Duration,
+    ) {
+        // other events may have been drawn in between
+        let is_adjacent = is_cont
+            && matches!(
+                prev,
+                Some(MafaEvent::SimpleProgress { .. }) | Some(MafaEvent::CacheRetry { .. })
+            );
+        if !is_adjacent && !is_final(prev) {
+            println_not!(self.is_quiet(), "");
+        }
+        if !is_cont {
+            self.progress.start(curr, elap);
+            self.progress.of = Some((cate, total));
+        }
+        self.progress.curr = curr;
+
+        let text = format!(
+            "[{}] {}{}",
+            cate.as_str(),
+            label,
+            progress_text(curr, total, self.progress.rate(curr, elap))
+        );
+
+        if self.progress.is_tty {
+            print_not!(self.is_quiet(), "\r{}\u{1b}[K", text);
+        } else if !is_adjacent || is_fin || elap - self.progress.drawn >= PROGRESS_PERIOD {
+            // end the line drawn previously
+            if is_adjacent {
+                println_not!(self.is_quiet(), "");
+            }
+            print_not!(self.is_quiet(), "{}", text);
+            self.progress.drawn = elap;
+        }
+
+        if is_fin {
+            println_not!(self.is_quiet(), "");
+            self.progress.of = None;
+        }
     }
+}
 
-    pub fn set_color(&mut self) {
-        self.color = true;
+impl ProgressLine {
+    fn new(is_tty: bool) -> Self {
+        ProgressLine {
+            is_tty,
+            of: None,
+            first: 0,
+            since: Duration::ZERO,
+            curr: 0,
+            drawn: Duration::ZERO,
+        }
     }
 
-    pub fn set_nocolor(&mut self) {
-        self.color = false;
+    fn start(&mut self, curr: u32, elap: Duration) {
+        self.first = curr;
+        self.since = elap;
+        self.curr = curr;
+        self.drawn = elap;
     }
 
     ///
-    /// Note that, this is not supposed to respond the errors directly,
-    /// but events, one of which is `FatalMafaError`, which is the
-    /// entry to handle errors.
-    pub fn notify(&mut self, ev: MafaEvent) {
-        let mut is_skip_push = false;
+    /// Per second, not known until the progress has moved.
+    fn rate(&self, curr: u32, elap: Duration) -> Option<f64> {
+        let secs = (elap - self.since).as_secs_f64();
+        if curr <= self.first || secs == 0.0 {
+            return None;
+        }
 
-        match ev {
+        Some((curr - self.first) as f64 / secs)
+    }
+}
+
+///
+/// e.g., "3/10 (30%) 1.5/s, ETA 5s", or "#3 (1.5/s)" if the total is not
+/// known.
+fn progress_text(curr: u32, total: Option<u32>, rate: Option<f64>) -> String {
+    match total {
+        Some(total) => {
+            let mut text = format!(
+                "{}/{} ({}%)",
+                curr,
+                total,
+                ((curr as f64 / total as f64) * 100.0) as u32
+            );
+            if let Some(rate) = rate {
+                let eta = (total.saturating_sub(curr) as f64 / rate).ceil() as u64;
+                text += &format!(" {:.1}/s, ETA {}", rate, fmt_secs(eta));
+            }
+            text
+        }
+        None => {
+            let mut text = format!("#{}", curr);
+            if let Some(rate) = rate {
+                text += &format!(" ({:.1}/s)", rate);
+            }
+            text
+        }
+    }
+}
+
+fn fmt_secs(secs: u64) -> String {
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+impl EventSink for HumanSink {
+    fn accept(&mut self, ev: &MafaEvent, prev: Option<&MafaEvent>, elap: Duration) {
+        // a retry finishes only if it has retried previously
+        if is_redundant(ev, prev) {
+            return;
+        }
+
+        match *ev {
             MafaEvent::ExactUserRequest { ref output, .. } => {
-                if !self.is_prev_final() {
+                if !is_final(prev) && !self.jmode {
                     println!();
                 }
                 // if start by _, we dont print anything
@@ -216,15 +469,16 @@ impl EventNotifier {
             }
 
             MafaEvent::WaitSecsMayInterrupt { cate, count, safe } => {
-                if !self.is_prev_final() {
+                if !is_final(prev) {
                     println!();
                 }
 
                 print!("[{}] Please finish in {} seconds, ", cate.as_str(), count,);
+                let theme = theme::current(!self.color);
                 if safe {
-                    print!("press \u{1b}[40;1mCtrl-C\u{1b}[0m here if finished.",);
+                    print!("press {} here if finished.", theme.key.paint("Ctrl-C"));
                 } else {
-                    print!("do \u{1b}[40;1mNOT\u{1b}[0m press other keys.",);
+                    print!("do {} press other keys.", theme.key.paint("NOT"));
                 }
                 println!();
             }
@@ -232,105 +486,91 @@ impl EventNotifier {
             //
             MafaEvent::Initialize { cate, is_fin } => {
                 if is_fin {
-                    if self.queue.len() > 0 {
-                        let last_ev = &self.queue.last().unwrap().0;
+                    if let Some(last_ev) = prev {
                         if let MafaEvent::Initialize { .. } = last_ev {
-                            println_not!(self.smode, "ok");
+                            println_not!(self.is_quiet(), "ok");
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
 
-                            println_not!(self.smode, "[{}] Initialize...ok", cate.as_str());
+                            println_not!(self.is_quiet(), "[{}] Initialize...ok", cate.as_str());
                         }
                     }
                 } else {
-                    print_not!(self.smode, "[{}] Initialize...", cate.as_str());
+                    print_not!(self.is_quiet(), "[{}] Initialize...", cate.as_str());
                 }
             }
 
             MafaEvent::BuildCache { cate, is_fin } => {
                 if is_fin {
-                    if self.queue.len() > 0 {
-                        let last_ev = &self.queue.last().unwrap().0;
+                    if let Some(last_ev) = prev {
                         if let MafaEvent::BuildCache { .. } = last_ev {
-                            println_not!(self.smode, "ok");
+                            println_not!(self.is_quiet(), "ok");
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
 
-                            println_not!(self.smode, "[{}] Building cache...ok", cate.as_str());
+                            println_not!(
+                                self.is_quiet(),
+                                "[{}] Building cache...ok",
+                                cate.as_str()
+                            );
                         }
                     }
                 } else {
-                    print_not!(self.smode, "[{}] Building cache...", cate.as_str());
+                    print_not!(self.is_quiet(), "[{}] Building cache...", cate.as_str());
                 }
             }
 
             MafaEvent::FetchResult { cate, is_fin } => {
                 if is_fin {
-                    if self.queue.len() > 0 {
-                        let last_ev = &self.queue.last().unwrap().0;
-
+                    if let Some(last_ev) = prev {
                         if let MafaEvent::FetchResult { .. } = last_ev {
-                            println_not!(self.smode, "ok");
+                            println_not!(self.is_quiet(), "ok");
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
-                            println_not!(self.smode, "[{}] Fetch...ok", cate.as_str());
+                            println_not!(self.is_quiet(), "[{}] Fetch...ok", cate.as_str());
                         }
                     }
                 } else {
-                    print_not!(self.smode, "[{}] Fetch...", cate.as_str());
+                    print_not!(self.is_quiet(), "[{}] Fetch...", cate.as_str());
                 }
             }
 
             MafaEvent::CacheRetry { cate, is_fin } => {
-                if self.queue.len() > 0 {
-                    let last_ev = &self.queue.last().unwrap().0;
-                    if is_fin {
-                        // only has retried previously
-                        if let MafaEvent::CacheRetry { .. } = last_ev {
-                            println_not!(self.smode, "");
-                        } else {
-                            is_skip_push = true;
-                        }
-                    } else {
-                        if let MafaEvent::CacheRetry {
-                            cate: o_cate,
-                            is_fin: o_is_fin,
-                        } = last_ev
-                        {
-                            if o_cate == &cate && o_is_fin == &is_fin {
-                                print_not!(self.smode, ".");
-                            }
-                        } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
-                            }
-                            print_not!(
-                                self.smode,
-                                "[{}] Build cache failed, retrying",
-                                cate.as_str()
-                            );
-                        }
-                    }
-                } else {
-                    unreachable!(); // FIXME: should be an mafaerror
-                }
+                let is_cont = match prev {
+                    Some(MafaEvent::CacheRetry { cate: o_cate, .. }) => o_cate == &cate,
+                    _ => false,
+                };
+                // the finishing one is not a retry
+                let curr = match (is_cont, is_fin) {
+                    (true, true) => self.progress.curr,
+                    (true, false) => self.progress.curr + 1,
+                    _ => 1,
+                };
+
+                self.draw_progress(
+                    cate,
+                    "Build cache failed, retrying ",
+                    curr,
+                    None,
+                    is_fin,
+                    is_cont,
+                    prev,
+                    elap,
+                );
             }
 
             MafaEvent::SrvTempUnavRetry { cate, is_fin } => {
-                if self.queue.len() > 0 {
-                    let last_ev = &self.queue.last().unwrap().0;
+                if let Some(last_ev) = prev {
                     if is_fin {
                         // only has retried previously
                         if let MafaEvent::SrvTempUnavRetry { .. } = last_ev {
-                            println_not!(self.smode, "");
-                        } else {
-                            is_skip_push = true;
+                            println_not!(self.is_quiet(), "");
                         }
                     } else {
                         if let MafaEvent::SrvTempUnavRetry {
@@ -339,14 +579,14 @@ impl EventNotifier {
                         } = last_ev
                         {
                             if o_cate == &cate && o_is_fin == &is_fin {
-                                print_not!(self.smode, ".");
+                                print_not!(self.is_quiet(), ".");
                             }
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
                             print_not!(
-                                self.smode,
+                                self.is_quiet(),
                                 "[{}] Service temporarily unavaiable, retrying",
                                 cate.as_str()
                             );
@@ -358,14 +598,11 @@ impl EventNotifier {
             }
 
             MafaEvent::ConnectTimeoutRetry { cate, is_fin } => {
-                if self.queue.len() > 0 {
-                    let last_ev = &self.queue.last().unwrap().0;
+                if let Some(last_ev) = prev {
                     if is_fin {
                         // only has retried previously
                         if let MafaEvent::ConnectTimeoutRetry { .. } = last_ev {
-                            println_not!(self.smode, "");
-                        } else {
-                            is_skip_push = true;
+                            println_not!(self.is_quiet(), "");
                         }
                     } else {
                         if let MafaEvent::ConnectTimeoutRetry {
@@ -374,14 +611,14 @@ impl EventNotifier {
                         } = last_ev
                         {
                             if o_cate == &cate && o_is_fin == &is_fin {
-                                print_not!(self.smode, ".");
+                                print_not!(self.is_quiet(), ".");
                             }
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
                             print_not!(
-                                self.smode,
+                                self.is_quiet(),
                                 "[{}] Connection timeout, retrying",
                                 cate.as_str()
                             );
@@ -393,14 +630,11 @@ impl EventNotifier {
             }
 
             MafaEvent::TryNextCache { cate, is_fin } => {
-                if self.queue.len() > 0 {
-                    let last_ev = &self.queue.last().unwrap().0;
+                if let Some(last_ev) = prev {
                     if is_fin {
                         // only has retried previously
                         if let MafaEvent::TryNextCache { .. } = last_ev {
-                            println_not!(self.smode, "");
-                        } else {
-                            is_skip_push = true;
+                            println_not!(self.is_quiet(), "");
                         }
                     } else {
                         if let MafaEvent::TryNextCache {
@@ -409,13 +643,13 @@ impl EventNotifier {
                         } = last_ev
                         {
                             if o_cate == &cate && o_is_fin == &is_fin {
-                                print_not!(self.smode, ".");
+                                print_not!(self.is_quiet(), ".");
                             }
                         } else {
-                            if !self.is_prev_final() {
-                                println_not!(self.smode, "");
+                            if !is_final(prev) {
+                                println_not!(self.is_quiet(), "");
                             }
-                            print_not!(self.smode, "[{}] Trying other caches", cate.as_str());
+                            print_not!(self.is_quiet(), "[{}] Trying other caches", cate.as_str());
                         }
                     }
                 } else {
@@ -430,367 +664,56 @@ impl EventNotifier {
                 curr,
                 is_fin,
             } => {
-                if self.queue.len() > 0 {
-                    let last_ev = &self.queue.last().unwrap().0;
-                    if let MafaEvent::SimpleProgress {
-                        cate: o_cate,
-                        total: o_total,
-                        curr: _,
-                        is_fin: _,
-                    } = last_ev
-                    {
-                        if o_cate == &cate && o_total == &total {
-                            print_not!(self.smode, "\r");
-                        }
-                    } else {
-                        if !self.is_prev_final() {
-                            println_not!(self.smode, "");
-                        }
-                    }
-                    print_not!(
-                        self.smode,
-                        "[{}] {}/{} ({}%)",
-                        cate.as_str(),
-                        curr,
-                        total,
-                        ((curr as f64 / total as f64) * 100.0) as u32
-                    );
-                    if is_fin {
-                        println_not!(self.smode, "");
-                    }
-                } else {
-                    unreachable!(); // FIXME: should be an mafaerror
-                }
-            }
-
-            MafaEvent::FatalMafaError { cate, ref err } => match err {
-                MafaError::InvalidSocks5Proxy => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "socks5 proxy is not a valid value({})",
-                        cate.as_str()
-                    );
-                }
-
-                MafaError::InvalidTimeoutPageLoad => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "page load timeout is not a valid value({})",
-                        cate.as_str()
-                    );
-                }
-
-                MafaError::InvalidTimeoutScript => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "script timeout is not a valid value({})",
-                        cate.as_str()
-                    );
-                }
-
-                MafaError::InvalidSourceLang => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "invalid source language({})", cate.as_str());
-                }
-
-                MafaError::InvalidTargetLang => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "invalid target language({})", cate.as_str());
-                }
-
-                MafaError::AllCachesInvalid => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "all caches invalid({})", cate.as_str());
-                }
+                // e.g., FetchResult of each entry of a batch comes in
+                // between
+                let is_cont = self.progress.of == Some((cate, Some(total)));
 
-                MafaError::CacheNotBuildable => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "cache not buildable({})", cate.as_str());
-                }
-
-                MafaError::RequireLogin => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "login required ({})", cate.as_str());
-                }
-
-                MafaError::MustGui => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "GUI mode required, try again with --gui option ({})",
-                        cate.as_str()
-                    );
-                }
-
-                MafaError::DataFetchedNotReachable => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
+                self.draw_progress(cate, "", curr, Some(total), is_fin, is_cont, prev, elap);
+            }
 
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "website is not reachable ({})", cate.as_str());
+            MafaEvent::FatalMafaError { cate, ref err } => {
+                if !is_final(prev) {
+                    eprintln_not!(self.smode, "");
                 }
 
-                MafaError::ClapMatchError(ca_err) => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
+                // clap has its own prefix
+                if let MafaError::ClapMatchError(ca_err) = err {
                     eprint_not!(self.smode, "{}", ca_err.ansi());
-                }
-
-                MafaError::WebDrvCmdRejected(err, msg) => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-
-                    if msg.contains("neterror")
-                        && (msg.contains("dnsNotFound") || msg.contains("nssFailure"))
-                    {
-                        eprintln_not!(self.smode, "internet connection failed");
-                    } else if msg.contains("neterror") && msg.contains("proxyConnectFailure") {
-                        eprintln_not!(self.smode, "proxy connection failed");
-                    } else if err == "script timeout" {
-                        eprintln_not!(self.smode, "script evaluation timeout ({})", cate.as_str());
-                    } else {
-                        eprintln_not!(
-                            self.smode,
-                            "webdriver cmd rejected({},{},{})",
-                            cate.as_str(),
-                            err,
-                            msg
-                        );
-                    }
-                }
-
-                MafaError::UnexpectedWda(err_wda) => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "unexpected wda error({}): {:?}",
-                        cate.as_str(),
-                        err_wda
-                    );
-                }
-
-                MafaError::InvalidUseProfile => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
-
+                } else {
                     eprint_not!(
                         self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
+                        "{}",
+                        theme::current(!self.color).error.paint("error: ")
                     );
-                    eprintln_not!(self.smode, "invalid profile id ({})", cate.as_str());
-                }
 
-                MafaError::CacheRebuildFail(fk) => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
-                    }
+                    if let MafaError::FirefoxNotFound = err {
+                        eprintln_not!(self.smode, "{}", err);
 
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(
-                        self.smode,
-                        "rebuild cache failed({}): {:?}",
-                        cate.as_str(),
-                        fk
-                    );
-                }
-
-                MafaError::FirefoxNotFound => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
+                        // extra hint
+                        let tip = get_ff_bin_tip(self.color);
+                        eprintln_not!(self.smode, "{}", bwrap::wrap_nobrk!(&tip, 80, "       "));
+                    } else {
+                        eprintln_not!(self.smode, "{} ({})", err, cate.as_str());
                     }
 
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-
-                    eprintln_not!(self.smode, "Firefox not installed");
-
-                    // extra hint
-                    eprintln_not!(
-                        self.smode,
-                        "{}",
-                        bwrap::wrap_nobrk!(get_ff_bin_tip(self.color), 80, "       ")
-                    );
-                }
-
-                err_other => {
-                    if !self.is_prev_final() {
-                        eprintln_not!(self.smode, "");
+                    let mut source = std::error::Error::source(err);
+                    while let Some(cause) = source {
+                        eprintln_not!(self.smode, "  caused by: {}", cause);
+                        source = cause.source();
                     }
-
-                    eprint_not!(
-                        self.smode,
-                        if self.color {
-                            "\u{1b}[31;1merror: \u{1b}[0m"
-                        } else {
-                            "error: "
-                        }
-                    );
-                    eprintln_not!(self.smode, "unexpected: {:?}", err_other);
                 }
-            },
+            }
 
             // DONT FORGET: when this error occurs, check process exit code
             MafaEvent::HandlerMissed { cate, ref err } => {
-                if !self.is_prev_final() {
+                if !is_final(prev) {
                     eprintln_not!(self.smode, "");
                 }
 
                 eprint_not!(
                     self.smode,
-                    if self.color {
-                        "\u{1b}[31;1merror: \u{1b}[0m"
-                    } else {
-                        "error: "
-                    }
+                    "{}",
+                    theme::current(!self.color).error.paint("error: ")
                 );
                 eprintln_not!(
                     self.smode,
@@ -804,48 +727,115 @@ impl EventNotifier {
         }
 
         io::stdout().flush().unwrap();
-        if !is_skip_push {
-            self.queue.push(EventDetail(ev, self.wall_clock.elapsed()));
+    }
+}
+
+fn is_final(prev: Option<&MafaEvent>) -> bool {
+    let last_ev = match prev {
+        Some(v) => v,
+        None => return true,
+    };
+
+    match last_ev {
+        MafaEvent::Initialize { is_fin, .. } => {
+            return *is_fin;
         }
-        // dbgg!(&self.queue);
+
+        MafaEvent::BuildCache { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        MafaEvent::FetchResult { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        MafaEvent::CacheRetry { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        MafaEvent::ConnectTimeoutRetry { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        MafaEvent::SrvTempUnavRetry { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        MafaEvent::SimpleProgress { is_fin, .. } => {
+            return *is_fin;
+        }
+
+        _ => {}
+    }
+
+    true
+}
+
+impl Default for EventNotifier {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn is_prev_final(&self) -> bool {
-        if self.queue.len() == 0 {
-            return true;
+impl EventNotifier {
+    pub fn new() -> Self {
+        EventNotifier {
+            human: HumanSink::new(),
+            sinks: vec![],
+            queue: vec![],
+            wall_clock: Instant::now(),
         }
+    }
 
-        let last_ev = &self.queue.last().unwrap().0;
+    pub fn set_silent(&mut self) {
+        self.human.smode = true;
+    }
 
-        match last_ev {
-            MafaEvent::Initialize { is_fin, .. } => {
-                return *is_fin;
-            }
+    pub fn set_nsilent(&mut self) {
+        self.human.smode = false;
+    }
 
-            MafaEvent::BuildCache { is_fin, .. } => {
-                return *is_fin;
-            }
+    ///
+    /// Under json mode, results are the only thing printed to standard
+    /// output, progress messages are all hidden.
+    pub fn set_json(&mut self) {
+        self.human.jmode = true;
+    }
 
-            MafaEvent::FetchResult { is_fin, .. } => {
-                return *is_fin;
-            }
+    pub fn set_color(&mut self) {
+        self.human.color = true;
+    }
 
-            MafaEvent::CacheRetry { is_fin, .. } => {
-                return *is_fin;
-            }
+    pub fn set_nocolor(&mut self) {
+        self.human.color = false;
+    }
 
-            MafaEvent::ConnectTimeoutRetry { is_fin, .. } => {
-                return *is_fin;
-            }
+    ///
+    /// Register a sink that receives every event from now on, along with
+    /// the built-in human one.
+    pub fn add_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.sinks.push(sink);
+    }
 
-            MafaEvent::SrvTempUnavRetry { is_fin, .. } => {
-                return *is_fin;
-            }
+    ///
+    /// Note that, this is not supposed to respond the errors directly,
+    /// but events, one of which is `FatalMafaError`, which is the
+    /// entry to handle errors.
+    pub fn notify(&mut self, ev: MafaEvent) {
+        let prev = self.queue.last().map(|v| &v.0);
+        let elap = self.wall_clock.elapsed();
 
-            _ => {}
+        self.human.accept(&ev, prev, elap);
+        for sink in self.sinks.iter_mut() {
+            sink.accept(&ev, prev, elap);
         }
 
-        return true;
+        // a retry finishing without having retried is not in the timeline
+        if is_redundant(&ev, prev) {
+            return;
+        }
+        self.queue.push(EventDetail(ev, elap));
+        // dbgg!(&self.queue);
     }
 
     // cannot be silent
@@ -862,6 +852,192 @@ impl EventNotifier {
         // }
     }
 
+    // cannot be silent
+    ///
+    /// Statistics of every span in the timeline so far, aggregated by
+    /// component and event, e.g., all `CacheRetry`s of Google Translate
+    /// across an interactive session.
+    pub fn elap_stats(&self) {
+        let spans = self.spans();
+
+        for cate in [
+            Category::Mafa,
+            Category::Twtl,
+            Category::Gtrans,
+            Category::Camd,
+        ] {
+            let mut names = Vec::<&'static str>::new();
+            for span in spans.iter().filter(|v| v.cate == cate) {
+                if !names.contains(&span.name) {
+                    names.push(span.name);
+                }
+            }
+
+            for name in names {
+                let durs = spans
+                    .iter()
+                    .filter(|v| v.cate == cate && v.name == name)
+                    .map(|v| v.end - v.start)
+                    .collect::<Vec<Duration>>();
+                let total: Duration = durs.iter().sum();
+                let max = durs.iter().max().copied().unwrap_or(Duration::ZERO);
+
+                print!("({}", cate.as_str());
+                print!(" | ");
+                print!("{} x{}", name, durs.len());
+                print!(" | ");
+                print!("TOTAL:{}ms", total.as_millis());
+                print!(" | ");
+                print!("AVG:{}ms", (total / durs.len() as u32).as_millis());
+                print!(" | ");
+                print!("MAX:{}ms", max.as_millis());
+                print!(")");
+
+                println!();
+            }
+        }
+
+        io::stdout().flush().unwrap();
+    }
+
+    ///
+    /// The whole timeline as Chrome trace-event JSON, which can be loaded
+    /// by `chrome://tracing` or Perfetto. Every component is a thread,
+    /// spans are complete events and others are instant events.
+    pub fn trace(&self) -> String {
+        let pid = std::process::id();
+        let mut trace_evs = Vec::<TraceEvent>::new();
+
+        for cate in [
+            Category::Mafa,
+            Category::Twtl,
+            Category::Gtrans,
+            Category::Camd,
+        ] {
+            trace_evs.push(TraceEvent {
+                name: "thread_name",
+                cat: "__metadata",
+                ph: "M",
+                ts: 0,
+                dur: None,
+                pid,
+                tid: cate.tid(),
+                s: None,
+                args: serde_json::json!({ "name": cate.as_str() }),
+            });
+        }
+
+        for span in self.spans() {
+            trace_evs.push(TraceEvent {
+                name: span.name,
+                cat: span.cate.id(),
+                ph: "X",
+                ts: span.start.as_micros() as u64,
+                dur: Some((span.end - span.start).as_micros() as u64),
+                pid,
+                tid: span.cate.tid(),
+                s: None,
+                args: serde_json::json!({ "ticks": span.ticks, "is_fin": span.is_fin }),
+            });
+        }
+
+        for EventDetail(ev, elap) in self.queue.iter() {
+            if span_is_fin(ev).is_some() {
+                continue;
+            }
+            trace_evs.push(TraceEvent {
+                name: ev.name(),
+                cat: ev.cate().id(),
+                ph: "i",
+                ts: elap.as_micros() as u64,
+                dur: None,
+                pid,
+                tid: ev.cate().tid(),
+                s: Some("t"),
+                args: serde_json::to_value(RawEvent::new(ev, *elap))
+                    .unwrap_or(serde_json::Value::Null),
+            });
+        }
+
+        serde_json::json!({
+            "traceEvents": trace_evs,
+            "displayTimeUnit": "ms",
+        })
+        .to_string()
+    }
+
+    pub fn write_trace(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.trace())
+            .map_err(|err_io| MafaError::InvalidElapTrace(PathBuf::from(path), err_io))
+    }
+
+    ///
+    /// Pair the events of the timeline into spans, in the order they
+    /// start. Retries and progress are made of consecutive events, they
+    /// end once any other event comes. Spans not finished end at the
+    /// last event they are made of, or the last event of the timeline.
+    fn spans(&self) -> Vec<Span> {
+        let mut spans = Vec::<Span>::new();
+        let mut open = Vec::<usize>::new();
+
+        for EventDetail(ev, elap) in self.queue.iter() {
+            let (name, cate) = (ev.name(), ev.cate());
+
+            open.retain(|&i| {
+                let span = &mut spans[i];
+                if is_repeating(span.name) && (span.name != name || span.cate != cate) {
+                    span.end = *elap;
+                    false
+                } else {
+                    true
+                }
+            });
+
+            let is_fin = match span_is_fin(ev) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let pos = open
+                .iter()
+                .position(|&i| spans[i].name == name && spans[i].cate == cate);
+
+            match pos {
+                Some(p) if is_fin || is_repeating(name) => {
+                    let span = &mut spans[open[p]];
+                    span.end = *elap;
+                    span.ticks += 1;
+                    if is_fin {
+                        span.is_fin = true;
+                        open.remove(p);
+                    }
+                }
+                Some(p) => {
+                    // started again before finishing
+                    spans[open[p]].end = *elap;
+                    open.remove(p);
+                    spans.push(Span::new(name, cate, *elap, false));
+                    open.push(spans.len() - 1);
+                }
+                None => {
+                    spans.push(Span::new(name, cate, *elap, is_fin));
+                    if !is_fin {
+                        open.push(spans.len() - 1);
+                    }
+                }
+            }
+        }
+
+        let last = self.queue.last().map(|v| v.1).unwrap_or(Duration::ZERO);
+        for i in open {
+            if !is_repeating(spans[i].name) {
+                spans[i].end = last;
+            }
+        }
+
+        spans
+    }
+
     fn elap_cate(&self, what_cate: Category) {
         let mut p_prepare = (Duration::ZERO, Duration::ZERO);
         let mut p_cache = (Duration::ZERO, Duration::ZERO);
@@ -872,8 +1048,7 @@ impl EventNotifier {
             arg.0 != Duration::ZERO && arg.1 != Duration::ZERO
         };
 
-        let lasti = self.queue.len() - 1;
-        for _i in 0..self.queue.len() {
+        for ev in self.queue.iter().rev() {
             if is_filled(p_prepare)
                 && is_filled(p_cache)
                 && is_filled(p_fetch)
@@ -881,8 +1056,6 @@ impl EventNotifier {
             {
                 break;
             }
-            let i = lasti - _i;
-            let ev = &self.queue[i];
             match ev.0 {
                 MafaEvent::Initialize { cate, is_fin } => {
                     if is_fin {
@@ -963,16 +1136,533 @@ impl EventNotifier {
     }
 }
 
-fn get_ff_bin_tip(color: bool) -> &'static str {
+#[derive(Debug, PartialEq)]
+struct Span {
+    name: &'static str,
+    cate: Category,
+    start: Duration,
+    end: Duration,
+    /// number of events the span is made of, e.g., 3 for a retry that
+    /// retried twice and finished
+    ticks: u32,
+    is_fin: bool,
+}
+
+impl Span {
+    fn new(name: &'static str, cate: Category, elap: Duration, is_fin: bool) -> Self {
+        Span {
+            name,
+            cate,
+            start: elap,
+            end: elap,
+            ticks: 1,
+            is_fin,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    ts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u64>,
+    pid: u32,
+    tid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    args: serde_json::Value,
+}
+
+///
+/// `Some(is_fin)` if the event starts or finishes a span.
+fn span_is_fin(ev: &MafaEvent) -> Option<bool> {
+    match ev {
+        MafaEvent::Initialize { is_fin, .. }
+        | MafaEvent::BuildCache { is_fin, .. }
+        | MafaEvent::FetchResult { is_fin, .. }
+        | MafaEvent::TryNextCache { is_fin, .. }
+        | MafaEvent::CacheRetry { is_fin, .. }
+        | MafaEvent::SrvTempUnavRetry { is_fin, .. }
+        | MafaEvent::ConnectTimeoutRetry { is_fin, .. }
+        | MafaEvent::SimpleProgress { is_fin, .. } => Some(*is_fin),
+        _ => None,
+    }
+}
+
+fn is_repeating(name: &str) -> bool {
+    matches!(
+        name,
+        "TryNextCache"
+            | "CacheRetry"
+            | "SrvTempUnavRetry"
+            | "ConnectTimeoutRetry"
+            | "SimpleProgress"
+    )
+}
+
+///
+/// A finishing retry that has not retried previously, nothing is rendered
+/// for it, nor does it join the timeline.
+fn is_redundant(ev: &MafaEvent, prev: Option<&MafaEvent>) -> bool {
+    match (ev, prev) {
+        (MafaEvent::CacheRetry { is_fin: true, .. }, Some(MafaEvent::CacheRetry { .. }))
+        | (
+            MafaEvent::SrvTempUnavRetry { is_fin: true, .. },
+            Some(MafaEvent::SrvTempUnavRetry { .. }),
+        )
+        | (
+            MafaEvent::ConnectTimeoutRetry { is_fin: true, .. },
+            Some(MafaEvent::ConnectTimeoutRetry { .. }),
+        )
+        | (MafaEvent::TryNextCache { is_fin: true, .. }, Some(MafaEvent::TryNextCache { .. })) => {
+            false
+        }
+
+        (MafaEvent::CacheRetry { is_fin: true, .. }, _)
+        | (MafaEvent::SrvTempUnavRetry { is_fin: true, .. }, _)
+        | (MafaEvent::ConnectTimeoutRetry { is_fin: true, .. }, _)
+        | (MafaEvent::TryNextCache { is_fin: true, .. }, _) => true,
+
+        _ => false,
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RawEvent<'a> {
+    elap_ms: u64,
+    event: &'static str,
+    cate: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_fin: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    curr: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    safe: Option<bool>,
+}
+
+impl<'a> RawEvent<'a> {
+    fn new(ev: &'a MafaEvent, elap: Duration) -> Self {
+        let mut raw = RawEvent {
+            elap_ms: elap.as_millis() as u64,
+            event: ev.name(),
+            cate: ev.cate().id(),
+            is_fin: None,
+            total: None,
+            curr: None,
+            kind: None,
+            output: None,
+            error: None,
+            count: None,
+            safe: None,
+        };
+
+        match ev {
+            MafaEvent::Initialize { is_fin, .. }
+            | MafaEvent::BuildCache { is_fin, .. }
+            | MafaEvent::FetchResult { is_fin, .. }
+            | MafaEvent::TryNextCache { is_fin, .. }
+            | MafaEvent::CacheRetry { is_fin, .. }
+            | MafaEvent::SrvTempUnavRetry { is_fin, .. }
+            | MafaEvent::ConnectTimeoutRetry { is_fin, .. } => {
+                raw.is_fin = Some(*is_fin);
+            }
+            MafaEvent::SimpleProgress {
+                total,
+                curr,
+                is_fin,
+                ..
+            } => {
+                raw.total = Some(*total);
+                raw.curr = Some(*curr);
+                raw.is_fin = Some(*is_fin);
+            }
+            MafaEvent::FatalMafaError { err, .. } | MafaEvent::HandlerMissed { err, .. } => {
+                raw.error = Some(err.to_string());
+            }
+            MafaEvent::ExactWhatRequest { kind, .. } => {
+                raw.kind = Some(format!("{:?}", kind));
+            }
+            MafaEvent::ExactUserRequest { kind, output, .. } => {
+                raw.kind = Some(format!("{:?}", kind));
+                raw.output = Some(output);
+            }
+            MafaEvent::WaitSecsMayInterrupt { count, safe, .. } => {
+                raw.count = Some(*count);
+                raw.safe = Some(*safe);
+            }
+        }
+
+        raw
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!("+{}ms [{}] {}", self.elap_ms, self.cate, self.event);
+
+        if let Some(v) = self.is_fin {
+            text += &format!(" is_fin={}", v);
+        }
+        if let (Some(curr), Some(total)) = (self.curr, self.total) {
+            text += &format!(" progress={}/{}", curr, total);
+        }
+        if let Some(v) = &self.kind {
+            text += &format!(" kind={}", v);
+        }
+        if let Some(v) = self.output {
+            text += &format!(" output={:?}", v);
+        }
+        if let Some(v) = &self.error {
+            text += &format!(" error={:?}", v);
+        }
+        if let Some(v) = self.count {
+            text += &format!(" count={}", v);
+        }
+        if let Some(v) = self.safe {
+            text += &format!(" safe={}", v);
+        }
+
+        text
+    }
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(w: W) -> Self {
+        JsonLinesSink { w }
+    }
+}
+
+impl JsonLinesSink<io::Stderr> {
+    pub fn stderr() -> Self {
+        JsonLinesSink::new(io::stderr())
+    }
+}
+
+impl<W: Write + Send> EventSink for JsonLinesSink<W> {
+    fn accept(&mut self, ev: &MafaEvent, _prev: Option<&MafaEvent>, elap: Duration) {
+        if let Ok(line) = serde_json::to_string(&RawEvent::new(ev, elap)) {
+            writeln!(self.w, "{}", line).ok();
+            self.w.flush().ok();
+        }
+    }
+}
+
+impl LogFileSink {
+    ///
+    /// The file is created if it does not exist, events are appended
+    /// otherwise.
+    pub fn open(path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err_io| MafaError::InvalidEventLog(PathBuf::from(path), err_io))?;
+
+        Ok(LogFileSink { file })
+    }
+}
+
+impl EventSink for LogFileSink {
+    fn accept(&mut self, ev: &MafaEvent, _prev: Option<&MafaEvent>, elap: Duration) {
+        writeln!(
+            self.file,
+            "{} {}",
+            secs_since_epoch(),
+            RawEvent::new(ev, elap).to_text()
+        )
+        .ok();
+    }
+}
+
+///
+/// how to get a Firefox usable by mafa on this platform
+pub(crate) fn ff_bin_hint() -> &'static str {
     let osinfo = os_info::get();
     match osinfo.os_type() {
         os_info::Type::Ubuntu => {
-            if color {
-                "  \u{1b}[32mtip\u{1b}[0m: Only Firefox ESR is supported on this platform, please install `firefox-esr` package"
-            } else {
-                "  tip: Only Firefox ESR is supported on this platform, please install `firefox-esr` package"
-            }
+            "Only Firefox ESR is supported on this platform, please install `firefox-esr` package"
+        }
+        _ => "Please install Firefox from https://www.mozilla.org/firefox/ or the system's package manager",
+    }
+}
+
+fn get_ff_bin_tip(color: bool) -> String {
+    format!(
+        "  {}: {}",
+        theme::current(!color).tip.paint("tip"),
+        ff_bin_hint()
+    )
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    struct VecSink(Arc<Mutex<Vec<String>>>);
+
+    impl EventSink for VecSink {
+        fn accept(&mut self, ev: &MafaEvent, _prev: Option<&MafaEvent>, elap: Duration) {
+            self.0
+                .lock()
+                .expect("buggy")
+                .push(serde_json::to_string(&RawEvent::new(ev, elap)).expect("buggy"));
+        }
+    }
+
+    #[test]
+    fn add_sink_1() {
+        let got = Arc::new(Mutex::new(vec![]));
+        let mut ntf = EventNotifier::new();
+        ntf.set_silent();
+        ntf.add_sink(Box::new(VecSink(Arc::clone(&got))));
+
+        let cate = Category::Gtrans;
+        ntf.notify(MafaEvent::BuildCache {
+            cate,
+            is_fin: false,
+        });
+        // not retried previously, delivered but not in the timeline
+        ntf.notify(MafaEvent::CacheRetry { cate, is_fin: true });
+        ntf.notify(MafaEvent::BuildCache { cate, is_fin: true });
+        ntf.notify(MafaEvent::FatalMafaError {
+            cate,
+            err: MafaError::InvalidSourceLang,
+        });
+
+        let got = got.lock().expect("buggy");
+        assert_eq!(got.len(), 4);
+        assert!(got[0].contains(r#""event":"BuildCache","cate":"gtrans","is_fin":false}"#));
+        assert!(got[1].contains(r#""event":"CacheRetry","cate":"gtrans","is_fin":true}"#));
+        assert!(got[2].contains(r#""event":"BuildCache","cate":"gtrans","is_fin":true}"#));
+        assert!(got[3].contains(r#""error":"invalid source language"}"#));
+        assert_eq!(ntf.queue.len(), 3);
+    }
+
+    #[test]
+    fn raw_event_1() {
+        let ev = MafaEvent::SimpleProgress {
+            cate: Category::Twtl,
+            total: 10,
+            curr: 3,
+            is_fin: false,
+        };
+        let raw = RawEvent::new(&ev, Duration::from_millis(1500));
+
+        assert_eq!(
+            serde_json::to_string(&raw).expect("buggy"),
+            r#"{"elap_ms":1500,"event":"SimpleProgress","cate":"twtl","is_fin":false,"total":10,"curr":3}"#
+        );
+        assert_eq!(
+            raw.to_text(),
+            "+1500ms [twtl] SimpleProgress is_fin=false progress=3/10"
+        );
+    }
+
+    fn ntf_of(evs: Vec<(MafaEvent, u64)>) -> EventNotifier {
+        let mut ntf = EventNotifier::new();
+        for (ev, ms) in evs {
+            ntf.queue.push(EventDetail(ev, Duration::from_millis(ms)));
         }
-        _ => "",
+        ntf
+    }
+
+    #[test]
+    fn spans_1() {
+        let cate = Category::Gtrans;
+        let ntf = ntf_of(vec![
+            (
+                MafaEvent::BuildCache {
+                    cate,
+                    is_fin: false,
+                },
+                10,
+            ),
+            (
+                MafaEvent::CacheRetry {
+                    cate,
+                    is_fin: false,
+                },
+                20,
+            ),
+            (
+                MafaEvent::CacheRetry {
+                    cate,
+                    is_fin: false,
+                },
+                30,
+            ),
+            (MafaEvent::CacheRetry { cate, is_fin: true }, 40),
+            (MafaEvent::BuildCache { cate, is_fin: true }, 50),
+            (
+                MafaEvent::FetchResult {
+                    cate,
+                    is_fin: false,
+                },
+                60,
+            ),
+            (
+                MafaEvent::ConnectTimeoutRetry {
+                    cate,
+                    is_fin: false,
+                },
+                70,
+            ),
+            (
+                MafaEvent::FatalMafaError {
+                    cate,
+                    err: MafaError::AllCachesInvalid,
+                },
+                80,
+            ),
+        ]);
+
+        let spans = ntf.spans();
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].name, "BuildCache");
+        assert_eq!(spans[0].end - spans[0].start, Duration::from_millis(40));
+        assert!(spans[0].is_fin);
+        assert_eq!(spans[1].name, "CacheRetry");
+        assert_eq!(spans[1].ticks, 3);
+        assert_eq!(spans[1].end - spans[1].start, Duration::from_millis(20));
+        // fetching is never finished
+        assert_eq!(spans[2].name, "FetchResult");
+        assert_eq!(spans[2].end, Duration::from_millis(80));
+        assert!(!spans[2].is_fin);
+        // retrying ends once the error comes
+        assert_eq!(spans[3].name, "ConnectTimeoutRetry");
+        assert_eq!(spans[3].end, Duration::from_millis(80));
+        assert!(!spans[3].is_fin);
+    }
+
+    #[test]
+    fn trace_1() {
+        let cate = Category::Camd;
+        let ntf = ntf_of(vec![
+            (
+                MafaEvent::Initialize {
+                    cate,
+                    is_fin: false,
+                },
+                1,
+            ),
+            (MafaEvent::Initialize { cate, is_fin: true }, 3),
+            (
+                MafaEvent::ExactUserRequest {
+                    cate,
+                    kind: EurKind::CamdResult,
+                    output: "_".to_string(),
+                },
+                4,
+            ),
+        ]);
+
+        let trace: serde_json::Value = serde_json::from_str(&ntf.trace()).expect("buggy");
+        let trace_evs = trace["traceEvents"].as_array().expect("buggy");
+        // 4 thread names, 1 span, 1 instant
+        assert_eq!(trace_evs.len(), 6);
+        assert_eq!(trace_evs[4]["name"], "Initialize");
+        assert_eq!(trace_evs[4]["ph"], "X");
+        assert_eq!(trace_evs[4]["ts"], 1000);
+        assert_eq!(trace_evs[4]["dur"], 2000);
+        assert_eq!(trace_evs[4]["tid"], Category::Camd.tid());
+        assert_eq!(trace_evs[5]["name"], "ExactUserRequest");
+        assert_eq!(trace_evs[5]["ph"], "i");
+    }
+
+    #[test]
+    fn elap_empty_1() {
+        let ntf = EventNotifier::new();
+        ntf.elap(Category::Gtrans);
+        ntf.elap_stats();
+        assert_eq!(ntf.spans().len(), 0);
+    }
+
+    #[test]
+    fn progress_text_1() {
+        assert_eq!(progress_text(3, Some(10), None), "3/10 (30%)");
+        assert_eq!(
+            progress_text(3, Some(10), Some(0.5)),
+            "3/10 (30%) 0.5/s, ETA 14s"
+        );
+        assert_eq!(
+            progress_text(1, Some(100), Some(1.0)),
+            "1/100 (1%) 1.0/s, ETA 1m39s"
+        );
+        assert_eq!(progress_text(2, None, None), "#2");
+        assert_eq!(progress_text(2, None, Some(0.25)), "#2 (0.2/s)");
+    }
+
+    #[test]
+    fn draw_progress_1() {
+        let mut sink = HumanSink {
+            smode: true,
+            jmode: false,
+            color: false,
+            progress: ProgressLine::new(false),
+        };
+        let cate = Category::Gtrans;
+        let evs = [
+            MafaEvent::SimpleProgress {
+                cate,
+                total: 3,
+                curr: 1,
+                is_fin: false,
+            },
+            MafaEvent::FetchResult {
+                cate,
+                is_fin: false,
+            },
+            MafaEvent::FetchResult { cate, is_fin: true },
+            MafaEvent::SimpleProgress {
+                cate,
+                total: 3,
+                curr: 2,
+                is_fin: false,
+            },
+        ];
+
+        let mut prev = None;
+        for (i, ev) in evs.iter().enumerate() {
+            sink.accept(ev, prev, Duration::from_secs(10 + 2 * i as u64));
+            prev = Some(ev);
+        }
+        assert_eq!(sink.progress.of, Some((cate, Some(3))));
+        assert_eq!(sink.progress.first, 1);
+        assert_eq!(
+            sink.progress.rate(2, Duration::from_secs(16)),
+            Some(1.0 / 6.0)
+        );
+
+        let fin = MafaEvent::SimpleProgress {
+            cate,
+            total: 3,
+            curr: 3,
+            is_fin: true,
+        };
+        sink.accept(&fin, prev, Duration::from_secs(18));
+        assert_eq!(sink.progress.of, None);
+    }
+
+    #[test]
+    fn progress_line_1() {
+        let mut pl = ProgressLine::new(false);
+        pl.start(1, Duration::from_secs(10));
+
+        assert_eq!(pl.rate(1, Duration::from_secs(10)), None);
+        assert_eq!(pl.rate(1, Duration::from_secs(12)), None);
+        assert_eq!(pl.rate(5, Duration::from_secs(12)), Some(2.0));
     }
 }
@@ -8,19 +8,14 @@
 //
 
 use std::borrow::Cow;
+use std::io::Read;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::thread::sleep;
 use std::time::Duration;
 use unicode_width::UnicodeWidthStr;
 
-use wda::BasicAutomation;
 use wda::WdaError;
-use wda::WdaSett;
-use wda::WebDrvAstn;
-
-use wda::GeckoDriver;
 use wda::WdcError;
 
 use crate::error::CacheRebuildFailKind;
@@ -29,16 +24,25 @@ use crate::error::Result;
 
 use crate::mafadata::MafaData;
 
+use crate::cache;
+use crate::cache::CacheEntry;
+use crate::cancel;
+use crate::retry;
+
 use crate::ev_ntf::Category;
 use crate::ev_ntf::EurKind;
 use crate::ev_ntf::EventNotifier;
 use crate::ev_ntf::MafaEvent;
 
+use crate::backend::BrowserBackend;
 use crate::MafaClient;
 use crate::MafaInput;
+use crate::OutputFormat;
 
 use crate::comm;
 use crate::comm::CacheMechanism;
+use crate::config;
+use crate::theme;
 
 use clap::Arg as ClapArg;
 use clap::ArgAction as ClapArgAction;
@@ -51,9 +55,21 @@ pub struct GtransInput {
     pub(crate) list_lang: bool,
     src_lang: String,
     tgt_lang: String,
+    from_file: Option<String>,
 }
 
 impl GtransInput {
+    ///
+    /// Key of the stored result, `None` if the request is not served by
+    /// one, e.g., a batch.
+    pub fn result_key(&self) -> Option<String> {
+        if self.list_lang || self.from_file.is_some() {
+            return None;
+        }
+
+        Some(result_key(&self.src_lang, &self.tgt_lang, &self.words))
+    }
+
     pub fn from_ca_matched(ca_matched: &ClapArgMatches) -> Result<Self> {
         let mut gtrans_in = GtransInput::default();
 
@@ -93,6 +109,11 @@ impl GtransInput {
             gtrans_in.tgt_lang = optval.clone();
         }
 
+        // from-file
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::FromFile::id()) {
+            gtrans_in.from_file = Some(optval.clone());
+        }
+
         dbgg!(&gtrans_in);
 
         Ok(gtrans_in)
@@ -172,6 +193,44 @@ $ mafa gtrans thank you"#;
         }
     }
 
+    pub struct FromFile;
+    impl FromFile {
+        #[inline]
+        pub fn id() -> &'static str {
+            "FROM_FILE"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "from-file"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Translate words line by line from a file"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Translate words line by line from a file
+
+Each non-empty line of the file is translated as one entry, all entries share the same browser session and the same cache. Results are printed in input order, entries failed to translate are reported at the end. Use "-" to read from standard input, for example:
+
+$ cat list.txt | mafa gtrans --from-file -
+
+NOTE: WORDS are ignored when this option is given."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
     pub struct TargetLang;
     impl TargetLang {
         #[inline]
@@ -271,30 +330,55 @@ pub fn get_cmd() -> ClapCommand {
 
     let opt_list_lang = {
         type O = opts::ListLang;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper()),
+            Some("gtrans"),
+            O::longopt(),
+        )
     };
 
     let opt_tl = {
         type O = opts::TargetLang;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .num_args(O::n_args())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("gtrans"),
+            O::longopt(),
+        )
     };
 
     let opt_sl = {
         type O = opts::SourceLang;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .num_args(O::n_args())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("gtrans"),
+            O::longopt(),
+        )
+    };
+
+    let opt_from_file = {
+        type O = opts::FromFile;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("gtrans"),
+            O::longopt(),
+        )
     };
 
     let cmd_gtrans = ClapCommand::new("gtrans")
@@ -302,7 +386,8 @@ pub fn get_cmd() -> ClapCommand {
         .arg(opt_words)
         .arg(opt_list_lang)
         .arg(opt_tl)
-        .arg(opt_sl);
+        .arg(opt_sl)
+        .arg(opt_from_file);
 
     cmd_gtrans
 }
@@ -310,63 +395,40 @@ pub fn get_cmd() -> ClapCommand {
 #[derive(Debug, Default)]
 pub struct Upath(Vec<u8>);
 
-#[derive(Debug, Default)]
-struct UpathCache(Vec<Upath>);
-
-impl UpathCache {
-    fn from_pbuf(pb: PathBuf) -> Result<Self> {
-        let rawdata = std::fs::read_to_string(&pb).unwrap(); // FIXME: no unwrap
-
-        let mut ret = vec![];
-
-        let bytes = rawdata.as_bytes();
-
-        let mut previ;
-        let mut curri = 0;
-
-        let mut caches = Vec::<&[u8]>::new();
-
-        for i in 0..bytes.len() {
-            if bytes[i] == 0xa {
-                previ = if curri == 0 { curri } else { curri + 1 };
-                curri = i;
-                caches.push(&bytes[previ..curri]);
-            }
-        }
-
-        for ele in caches {
-            let deser = serde_json::from_slice::<Vec<u8>>(ele).expect("buggy");
-            if deser.len() == 0 {
-                return Err(MafaError::CacheCorrupted);
-            }
-            let mut nr = Upath::default();
-            nr.0 = deser;
-            ret.push(nr);
-        }
-
-        Ok(Self(ret))
-    }
-}
-
-impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
+impl<'a, 'b, 'c, B: BrowserBackend> MafaClient<'a, 'b, 'c, GtransInput, Upath, B> {
     ///
     /// Returned `String` is pretty-printed.
     pub fn handle(&mut self, pred_caches: Option<Vec<Vec<u8>>>) -> Result<(EurKind, String)> {
         if self.sub_input.list_lang {
+            if let OutputFormat::Json = self.input.output {
+                return Ok((EurKind::GtransAllLang, list_all_lang_json()));
+            }
             return Ok((EurKind::GtransAllLang, list_all_lang().to_string()));
         }
 
-        if pred_caches.is_none() {
-            self.try_rebuild_cache()?;
-        } else {
-            let pred_caches = pred_caches.ok_or(MafaError::BugFound(4567))?;
-            pred_caches
-                .iter()
-                .for_each(|v| self.caches.push(Upath(v.clone())));
+        // a stored result needs no caches at all
+        let stored = match self.sub_input.result_key() {
+            Some(key) => self.stored_result("gtrans", &key)?,
+            None => None,
+        };
+
+        if stored.is_none() && !self.input.offline {
+            if pred_caches.is_none() {
+                self.try_rebuild_cache()?;
+            } else {
+                let pred_caches = pred_caches.ok_or(MafaError::Bug("caches not loaded"))?;
+                pred_caches
+                    .iter()
+                    .for_each(|v| self.caches.push(Upath(v.clone())));
+            }
+
+            if self.caches.is_empty() {
+                panic!("buggy");
+            }
         }
 
-        if self.caches.len() == 0 {
-            panic!("buggy");
+        if let Some(from_file) = &self.sub_input.from_file {
+            return self.handle_batch(from_file);
         }
 
         let source_lang = &self.sub_input.src_lang;
@@ -374,30 +436,171 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
 
         let orig_words = &self.sub_input.words;
 
-        self.notify(MafaEvent::FetchResult {
-            cate: Category::Gtrans,
-            is_fin: false,
-        })?;
-        let translated = self.fetch(orig_words, source_lang, target_lang)?;
-        self.notify(MafaEvent::FetchResult {
-            cate: Category::Gtrans,
-            is_fin: true,
-        })?;
+        let translated = match stored {
+            Some(v) => v,
+            None => {
+                self.notify(MafaEvent::FetchResult {
+                    cate: Category::Gtrans,
+                    is_fin: false,
+                })?;
+                let translated = self.fetch(orig_words, source_lang, target_lang)?;
+                self.notify(MafaEvent::FetchResult {
+                    cate: Category::Gtrans,
+                    is_fin: true,
+                })?;
+
+                self.store_result(
+                    "gtrans",
+                    &result_key(source_lang, target_lang, orig_words),
+                    &translated,
+                );
+
+                translated
+            }
+        };
 
         let gtrans_res = GtransResult::from_str(source_lang, target_lang, orig_words, &translated)?;
         dbgg!(&gtrans_res);
 
+        if let OutputFormat::Json = self.input.output {
+            return Ok((
+                EurKind::GtransResult,
+                serde_json::to_string(&gtrans_res).map_err(MafaError::Json)?,
+            ));
+        }
+
         Ok((
             EurKind::GtransResult,
-            gtrans_res.pretty_print(self.input.nocolor, self.input.ascii, self.input.wrap_width)?,
+            gtrans_res.pretty_print(
+                self.input.nocolor,
+                self.input.ascii,
+                self.input.wrap_width_now(),
+            )?,
         ))
     }
 
+    fn fetch_or_stored(&self, words: &str, sl: &str, tl: &str) -> Result<String> {
+        let key = result_key(sl, tl, words);
+        if let Some(raw) = self.stored_result("gtrans", &key)? {
+            return Ok(raw);
+        }
+
+        let translated = self.fetch(words, sl, tl)?;
+        self.store_result("gtrans", &key, &translated);
+
+        Ok(translated)
+    }
+
+    ///
+    /// Every entry is fetched with the already loaded caches, failed ones
+    /// do not stop the whole batch, but are reported after all results.
+    fn handle_batch(&self, from_file: &str) -> Result<(EurKind, String)> {
+        let entries = read_batch_entries(from_file)?;
+
+        let source_lang = &self.sub_input.src_lang;
+        let target_lang = &self.sub_input.tgt_lang;
+        let is_json = self.input.output == OutputFormat::Json;
+
+        #[derive(serde::Serialize)]
+        struct BatchItem<'a, 'b> {
+            line: usize,
+            #[serde(flatten)]
+            res: GtransResult<'a, 'b>,
+        }
+
+        #[derive(serde::Serialize)]
+        struct BatchFailure<'a> {
+            line: usize,
+            words: &'a str,
+            error: String,
+        }
+
+        let mut output = String::from("");
+        let mut json_items = Vec::<serde_json::Value>::new();
+        let mut failures = Vec::<BatchFailure>::new();
+
+        let total = entries.len() as u32;
+        for (i, (line, words)) in entries.iter().enumerate() {
+            self.notify(MafaEvent::SimpleProgress {
+                cate: Category::Gtrans,
+                total,
+                curr: i as u32 + 1,
+                is_fin: i as u32 + 1 == total,
+            })?;
+
+            let translated = match self.fetch_or_stored(words, source_lang, target_lang) {
+                Ok(v) => v,
+                // Ctrl-C stops the whole batch, not only this entry
+                Err(MafaError::Cancelled) => return Err(MafaError::Cancelled),
+                Err(err) => {
+                    failures.push(BatchFailure {
+                        line: *line,
+                        words,
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let gtrans_res =
+                match GtransResult::from_str(source_lang, target_lang, words, &translated) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        failures.push(BatchFailure {
+                            line: *line,
+                            words,
+                            error: err.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+            if is_json {
+                let item = BatchItem {
+                    line: *line,
+                    res: gtrans_res,
+                };
+                json_items.push(serde_json::to_value(&item).map_err(MafaError::Json)?);
+            } else {
+                output += &gtrans_res.pretty_print(
+                    self.input.nocolor,
+                    self.input.ascii,
+                    self.input.wrap_width_now(),
+                )?;
+                output += "\n";
+            }
+        }
+
+        // results are printed all the same, but the exit code tells
+        let eurk = if failures.is_empty() {
+            EurKind::GtransResult
+        } else {
+            EurKind::GtransPartial
+        };
+
+        if is_json {
+            let batch = serde_json::json!({
+                "results": json_items,
+                "failures": failures,
+            });
+            return Ok((eurk, batch.to_string()));
+        }
+
+        if !failures.is_empty() {
+            output += &format!("Failed entries ({}/{}):\n", failures.len(), total);
+            for fail in &failures {
+                output += &format!("  line {}: {} ({})\n", fail.line, fail.words, fail.error);
+            }
+        }
+
+        Ok((eurk, output))
+    }
+
     fn upaths_locate(
         &self,
         en_words: &str,
         tc_words: &str,
-        wait_before_extract: u64,
+        wait_before_extract: Duration,
     ) -> Result<Vec<u8>> {
         let url = format!(
             "https://translate.google.com/?sl=en&tl=zh-TW&text={}&op=translate",
@@ -413,7 +616,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
             }
         }
 
-        sleep(Duration::from_millis(wait_before_extract));
+        cancel::sleep(wait_before_extract)?;
 
         // script/gtrans-upath.js
         let js_in="console.log=function(){};function locate_elem(e){var o=[];function l(e,n,t){let c=e.childNodes.length;for(let d=0;d<c;d++){let c=e.childNodes[d];if(c.innerText&&c.innerText==n){console.log('yes',c);o=[...t,d]}else{l(c,n,[...t,d])}}}let n=e;l(document.body,n,[]);console.log(o);let t=o.map((()=>document.body));console.log(t);for(let e=0;e<o.length;e++){for(let l=0;l<o[e].length;l++){t[e]=t[e].childNodes[o[e][l]]}}return o}return locate_elem(arguments[0]);";
@@ -441,7 +644,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
     fn notify(&self, ev: MafaEvent) -> Result<()> {
         self.ntf
             .lock()
-            .map_err(|_| MafaError::BugFound(7890))?
+            .map_err(|_| MafaError::Bug("lock poisoned"))?
             .notify(ev);
 
         Ok(())
@@ -449,22 +652,23 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
 
     fn refresh_upath(&mut self, rebuild_cache: bool) -> Result<()> {
         if !rebuild_cache {
-            let caches_from_files =
-                UpathCache::from_pbuf(self.mafad.pathto_exist_cache("gtrans")?)?;
-            self.caches = caches_from_files.0;
+            self.caches = cache::load(self.mafad, "gtrans")?
+                .into_iter()
+                .map(|record| record.entry.into_upath().map(Upath))
+                .collect::<Result<Vec<Upath>>>()?;
             return Ok(());
         }
 
         let mut upath1: Option<Vec<u8>> = None;
         let mut upath2: Option<Vec<u8>> = None;
 
-        // let mut time_before = 50; // test purpose
-        let mut time_before = 500; // in millis
+        let mut attempts = self.input.retry.apply(retry::CACHE_BUILD).begin();
+
+        while attempts.remain() {
+            cancel::check()?;
 
-        // let mut try_times = 3; // test purpose
-        let mut try_times = 5;
+            let time_before = attempts.delay();
 
-        while try_times > 0 {
             if upath1.is_none() {
                 match self.upaths_locate("OMG", "\"我的天啊\"", time_before) {
                     Ok(ret) => {
@@ -518,13 +722,12 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
                     cate: Category::Gtrans,
                     is_fin: false,
                 })?;
-                try_times -= 1;
-                time_before += time_before;
-                dbgmsg!("need retry {} {}", try_times, time_before);
+                attempts.backoff();
+                dbgmsg!("need retry {} {:?}", attempts.left(), time_before);
             }
         }
 
-        dbgg!(&try_times);
+        dbgg!(&attempts);
 
         if upath1.is_none() || upath2.is_none() {
             return Err(MafaError::CacheRebuildFail(
@@ -550,67 +753,36 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
             }
         }
 
-        let u_part = serde_json::to_string(&upath1).unwrap();
-        let comb = format!("{}\n", u_part);
-        dbgg!(&comb);
-
-        self.mafad
-            .cache_append("gtrans", &comb, &format!("{}-", &comb))?;
+        cache::learn(
+            self.mafad,
+            "gtrans",
+            CacheEntry::Upath(upath1.clone()),
+            "OMG ASAP",
+        )?;
 
         self.caches.push(Upath(upath1));
 
         Ok(())
     }
 
-    fn cache_on_gh(&self, url: &str) -> Result<String> {
-        match self.wda.go_url(url) {
-            Ok(_) => {}
-            Err(err_navi) => {
-                if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_navi {
-                    dbgg!(123);
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_navi));
-                }
-            }
-        }
-
-        let jsout: String;
-        let jsin = "return document.getElementsByTagName('pre')[0].innerText;";
-
-        match self.wda.eval(&jsin, vec![]) {
-            Ok(ret) => jsout = ret,
-            Err(err_eval) => {
-                if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_eval {
-                    dbgg!(123);
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_eval));
-                }
-            }
-        }
-
-        // let jsout = jsout.replace("\"", "").replace("\\n", "\n");
-        let jsout = jsout.replace('"', "").replace("\\n", "\n");
-
-        dbgg!(&jsout);
-
-        Ok(jsout)
-    }
-
     fn try_rebuild_cache(&mut self) -> Result<()> {
         let mut rebuild_cache = false;
 
         if let CacheMechanism::Remote = self.input.cachm {
-            let remote_data = self.cache_on_gh(
-                "https://raw.githubusercontent.com/micl2e2/mafa-cache/master/gtrans",
+            let remote_data = cache::fetch_remote(
+                &self.input.cache_remote,
+                &self.input.cache_manifest,
+                "gtrans",
+                |url| cache::fetch_via_browser(self.wda, url),
             )?;
 
-            self.mafad.init_cache("gtrans", &remote_data)?;
+            cache::init(self.mafad, "gtrans", &remote_data, "remote")?;
         } else if let CacheMechanism::Local = self.input.cachm {
-            self.mafad.try_init_cache(
+            cache::try_init(
+                self.mafad,
                 "gtrans",
                 "[4,0,1,0,1,0,1,1,2,1,1,9,0,3,0,0,1]\n[4,0,1,0,1,0,1,1,2,1,1,9,0,2,0,0,1]\n-",
+                "local",
             )?;
         } else if let CacheMechanism::No = self.input.cachm {
             rebuild_cache = true;
@@ -633,6 +805,35 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
         Ok(())
     }
 
+    ///
+    /// Rebuild the cache by visiting the website, regardless of the cache
+    /// mechanism, the rebuilt entry goes first. Returns the number of
+    /// entries in use.
+    pub fn rebuild_cache(&mut self) -> Result<usize> {
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Gtrans,
+            is_fin: false,
+        })?;
+        self.refresh_upath(true)?;
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Gtrans,
+            is_fin: true,
+        })?;
+
+        self.refresh_upath(false)?;
+
+        Ok(self.caches.len())
+    }
+
+    ///
+    /// not being recorded is not fatal
+    fn record_outcome(&self, upaths_i: usize, is_ok: bool) {
+        let entry = CacheEntry::Upath(self.caches[upaths_i].0.clone());
+        if let Err(_err_rec) = cache::record_outcome(self.mafad, "gtrans", &entry, is_ok) {
+            dbgg!(_err_rec);
+        }
+    }
+
     fn fetch(&self, orig_words: &str, sl: &str, tl: &str) -> Result<String> {
         let mut url = String::from("");
         url += &format!("https://translate.google.com/?sl={}&tl={}&text=", sl, tl);
@@ -650,13 +851,12 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
 
         let mut is_url_reached = false;
 
-        // try_times = go_url + eval_js
-        let mut try_times = 5; // sufficient to let try again succeed
+        // attempts = go_url + eval_js
+        let mut attempts = self.input.retry.apply(retry::GTRANS_FETCH).begin();
 
-        // let mut wait_before = 500;
-        let mut wait_before = 100;
+        while attempts.remain() {
+            cancel::check()?;
 
-        while try_times > 0 {
             if let Err(err_navi) = self.wda.go_url(&url) {
                 if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_navi {
                     if err.contains("timeout") {
@@ -664,7 +864,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
                             cate: Category::Gtrans,
                             is_fin: false,
                         })?;
-                        try_times -= 1;
+                        attempts.fail();
                         continue;
                     } else {
                         return Err(MafaError::WebDrvCmdRejected(err, msg));
@@ -683,7 +883,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
             let upath_curr = &self.caches[upaths_i].0;
             let arg0_detect_err = serde_json::to_string(&upath_curr[..]).unwrap();
 
-            sleep(Duration::from_millis(wait_before));
+            cancel::sleep(attempts.delay())?;
 
             match self
                 .wda
@@ -700,6 +900,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
                         dbgg!(&retstr);
                         // std::thread::sleep(std::time::Duration::from_secs(100));
                         translate_res = retstr;
+                        self.record_outcome(upaths_i, true);
                         self.notify(MafaEvent::SrvTempUnavRetry {
                             cate: Category::Gtrans,
                             is_fin: true,
@@ -711,6 +912,7 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
                 Err(err_eval) => {
                     if let WdaError::WdcFail(WdcError::BadDrvCmd(err, msg)) = err_eval {
                         if err.contains("timeout") {
+                            self.record_outcome(upaths_i, false);
                             upaths_i += 1;
                             if upaths_i < upaths_len {
                                 self.notify(MafaEvent::TryNextCache {
@@ -734,9 +936,8 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
                 }
             }
 
-            try_times -= 1;
-            wait_before += wait_before;
-            dbgmsg!("need retry {} {}", try_times, wait_before);
+            attempts.backoff();
+            dbgmsg!("need retry {}", attempts.left());
         }
 
         self.notify(MafaEvent::TryNextCache {
@@ -754,6 +955,46 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, GtransInput, Upath> {
 
 // ---------------------------
 
+///
+/// Key of the stored result, requests differing only in whitespaces share
+/// a result.
+fn result_key(sl: &str, tl: &str, words: &str) -> String {
+    format!(
+        "{}:{}:{}",
+        sl,
+        tl,
+        words.split_whitespace().collect::<Vec<&str>>().join(" ")
+    )
+}
+
+///
+/// (line number, words) of every non-empty line, "-" means standard input.
+fn read_batch_entries(from_file: &str) -> Result<Vec<(usize, String)>> {
+    let mut content = String::from("");
+
+    if from_file == "-" {
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|err_io| MafaError::InvalidFromFile(PathBuf::from(from_file), err_io))?;
+    } else {
+        content = std::fs::read_to_string(from_file)
+            .map_err(|err_io| MafaError::InvalidFromFile(PathBuf::from(from_file), err_io))?;
+    }
+
+    let entries = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_valid_words(line.trim()))
+        .map(|(i, line)| (i + 1, line.trim().to_string()))
+        .collect::<Vec<(usize, String)>>();
+
+    if entries.is_empty() {
+        return Err(MafaError::InvalidWords);
+    }
+
+    Ok(entries)
+}
+
 pub(crate) fn list_all_lang() -> &'static str {
     r"All languages supported by Google Translate (<Language>: <code>):
 
@@ -893,15 +1134,48 @@ Yoruba: yo
 Zulu: zu"
 }
 
-#[derive(Debug, Default)]
+///
+/// lists every `<Language>: <code>` line as a JSON object.
+fn list_all_lang_json() -> String {
+    #[derive(serde::Serialize)]
+    struct Lang<'a> {
+        name: &'a str,
+        code: &'a str,
+    }
+
+    let langs = list_all_lang()
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.rsplit_once(": "))
+        .map(|(name, code)| Lang { name, code })
+        .collect::<Vec<Lang>>();
+
+    serde_json::to_string(&langs).expect("buggy")
+}
+
+#[derive(Debug, Default, serde::Serialize)]
 struct GtransResult<'a, 'b> {
+    #[serde(rename = "source_lang")]
     sl: Cow<'a, str>,
+    #[serde(rename = "target_lang")]
     tl: Cow<'a, str>,
+    #[serde(rename = "words")]
     orig_words: Cow<'a, str>,
+    #[serde(rename = "translation", serialize_with = "ser_printable")]
     trans_words: Cow<'b, str>,
+    #[serde(rename = "pronunciation", serialize_with = "ser_printable")]
     trans_pronun: Cow<'b, str>,
 }
 
+// serde hands over the field as it is
+#[allow(clippy::ptr_arg)]
+fn ser_printable<S: serde::Serializer>(
+    s: &Cow<'_, str>,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&comm::make_printable(s))
+}
+
 impl<'a, 'b> GtransResult<'a, 'b> {
     fn from_str(
         sl: &'a str,
@@ -923,6 +1197,11 @@ impl<'a, 'b> GtransResult<'a, 'b> {
             }
         }
 
+        // quoted only at the beginning
+        if begi_trans > endi_trans {
+            return Err(MafaError::UpathValNotMatched);
+        }
+
         Ok(GtransResult {
             sl: sl.into(),
             tl: tl.into(),
@@ -945,10 +1224,11 @@ impl<'a, 'b> GtransResult<'a, 'b> {
             format!(" Result │")
         };
 
+        let theme = theme::current(nocolor);
         let header_part_colorful = if asciiful {
-            format!(" \x1b[36;1mResult\x1b[0m |")
+            format!(" {} |", theme.header.paint("Result"))
         } else {
-            format!(" \x1b[36;1mResult\x1b[0m │")
+            format!(" {} │", theme.header.paint("Result"))
         };
 
         // dbgg!(&header_part);
@@ -970,11 +1250,7 @@ impl<'a, 'b> GtransResult<'a, 'b> {
         output += line_tail_comp.0;
         output += "\n";
 
-        output += if nocolor {
-            &header_part
-        } else {
-            &header_part_colorful
-        };
+        output += &header_part_colorful;
         output += "\n";
 
         // bottom line
@@ -1191,4 +1467,56 @@ mod utst_merged {
             Err(_) => assert!(false),
         }
     }
+
+    #[test]
+    fn json_1() {
+        let gtrans_res = GtransResult::from_str("en", "fr", "hello world", r#""bonjour le monde""#)
+            .expect("buggy");
+
+        assert_eq!(
+            serde_json::to_string(&gtrans_res).expect("buggy"),
+            r#"{"source_lang":"en","target_lang":"fr","words":"hello world","translation":"bonjour le monde","pronunciation":""}"#
+        );
+    }
+
+    #[test]
+    fn from_file_1() {
+        let pbuf = std::env::temp_dir().join("mafa-gtrans-from-file-1");
+        std::fs::write(&pbuf, "hello\n\n  good morning \nthank you\n").expect("buggy");
+
+        let matched = crate::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "gtrans",
+                "--from-file",
+                pbuf.to_str().expect("buggy"),
+            ])
+            .expect("buggy");
+
+        match matched.subcommand() {
+            Some(("gtrans", sub_m)) => {
+                let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+                let from_file = gtrans_in.from_file.expect("must some");
+                assert_eq!(
+                    read_batch_entries(&from_file).expect("must ok"),
+                    vec![
+                        (1, "hello".to_string()),
+                        (3, "good morning".to_string()),
+                        (4, "thank you".to_string())
+                    ]
+                );
+            }
+            _ => panic!("buggy"),
+        }
+
+        std::fs::remove_file(&pbuf).expect("buggy");
+    }
+
+    #[test]
+    fn from_file_2() {
+        match read_batch_entries("/path/not/existing/list.txt") {
+            Err(MafaError::InvalidFromFile(..)) => {}
+            _ => panic!("buggy"),
+        }
+    }
 }
@@ -0,0 +1,729 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! HTTP JSON API.
+//!
+//! Each component is an endpoint, e.g., `/gtrans`, `/camd`, `/twtl`, whose
+//! parameters are the component's options, named by their long names, plus
+//! `words` or `username` for the positional ones:
+//!
+//! ```text
+//! GET /gtrans?tl=fr&words=thank%20you
+//! POST /camd {"words": "hello"}
+//! ```
+//!
+//! Results are the same as `--output json`. Requests are handled one at a
+//! time, on one browser session. Failures are reported as
+//! `{"error": {"kind": ..., "message": ...}}`, with a status code
+//! according to the error.
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use clap::Arg as ClapArg;
+use clap::ArgMatches as ClapArgMatches;
+use clap::Command as ClapCommand;
+
+use crate::backend::BrowserBackend;
+use crate::config;
+use crate::error::MafaError;
+use crate::error::Result;
+use crate::ev_ntf::EventNotifier;
+use crate::mafadata::MafaData;
+use crate::MafaClient;
+use crate::MafaInput;
+use crate::OutputFormat;
+
+#[cfg(feature = "camd")]
+use crate::camd::CamdInput;
+#[cfg(feature = "gtrans")]
+use crate::gtrans::GtransInput;
+#[cfg(feature = "twtl")]
+use crate::twtl::TwtlInput;
+
+#[derive(Debug, Default)]
+pub struct HttpdInput {
+    pub listen: String,
+}
+
+impl HttpdInput {
+    pub fn from_ca_matched(ca_matched: &ClapArgMatches) -> Result<Self> {
+        let mut httpd_in = HttpdInput::default();
+
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::Listen::id()) {
+            httpd_in.listen = optval.clone();
+        }
+
+        Ok(httpd_in)
+    }
+}
+
+pub mod opts {
+    use core::ops::Range;
+
+    pub struct Listen;
+    impl Listen {
+        #[inline]
+        pub fn id() -> &'static str {
+            "LISTEN"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "listen"
+        }
+        #[inline]
+        pub fn def_val() -> &'static str {
+            "127.0.0.1:8421"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "The address to listen on"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"The address to listen on
+
+The address is in form of IP:PORT, e.g., 127.0.0.1:8421.
+
+NOTE: there is no authentication, listening on addresses other than loopback ones exposes the browser session, which may be logged in, to others."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+}
+
+pub fn get_cmd() -> ClapCommand {
+    let opt_listen = {
+        type O = opts::Listen;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("serve"),
+            O::longopt(),
+        )
+    };
+
+    ClapCommand::new("serve")
+        .about("Serve components as an HTTP JSON API")
+        .long_about(
+            "Serve components as an HTTP JSON API
+
+Components are served at /gtrans, /camd and /twtl, with parameters named
+after their options' long names, e.g.,
+
+  GET /gtrans?tl=fr&words=thank%20you
+  POST /camd {\"words\": \"hello\"}
+
+Results are identical to the ones of --output json. Requests are handled
+one at a time, on one browser session.",
+        )
+        .arg(opt_listen)
+}
+
+///
+/// parameters must not touch files of the serving side, nor hold the
+/// browser session for long, e.g., twtl's login
+const PARAMS_DENIED: [&str; 4] = ["save-to", "save-format", "from-file", "login"];
+
+///
+/// bodies larger than this are refused with 413
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+///
+/// request lines and headers longer than this are refused with 431, so
+/// are requests with more headers than MAX_HEADERS
+const MAX_LINE_LEN: usize = 8 * 1024;
+const MAX_HEADERS: usize = 64;
+
+///
+/// idle requesters are dropped after this long, in seconds
+const CONN_TIMEOUT: u64 = 10;
+
+#[derive(Debug, PartialEq)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    params: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+struct HttpResponse {
+    status: u16,
+    body: String,
+}
+
+impl HttpResponse {
+    fn error(status: u16, kind: &str, message: &str) -> Self {
+        HttpResponse {
+            status,
+            body: serde_json::json!({
+                "error": { "kind": kind, "message": message }
+            })
+            .to_string(),
+        }
+    }
+
+    fn from_mafa_error(err: &MafaError) -> Self {
+        let dbg = format!("{:?}", err);
+        let kind = dbg.split('(').next().unwrap_or_default();
+
+        HttpResponse::error(status_of(err), kind, &err.to_string())
+    }
+
+    fn bad_request(reason: &str) -> Self {
+        HttpResponse::error(400, "BadRequest", reason)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            500 => "Internal Server Error",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "Unknown",
+        };
+
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )
+        .into_bytes()
+    }
+}
+
+///
+/// 400 for invalid parameters, 401 for login required, 404 for no stored
+/// result while offline, 502 for the page not being recognized, 503 for a
+/// cache being locked by others, 504 for the page not being reachable in
+/// time, 500 for others.
+fn status_of(err: &MafaError) -> u16 {
+    match err {
+        MafaError::ClapMatchError(_)
+        | MafaError::InvalidTimeoutPageLoad
+        | MafaError::InvalidTimeoutScript
+        | MafaError::InvalidSocks5Proxy
+        | MafaError::InvalidSourceLang
+        | MafaError::InvalidTargetLang
+        | MafaError::InvalidWords
+        | MafaError::InvalidTwitterUsername
+        | MafaError::InvalidNumTweets
+        | MafaError::InvalidWrapWidth
+        | MafaError::InvalidFromFile(..)
+        | MafaError::InvalidResultTtl
+        | MafaError::InvalidRetryPolicy(_)
+        | MafaError::InvalidCacheRemote
+        | MafaError::InvalidEventLog(..)
+        | MafaError::InvalidElapTrace(..) => 400,
+
+        MafaError::RequireLogin => 401,
+
+        MafaError::ResultNotStored => 404,
+
+        MafaError::CacheLockTimeout => 503,
+
+        MafaError::WebDrvCmdRejected(err, msg)
+            if err.contains("timeout") || msg.contains("Timed out") =>
+        {
+            504
+        }
+        MafaError::DataFetchedNotReachable
+        | MafaError::RemoteCacheNotReachable(_)
+        | MafaError::RemoteManifestMissing(_) => 504,
+
+        MafaError::WebDrvCmdRejected(..)
+        | MafaError::CacheRebuildFail(_)
+        | MafaError::CacheNotBuildable
+        | MafaError::AllCachesInvalid
+        | MafaError::UpathNotFound
+        | MafaError::UpathLenNotMatched
+        | MafaError::UpathValNotMatched
+        | MafaError::TweetNotRecoginized(_)
+        | MafaError::CamdLevelNotRecoginized(_)
+        | MafaError::CacheCorrupted
+        | MafaError::RemoteCacheRejected(_) => 502,
+
+        _ => 500,
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let hexval = |b: u8| (b as char).to_digit(16).map(|v| v as u8);
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hexval(bytes[i + 1]), hexval(bytes[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    decoded.push(hi * 16 + lo);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            other => decoded.push(other),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+        .map(|kv| match kv.split_once('=') {
+            Some((k, v)) => (percent_decode(k), percent_decode(v)),
+            None => (percent_decode(kv), String::from("true")),
+        })
+        .collect()
+}
+
+fn parse_json_params(body: &str) -> core::result::Result<Vec<(String, String)>, String> {
+    let obj = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(obj)) => obj,
+        _ => return Err("body is not a JSON object".to_string()),
+    };
+
+    let mut params = vec![];
+    for (k, v) in obj {
+        let v = match v {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            _ => return Err(format!("parameter {} is not a scalar", k)),
+        };
+        params.push((k, v));
+    }
+
+    Ok(params)
+}
+
+///
+/// Append one line of `reader` to `line`, at most MAX_LINE_LEN bytes.
+fn read_line_capped(
+    reader: &mut impl BufRead,
+    line: &mut String,
+) -> core::result::Result<usize, HttpResponse> {
+    let nread = reader
+        .take(MAX_LINE_LEN as u64 + 1)
+        .read_line(line)
+        .map_err(|_| HttpResponse::bad_request("request not readable"))?;
+    if nread > MAX_LINE_LEN {
+        return Err(HttpResponse::error(
+            431,
+            "RequestHeaderFieldsTooLarge",
+            &format!("line longer than {} bytes", MAX_LINE_LEN),
+        ));
+    }
+
+    Ok(nread)
+}
+
+fn read_request(stream: &mut impl Read) -> core::result::Result<HttpRequest, HttpResponse> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    read_line_capped(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or(HttpResponse::bad_request("no method"))?
+        .to_string();
+    let target = parts
+        .next()
+        .ok_or(HttpResponse::bad_request("no target"))?
+        .to_string();
+
+    let mut content_len = 0usize;
+    let mut n_headers = 0usize;
+    loop {
+        let mut hdr = String::new();
+        let nread = read_line_capped(&mut reader, &mut hdr)?;
+        let hdr = hdr.trim_end();
+        if nread == 0 || hdr.is_empty() {
+            break;
+        }
+        n_headers += 1;
+        if n_headers > MAX_HEADERS {
+            return Err(HttpResponse::error(
+                431,
+                "RequestHeaderFieldsTooLarge",
+                &format!("more than {} headers", MAX_HEADERS),
+            ));
+        }
+        if let Some((k, v)) = hdr.split_once(':') {
+            if k.trim().eq_ignore_ascii_case("content-length") {
+                content_len = v
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| HttpResponse::bad_request("invalid content-length"))?;
+            }
+        }
+    }
+
+    if content_len > MAX_BODY_LEN {
+        return Err(HttpResponse::error(
+            413,
+            "PayloadTooLarge",
+            &format!("body larger than {} bytes", MAX_BODY_LEN),
+        ));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let mut params = parse_query(query);
+
+    if content_len > 0 {
+        let mut body = vec![0u8; content_len];
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| HttpResponse::bad_request("body not readable"))?;
+        params.extend(
+            parse_json_params(&String::from_utf8_lossy(&body))
+                .map_err(|reason| HttpResponse::bad_request(&reason))?,
+        );
+    }
+
+    Ok(HttpRequest {
+        method,
+        path: path.to_string(),
+        params,
+    })
+}
+
+fn is_truthy(v: &str) -> bool {
+    matches!(v, "true" | "yes" | "on" | "1")
+}
+
+///
+/// Command line arguments equivalent to `params`, for component `cmd`.
+fn params_to_args(
+    cmd: &ClapCommand,
+    params: &[(String, String)],
+) -> core::result::Result<Vec<String>, String> {
+    let mut args = vec![cmd.get_name().to_string()];
+    let mut positionals = vec![];
+
+    for (k, v) in params {
+        if PARAMS_DENIED.contains(&k.as_str()) {
+            return Err(format!("parameter {} is not allowed", k));
+        }
+
+        let arg = cmd
+            .get_arguments()
+            .find(|arg| match arg.get_long() {
+                Some(longopt) => longopt == k,
+                // both "words" and "word" are accepted
+                None => {
+                    let id = arg.get_id().as_str();
+                    id.eq_ignore_ascii_case(k) || id.eq_ignore_ascii_case(k.trim_end_matches('s'))
+                }
+            })
+            .ok_or(format!("parameter {} is not recognized", k))?;
+
+        if arg.get_long().is_none() {
+            positionals.push(v.clone());
+        } else if !arg.get_action().takes_values() {
+            if is_truthy(v) {
+                args.push(format!("--{}", k));
+            }
+        } else {
+            args.push(format!("--{}", k));
+            args.push(v.clone());
+        }
+    }
+
+    if !positionals.is_empty() {
+        args.push("--".to_string());
+        args.extend(positionals);
+    }
+
+    Ok(args)
+}
+
+pub struct HttpApi<'a, 'c, B> {
+    mafad: &'a MafaData,
+    input: MafaInput,
+    wda: &'c B,
+}
+
+impl<'a, 'c, B: BrowserBackend> HttpApi<'a, 'c, B> {
+    pub fn new(mafad: &'a MafaData, mafa_in: &MafaInput, wda_inst: &'c B) -> Self {
+        let mut input = mafa_in.clone();
+        input.output = OutputFormat::Json;
+
+        HttpApi {
+            mafad,
+            input,
+            wda: wda_inst,
+        }
+    }
+
+    ///
+    /// One for each request, so that events of earlier requests are not
+    /// kept for the server's lifetime.
+    fn new_notifier() -> Arc<Mutex<EventNotifier>> {
+        // progress is not for anyone to see
+        let mut ntf = EventNotifier::new();
+        ntf.set_json();
+
+        Arc::new(Mutex::new(ntf))
+    }
+
+    ///
+    /// Handle connections one by one, forever.
+    pub fn serve(&self, listen: &str) -> Result<()> {
+        let listener = TcpListener::bind(listen).map_err(|_| MafaError::InvalidListenAddr)?;
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => self.handle_conn(stream),
+                Err(_err_io) => {
+                    dbgg!(_err_io);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_conn(&self, mut stream: TcpStream) {
+        // requests are served one at a time, an idle requester must not
+        // hold the others forever
+        let timeout = Some(Duration::from_secs(CONN_TIMEOUT));
+        let _ = stream.set_read_timeout(timeout);
+        let _ = stream.set_write_timeout(timeout);
+
+        let resp = match read_request(&mut stream) {
+            Ok(req) => self.handle_request(&req),
+            Err(resp) => resp,
+        };
+
+        // the requester may have gone, nothing to do then
+        let _ = stream.write_all(&resp.to_bytes());
+    }
+
+    fn handle_request(&self, req: &HttpRequest) -> HttpResponse {
+        if req.method != "GET" && req.method != "POST" {
+            return HttpResponse::error(405, "MethodNotAllowed", "only GET and POST");
+        }
+
+        let ret = match req.path.as_str() {
+            #[cfg(feature = "gtrans")]
+            "/gtrans" => self.handle_gtrans(&req.params),
+            #[cfg(feature = "camd")]
+            "/camd" => self.handle_camd(&req.params),
+            #[cfg(feature = "twtl")]
+            "/twtl" => self.handle_twtl(&req.params),
+            _ => return HttpResponse::error(404, "NotFound", "no such component"),
+        };
+
+        match ret {
+            Ok(Ok(body)) => HttpResponse { status: 200, body },
+            Ok(Err(err)) => HttpResponse::from_mafa_error(&err),
+            Err(reason) => HttpResponse::error(400, "InvalidParameter", &reason),
+        }
+    }
+
+    #[cfg(feature = "gtrans")]
+    fn handle_gtrans(
+        &self,
+        params: &[(String, String)],
+    ) -> core::result::Result<Result<String>, String> {
+        let args = params_to_args(&crate::gtrans::get_cmd(), params)?;
+        let sub_in = match GtransInput::from_imode_args(args.iter().map(|v| v.as_str()).collect()) {
+            Ok(v) => v,
+            Err(err_in) => return Ok(Err(err_in)),
+        };
+
+        let mut client = MafaClient::new(
+            self.mafad,
+            Self::new_notifier(),
+            &self.input,
+            sub_in,
+            self.wda,
+        );
+
+        Ok(client.handle(None).map(|(_, out)| out))
+    }
+
+    #[cfg(feature = "camd")]
+    fn handle_camd(
+        &self,
+        params: &[(String, String)],
+    ) -> core::result::Result<Result<String>, String> {
+        let args = params_to_args(&crate::camd::get_cmd(), params)?;
+        let sub_in = match CamdInput::from_imode_args(args.iter().map(|v| v.as_str()).collect()) {
+            Ok(v) => v,
+            Err(err_in) => return Ok(Err(err_in)),
+        };
+
+        let mut client = MafaClient::new(
+            self.mafad,
+            Self::new_notifier(),
+            &self.input,
+            sub_in,
+            self.wda,
+        );
+
+        Ok(client.handle(None).map(|(_, out)| out))
+    }
+
+    #[cfg(feature = "twtl")]
+    fn handle_twtl(
+        &self,
+        params: &[(String, String)],
+    ) -> core::result::Result<Result<String>, String> {
+        let args = params_to_args(&crate::twtl::get_cmd(), params)?;
+        let sub_in = match TwtlInput::from_imode_args(args.iter().map(|v| v.as_str()).collect()) {
+            Ok(v) => v,
+            Err(err_in) => return Ok(Err(err_in)),
+        };
+
+        let mut client = MafaClient::new(
+            self.mafad,
+            Self::new_notifier(),
+            &self.input,
+            sub_in,
+            self.wda,
+        );
+
+        Ok(client.handle(None).map(|(_, out)| out))
+    }
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    #[test]
+    fn percent_decode_1() {
+        assert_eq!(percent_decode("thank%20you"), "thank you");
+        assert_eq!(percent_decode("thank+you"), "thank you");
+        assert_eq!(percent_decode("%E4%BD%A0%E5%A5%BD"), "你好");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn read_request_1() {
+        let raw = "GET /gtrans?tl=fr&words=thank%20you HTTP/1.1\r\nHost: x\r\n\r\n";
+        let req = read_request(&mut raw.as_bytes()).expect("buggy");
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/gtrans");
+        assert_eq!(
+            req.params,
+            vec![
+                ("tl".to_string(), "fr".to_string()),
+                ("words".to_string(), "thank you".to_string())
+            ]
+        );
+
+        let raw =
+            "POST /twtl HTTP/1.1\r\nContent-Length: 30\r\n\r\n{\"username\":\"abc\",\"ntweets\":5}";
+        let req = read_request(&mut raw.as_bytes()).expect("buggy");
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/twtl");
+        assert!(req
+            .params
+            .contains(&("ntweets".to_string(), "5".to_string())));
+
+        let raw = "POST /twtl HTTP/1.1\r\nContent-Length: 2\r\n\r\n[]";
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 400);
+
+        let raw = "POST /twtl HTTP/1.1\r\nContent-Length: 999999999999\r\n\r\n{}";
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 413);
+
+        let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_LEN));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+
+        let raw = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X: y\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+    }
+
+    #[cfg(feature = "gtrans")]
+    #[test]
+    fn params_to_args_1() {
+        let cmd = crate::gtrans::get_cmd();
+        let params = vec![
+            ("tl".to_string(), "fr".to_string()),
+            ("list-lang".to_string(), "false".to_string()),
+            ("words".to_string(), "-thank you".to_string()),
+        ];
+        assert_eq!(
+            params_to_args(&cmd, &params).expect("buggy"),
+            vec!["gtrans", "--tl", "fr", "--", "-thank you"]
+        );
+
+        let params = vec![("from-file".to_string(), "/etc/passwd".to_string())];
+        assert!(params_to_args(&cmd, &params).is_err());
+
+        let params = vec![("nope".to_string(), "".to_string())];
+        assert!(params_to_args(&cmd, &params).is_err());
+    }
+
+    #[test]
+    fn status_of_1() {
+        assert_eq!(status_of(&MafaError::InvalidTargetLang), 400);
+        assert_eq!(status_of(&MafaError::RequireLogin), 401);
+        assert_eq!(status_of(&MafaError::TweetNotRecoginized(1)), 502);
+        assert_eq!(
+            status_of(&MafaError::WebDrvCmdRejected(
+                "timeout".to_string(),
+                "".to_string()
+            )),
+            504
+        );
+        assert_eq!(status_of(&MafaError::Bug("lock poisoned")), 500);
+
+        let resp = HttpResponse::from_mafa_error(&MafaError::CamdLevelNotRecoginized(3));
+        assert_eq!(resp.status, 502);
+        assert_eq!(
+            resp.body,
+            r#"{"error":{"kind":"CamdLevelNotRecoginized","message":"word level not recognized(3)"}}"#
+        );
+    }
+}
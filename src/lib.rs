@@ -10,6 +10,7 @@
 use std::borrow::Cow;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use wda::{GeckoDriver, WdaError, WdaSett, WdcError, WebDrvAstn};
 
 use clap::Arg as ClapArg;
@@ -32,6 +33,29 @@ pub mod ev_ntf;
 use ev_ntf::EurKind;
 use ev_ntf::EventNotifier;
 
+pub mod backend;
+
+pub mod cassette;
+
+pub mod config;
+
+pub mod cancel;
+
+pub mod retry;
+use retry::RetryOverride;
+
+pub mod theme;
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+pub mod cache;
+
+pub mod daemon;
+
+pub mod doctor;
+
+#[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+pub mod httpd;
+
 #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
 mod comm;
 #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
@@ -46,21 +70,52 @@ pub mod gtrans;
 #[cfg(feature = "camd")]
 pub mod camd;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    fn from_str(s: &str) -> Self {
+        match s {
+            "TEXT" | "text" => Self::Text,
+            "JSON" | "json" => Self::Json,
+            _ => Self::Text,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct MafaInput {
     pub silent: bool,
     pub nocolor: bool,
     pub ascii: bool,
+    ///
+    /// 0 for following the terminal, see [`MafaInput::wrap_width_now`]
     pub wrap_width: u16,
     pub wrap_may_break: bool,
     pub tout_page_load: u32,
     pub tout_script: u32,
+    pub retry: RetryOverride,
     pub socks5: String,
     pub gui: bool,
     pub list_profile: bool,
     pub use_profile: String,
     cachm: CacheMechanism,
+    pub cache_remote: String,
+    pub cache_manifest: String,
     pub elap: bool,
+    pub elap_trace: String,
+    pub output: OutputFormat,
+    pub events_json: bool,
+    pub event_log: String,
+    pub record: String,
+    pub replay: String,
+    pub no_daemon: bool,
+    pub result_ttl: u64,
+    pub offline: bool,
 }
 
 impl MafaInput {
@@ -84,9 +139,16 @@ impl MafaInput {
 
         // wrap-width
         if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::WrapWidth::id()) {
-            let intval =
-                u16::from_str_radix(&optval, 10).map_err(|_| MafaError::InvalidWrapWidth)?;
-            mafa_in.wrap_width = intval;
+            if optval != "auto" {
+                let intval = optval
+                    .parse::<u16>()
+                    .map_err(|_| MafaError::InvalidWrapWidth)?;
+                mafa_in.wrap_width = if intval < MIN_WRAP_WIDTH {
+                    DEF_WRAP_WIDTH
+                } else {
+                    intval
+                };
+            }
         }
 
         // wrap-may-break
@@ -100,7 +162,7 @@ impl MafaInput {
                 .or_else(|_| Err(MafaError::InvalidTimeoutPageLoad))?;
         } else {
             mafa_in.tout_page_load = u32::from_str_radix(opts::TimeoutPageLoad::def_val(), 10)
-                .or_else(|_| Err(MafaError::Buggy))?;
+                .or_else(|_| Err(MafaError::Bug("invalid default page load timeout")))?;
         }
 
         // script timeout
@@ -109,7 +171,56 @@ impl MafaInput {
                 .or_else(|_| Err(MafaError::InvalidTimeoutScript))?;
         } else {
             mafa_in.tout_script = u32::from_str_radix(opts::TimeoutScript::def_val(), 10)
-                .or_else(|_| Err(MafaError::Buggy))?;
+                .or_else(|_| Err(MafaError::Bug("invalid default script timeout")))?;
+        }
+
+        // retry max
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::RetryMax::id()) {
+            let intval = optval
+                .parse::<u32>()
+                .map_err(|_| MafaError::InvalidRetryPolicy(opts::RetryMax::longopt()))?;
+            if intval == 0 {
+                return Err(MafaError::InvalidRetryPolicy(opts::RetryMax::longopt()));
+            }
+            mafa_in.retry.max_attempts = Some(intval);
+        }
+
+        // retry delay
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::RetryDelay::id()) {
+            let intval = optval
+                .parse::<u64>()
+                .map_err(|_| MafaError::InvalidRetryPolicy(opts::RetryDelay::longopt()))?;
+            mafa_in.retry.base_delay = Some(Duration::from_millis(intval));
+        }
+
+        // retry multiplier
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::RetryMultiplier::id()) {
+            let fval = optval
+                .parse::<f64>()
+                .ok()
+                .filter(|v| *v >= 1.0 && *v <= 10.0)
+                .ok_or(MafaError::InvalidRetryPolicy(
+                    opts::RetryMultiplier::longopt(),
+                ))?;
+            mafa_in.retry.multiplier = Some(fval);
+        }
+
+        // retry jitter
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::RetryJitter::id()) {
+            let fval = optval
+                .parse::<f64>()
+                .ok()
+                .filter(|v| *v >= 0.0 && *v <= 1.0)
+                .ok_or(MafaError::InvalidRetryPolicy(opts::RetryJitter::longopt()))?;
+            mafa_in.retry.jitter = Some(fval);
+        }
+
+        // retry deadline
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::RetryDeadline::id()) {
+            let intval = optval
+                .parse::<u64>()
+                .map_err(|_| MafaError::InvalidRetryPolicy(opts::RetryDeadline::longopt()))?;
+            mafa_in.retry.deadline = Some(Duration::from_millis(intval));
         }
 
         // socks5
@@ -127,11 +238,76 @@ impl MafaInput {
             mafa_in.elap = true;
         }
 
+        // elap trace
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::ElapTrace::id()) {
+            mafa_in.elap_trace = val.clone();
+        }
+
         // cachm
         if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::CacheMech::id()) {
             mafa_in.cachm = CacheMechanism::from_str(optval);
         }
 
+        // cache remote
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::CacheRemote::id()) {
+            #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+            if !cache::is_valid_remote(val) {
+                return Err(MafaError::InvalidCacheRemote);
+            }
+            mafa_in.cache_remote = val.clone();
+        }
+
+        // cache manifest
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::CacheManifest::id()) {
+            #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+            if !cache::is_valid_remote(val) {
+                return Err(MafaError::InvalidCacheRemote);
+            }
+            mafa_in.cache_manifest = val.clone();
+        }
+
+        // output
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::Output::id()) {
+            mafa_in.output = OutputFormat::from_str(optval);
+        }
+
+        // events json
+        if ca_matched.get_flag(opts::EventsJson::id()) {
+            mafa_in.events_json = true;
+        }
+
+        // event log
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::EventLog::id()) {
+            mafa_in.event_log = val.clone();
+        }
+
+        // record
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::Record::id()) {
+            mafa_in.record = val.clone();
+        }
+
+        // replay
+        if let Ok(Some(val)) = ca_matched.try_get_one::<String>(opts::Replay::id()) {
+            mafa_in.replay = val.clone();
+        }
+
+        // result ttl
+        if let Ok(Some(optval)) = ca_matched.try_get_one::<String>(opts::ResultTtl::id()) {
+            mafa_in.result_ttl = optval
+                .parse::<u64>()
+                .map_err(|_| MafaError::InvalidResultTtl)?;
+        }
+
+        // offline
+        if ca_matched.get_flag(opts::Offline::id()) {
+            mafa_in.offline = true;
+        }
+
+        // no daemon
+        if ca_matched.get_flag(opts::NoDaemon::id()) {
+            mafa_in.no_daemon = true;
+        }
+
         // list profile
         if ca_matched.get_flag(opts::ListProfile::id()) {
             mafa_in.list_profile = true;
@@ -146,6 +322,38 @@ impl MafaInput {
 
         Ok(mafa_in)
     }
+
+    ///
+    /// The explicit wrap width, or the terminal's current width, read
+    /// anew on every call so that resizing takes effect on the next
+    /// output.
+    pub fn wrap_width_now(&self) -> u16 {
+        if self.wrap_width > 0 {
+            return self.wrap_width;
+        }
+
+        match term_width() {
+            Some(w) => w.max(MIN_WRAP_WIDTH),
+            None => DEF_WRAP_WIDTH,
+        }
+    }
+}
+
+const MIN_WRAP_WIDTH: u16 = 18;
+const DEF_WRAP_WIDTH: u16 = 80;
+
+///
+/// Columns of the terminal at standard output, None if it is not a
+/// terminal.
+pub fn term_width() -> Option<u16> {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+
+    if rc == 0 && ws.ws_col > 0 {
+        Some(ws.ws_col)
+    } else {
+        None
+    }
 }
 
 // opts //
@@ -204,9 +412,11 @@ Any insignificant output redirected to standard output will be hidden.";
         pub fn long_helper() -> String {
             let bf = "Enable non-color mode
 
-Any output will be printed without color. Default is with color.";
+Any output will be printed without color. Default is with color, unless
+NO_COLOR is set or the output is not a terminal. Colors can be customized
+in the [theme] table of the config file.";
 
-            let mut af_buf = [0u8; 256];
+            let mut af_buf = [0u8; 512];
 
             let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
                 .unwrap()
@@ -265,7 +475,7 @@ NOTE: the minimum is 18, any value smaller than 18 will fallback to 80."#;
         }
         #[inline]
         pub fn def_val() -> &'static str {
-            "80"
+            "auto"
         }
         #[inline]
         pub fn helper() -> &'static str {
@@ -275,8 +485,10 @@ NOTE: the minimum is 18, any value smaller than 18 will fallback to 80."#;
         pub fn long_helper() -> String {
             let bf = r#"Wrap output with a width limit
 
-NOTE: the minimun is 18, any value smaller than 18 will fallback to 80."#;
-            let mut af_buf = [0u8; 128];
+Default is "auto", which follows the width of the terminal, re-read before
+every output, or 80 if the output is not a terminal. NOTE: the minimum is
+18, any value smaller than 18 will fallback to 80."#;
+            let mut af_buf = [0u8; 256];
 
             let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
                 .unwrap()
@@ -547,6 +759,176 @@ NOTE: customizing this option mostly brings the negative effect for script evalu
         }
     }
 
+    pub struct RetryMax;
+    impl RetryMax {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RETRY_MAX"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "retry-max"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Maximum attempts of each retry loop"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Maximum attempts of each retry loop
+
+Requests to websites, e.g., navigating to a page or extracting results from it, are retried on timeouts. Each kind of retry has its own default, this option replaces all of them. Larger values help slow connections, smaller ones make failures quicker."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct RetryDelay;
+    impl RetryDelay {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RETRY_DELAY"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "retry-delay"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Delay of the first retry(ms)"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Delay of the first retry(ms)
+
+The time waited in the first attempt of each retry loop, e.g., for the page to load before extracting results. It grows by --retry-multiplier on each retry. Each kind of retry has its own default, this option replaces all of them."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct RetryMultiplier;
+    impl RetryMultiplier {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RETRY_MULTIPLIER"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "retry-multiplier"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Growth factor of retry delays"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Growth factor of retry delays
+
+The delay of each retry is the previous one multiplied by this factor, e.g., 2 doubles it every time, 1 keeps it constant. It ranges from 1 to 10. Each kind of retry has its own default, this option replaces all of them."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct RetryJitter;
+    impl RetryJitter {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RETRY_JITTER"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "retry-jitter"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Random variation of retry delays, 0 to 1"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Random variation of retry delays, 0 to 1
+
+Each retry delay is randomly shifted by up to this fraction of itself, e.g., 0.2 turns a delay of 1000ms into one between 800ms and 1200ms. Default is 0, no variation."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct RetryDeadline;
+    impl RetryDeadline {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RETRY_DEADLINE"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "retry-deadline"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Time limit of each retry loop(ms)"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Time limit of each retry loop(ms)
+
+No more attempts are made once this long has passed since the first one, regardless of --retry-max. Default is no limit."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
     pub struct CacheMech;
     impl CacheMech {
         #[inline]
@@ -605,125 +987,763 @@ Stability   :    NO > REMOTE > LOCAL"#;
             "Report the time cost in major phases"
         }
     }
-}
-
-pub fn get_cmd() -> ClapCommand {
-    let opt_silient = {
-        type O = opts::SilentMode;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
-
-    let opt_nocolor = {
-        type O = opts::NoColorMode;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
-
-    let opt_ascii = {
-        type O = opts::AsciiMode;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-    };
 
-    let opt_wrapwidth = {
-        type O = opts::WrapWidth;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
+    pub struct ElapTrace;
+    impl ElapTrace {
+        #[inline]
+        pub fn id() -> &'static str {
+            "ELAP_TRACE"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "elap-trace"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Write the timeline of all phases to a trace file"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Write the timeline of all phases to a trace file
 
-    let opt_wrapmaybreak = {
-        type O = opts::WrapMayBreak;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
+Before mafa exits, the timeline of every phase, including every retry and every attempt of other caches, is written to FILE in Chrome trace-event JSON, which can be loaded by chrome://tracing or Perfetto. The file is overwritten if it exists."#;
+            let mut af_buf = [0u8; 512];
 
-    let opt_gui = {
-        type O = opts::GuiMode;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
 
-    let opt_socks5 = {
-        type O = opts::Socks5Proxy;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .num_args(O::n_args())
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
 
-    let opt_tout_pageload = {
-        type O = opts::TimeoutPageLoad;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .num_args(O::n_args())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
-    };
+    pub struct Output;
+    impl Output {
+        #[inline]
+        pub fn id() -> &'static str {
+            "OUTPUT"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "output"
+        }
+        #[inline]
+        pub fn def_val() -> &'static str {
+            "text"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "The format of components' result"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"The format of components' result
+
+Available values are: text, json.
+
+text instructs mafa to pretty-print results for human reading; json instructs mafa to print results as JSON documents, with all progress messages hidden, which suits for scripting.
+
+NOTE: errors are still reported to standard error in human-readable form."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct EventsJson;
+    impl EventsJson {
+        #[inline]
+        pub fn id() -> &'static str {
+            "EVENTS_JSON"
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "events-json"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Print every event as a JSON line to standard error"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Print every event as a JSON line to standard error
+
+Every event, such as progress, retries and errors, is printed as a JSON object on its own line, along with the milliseconds elapsed since mafa started. This is independent of --output and --silent, which affect only the human-readable messages."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct EventLog;
+    impl EventLog {
+        #[inline]
+        pub fn id() -> &'static str {
+            "EVENT_LOG"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "event-log"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Append every event to a log file"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Append every event to a log file
+
+Every event, such as progress, retries and errors, is appended to FILE as a line of text, prefixed with the seconds since the epoch. The file is created if it does not exist."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct Record;
+    impl Record {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RECORD"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "record"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Record browser interactions into a directory"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Record browser interactions into a directory
+
+Every URL visited and every raw string returned by the page scripts are written to DIR/cassette.jsonl, replacing the one recorded earlier, the directory is created if it does not exist. The recorded cassette can be served later by --replay.
+
+NOTE: the cassette may contain data from logged-in sessions, e.g., tweets of a private timeline, share it with care."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct Replay;
+    impl Replay {
+        #[inline]
+        pub fn id() -> &'static str {
+            "REPLAY"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "replay"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Replay browser interactions from a directory"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Replay browser interactions from a directory
+
+Serve the interactions recorded by --record in DIR/cassette.jsonl, in the same order they were recorded, instead of launching the browser. Any interaction not matching the recorded one fails as if the browser rejected it.
+
+NOTE: the component, its options and the cache mechanism should be identical to the ones used when recording, --cache NO is recommended for both."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct NoDaemon;
+    impl NoDaemon {
+        #[inline]
+        pub fn id() -> &'static str {
+            "NO_DAEMON"
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "no-daemon"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Do not forward to the running daemon"
+        }
+    }
+
+    pub struct CacheRemote;
+    impl CacheRemote {
+        #[inline]
+        pub fn id() -> &'static str {
+            "CACHE_REMOTE"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "cache-remote"
+        }
+        #[inline]
+        pub fn def_val() -> &'static str {
+            "https://raw.githubusercontent.com/micl2e2/mafa-cache/master"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Base URL of the remote cache"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Base URL of the remote cache
+
+The repository used by --cache REMOTE. Available schemes are file://, http:// and https://, e.g., file:///srv/mafa-cache for a local copy, http://127.0.0.1:8000 for a local mirror. http:// and https:// ones are fetched through the browser.
+
+The repository provides one file for each component. A remote cache that is malformed, or not matching its checksum in --cache-manifest if given, is rejected, leaving the local one intact."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct CacheManifest;
+    impl CacheManifest {
+        #[inline]
+        pub fn id() -> &'static str {
+            "CACHE_MANIFEST"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "cache-manifest"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "URL of checksums the remote cache is verified against"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"URL of checksums the remote cache is verified against
+
+A file in sha256sum format, listing the checksum of each component's cache. It is not taken from --cache-remote, so a mirror is trusted only if it serves the same caches as the manifest lists. A missing manifest is an error.
+
+Without it, a remote cache is only checked to be well-formed."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct ResultTtl;
+    impl ResultTtl {
+        #[inline]
+        pub fn id() -> &'static str {
+            "RESULT_TTL"
+        }
+        #[inline]
+        pub fn n_args() -> Range<usize> {
+            1..2
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "result-ttl"
+        }
+        #[inline]
+        pub fn def_val() -> &'static str {
+            "0"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Reuse stored results within TTL seconds"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Reuse stored results within TTL seconds
+
+Results fetched by gtrans and camd are always stored in mafa's data directory, keyed by the request, e.g., the source language, the target language and the words for gtrans. A request whose result was stored less than TTL seconds ago is answered from the store, without launching the browser.
+
+Stored results older than TTL seconds are removed whenever a new one is stored. 0 disables the reuse, and keeps all stored results for --offline."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+
+    pub struct Offline;
+    impl Offline {
+        #[inline]
+        pub fn id() -> &'static str {
+            "OFFLINE"
+        }
+        #[inline]
+        pub fn longopt() -> &'static str {
+            "offline"
+        }
+        #[inline]
+        pub fn helper() -> &'static str {
+            "Answer from stored results only"
+        }
+        #[inline]
+        pub fn long_helper() -> String {
+            let bf = r#"Answer from stored results only
+
+The browser is never launched, requests are answered from results stored previously, regardless of their age, see --result-ttl. A request without stored result fails."#;
+            let mut af_buf = [0u8; 512];
+
+            let rl = bwrap::Wrapper::new(bf, 70, &mut af_buf)
+                .unwrap()
+                .wrap()
+                .unwrap();
+
+            String::from_utf8_lossy(&af_buf[0..rl]).to_string()
+        }
+    }
+}
+
+pub fn get_cmd() -> ClapCommand {
+    let opt_silient = {
+        type O = opts::SilentMode;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_nocolor = {
+        type O = opts::NoColorMode;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_ascii = {
+        type O = opts::AsciiMode;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_wrapwidth = {
+        type O = opts::WrapWidth;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_wrapmaybreak = {
+        type O = opts::WrapMayBreak;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_gui = {
+        type O = opts::GuiMode;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_socks5 = {
+        type O = opts::Socks5Proxy;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_tout_pageload = {
+        type O = opts::TimeoutPageLoad;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
 
     let opt_tout_script = {
         type O = opts::TimeoutScript;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .num_args(O::n_args())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_retry_max = {
+        type O = opts::RetryMax;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_retry_delay = {
+        type O = opts::RetryDelay;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_retry_multiplier = {
+        type O = opts::RetryMultiplier;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_retry_jitter = {
+        type O = opts::RetryJitter;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_retry_deadline = {
+        type O = opts::RetryDeadline;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
     };
 
     let opt_cachm = {
         type O = opts::CacheMech;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_cache_remote = {
+        type O = opts::CacheRemote;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_cache_manifest = {
+        type O = opts::CacheManifest;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
     };
 
     let opt_elapsed = {
         type O = opts::Elapsed;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_elap_trace = {
+        type O = opts::ElapTrace;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_output = {
+        type O = opts::Output;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .value_parser(["text", "json"])
+                .ignore_case(true)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_events_json = {
+        type O = opts::EventsJson;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_event_log = {
+        type O = opts::EventLog;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_record = {
+        type O = opts::Record;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .conflicts_with(opts::Replay::id())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_replay = {
+        type O = opts::Replay;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_result_ttl = {
+        type O = opts::ResultTtl;
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .num_args(O::n_args())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_offline = {
+        type O = opts::Offline;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
+    };
+
+    let opt_no_daemon = {
+        type O = opts::NoDaemon;
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper()),
+            None,
+            O::longopt(),
+        )
     };
 
     let opt_list_profile = {
         type O = opts::ListProfile;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
     };
 
     let opt_use_profile = {
         type O = opts::UseProfile;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .short(O::shortopt())
-            .num_args(O::n_args())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .short(O::shortopt())
+                .num_args(O::n_args())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            None,
+            O::longopt(),
+        )
     };
 
     static HELPER_TXT: once_cell::sync::Lazy<String> = once_cell::sync::Lazy::new(|| {
@@ -758,7 +1778,8 @@ There is NO WARRANTY, to the extent permitted by law.";
     let cmd_mafa = ClapCommand::new("mafa")
         .version(clap::crate_version!())
         .long_version(HELPER_TXT.as_str())
-        .about(clap::crate_description!());
+        .about(clap::crate_description!())
+        .after_long_help(error::exit_code::HELPER);
 
     #[cfg(feature = "imode")]
     let cmd_mafa = cmd_mafa.subcommand(
@@ -777,6 +1798,18 @@ ones under normal mode, i.e., -h for short help, --help for long help.
             ),
     );
 
+    let cmd_mafa = cmd_mafa.subcommand(config::get_cmd());
+
+    #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+    let cmd_mafa = cmd_mafa.subcommand(cache::get_cmd());
+
+    let cmd_mafa = cmd_mafa.subcommand(daemon::get_cmd());
+
+    let cmd_mafa = cmd_mafa.subcommand(doctor::get_cmd());
+
+    #[cfg(any(feature = "twtl", feature = "gtrans", feature = "camd"))]
+    let cmd_mafa = cmd_mafa.subcommand(httpd::get_cmd());
+
     #[cfg(feature = "twtl")]
     let cmd_mafa = cmd_mafa.subcommand(twtl::get_cmd());
 
@@ -796,8 +1829,24 @@ ones under normal mode, i.e., -h for short help, --help for long help.
         .arg(opt_socks5)
         .arg(opt_tout_pageload)
         .arg(opt_tout_script)
+        .arg(opt_retry_max)
+        .arg(opt_retry_delay)
+        .arg(opt_retry_multiplier)
+        .arg(opt_retry_jitter)
+        .arg(opt_retry_deadline)
         .arg(opt_cachm)
+        .arg(opt_cache_remote)
+        .arg(opt_cache_manifest)
         .arg(opt_elapsed)
+        .arg(opt_elap_trace)
+        .arg(opt_output)
+        .arg(opt_events_json)
+        .arg(opt_event_log)
+        .arg(opt_record)
+        .arg(opt_replay)
+        .arg(opt_result_ttl)
+        .arg(opt_offline)
+        .arg(opt_no_daemon)
         .arg(opt_list_profile)
         .arg(opt_use_profile);
 
@@ -806,23 +1855,26 @@ ones under normal mode, i.e., -h for short help, --help for long help.
 
 //
 
+///
+/// `B` is the browser backend that components drive, which is the
+/// WebDriver assistant by default, see [`backend::BrowserBackend`].
 #[derive(Debug)]
-pub struct MafaClient<'a, 'b, 'c, I, C> {
+pub struct MafaClient<'a, 'b, 'c, I, C, B = WebDrvAstn<GeckoDriver>> {
     mafad: &'a MafaData,
     ntf: Arc<Mutex<EventNotifier>>,
     input: &'b MafaInput,
     sub_input: I,
-    wda: &'c WebDrvAstn<GeckoDriver>,
+    wda: &'c B,
     caches: Vec<C>,
 }
 
-impl<'a, 'b, 'c, I, C: Default> MafaClient<'a, 'b, 'c, I, C> {
+impl<'a, 'b, 'c, I, C: Default, B> MafaClient<'a, 'b, 'c, I, C, B> {
     pub fn new(
         mafad: &'a MafaData,
         ntf: Arc<Mutex<EventNotifier>>,
         mafa_in: &'b MafaInput,
         sub_in: I,
-        wda_inst: &'c WebDrvAstn<GeckoDriver>,
+        wda_inst: &'c B,
     ) -> Self {
         MafaClient {
             mafad,
@@ -837,6 +1889,33 @@ impl<'a, 'b, 'c, I, C: Default> MafaClient<'a, 'b, 'c, I, C> {
     pub fn set_sub_input(&mut self, newin: I) {
         self.sub_input = newin;
     }
+
+    ///
+    /// Result of `comp` stored for `key` within TTL, or regardless of TTL
+    /// if offline, in which case a missing one is an error.
+    #[cfg(any(feature = "gtrans", feature = "camd"))]
+    fn stored_result(&self, comp: &str, key: &str) -> Result<Option<String>> {
+        let ttl = if self.input.offline {
+            u64::MAX
+        } else {
+            self.input.result_ttl
+        };
+
+        match self.mafad.result_get(comp, key, ttl) {
+            Some(raw) => Ok(Some(raw)),
+            None if self.input.offline => Err(MafaError::ResultNotStored),
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// failing to store is not fatal, the result is still there
+    #[cfg(any(feature = "gtrans", feature = "camd"))]
+    fn store_result(&self, comp: &str, key: &str, raw: &str) {
+        if let Err(_err_store) = self.mafad.result_put(comp, key, raw, self.input.result_ttl) {
+            dbgg!(_err_store);
+        }
+    }
 }
 
 fn get_wda_setts(mafa_in: &MafaInput) -> Vec<WdaSett> {
@@ -874,6 +1953,36 @@ fn get_wda_setts(mafa_in: &MafaInput) -> Vec<WdaSett> {
     wda_setts
 }
 
+///
+/// Whether the request in `matched` is answered by a stored result within
+/// TTL, in which case there is no need to launch the browser.
+#[cfg(any(feature = "gtrans", feature = "camd"))]
+pub fn is_result_stored(mafad: &MafaData, mafa_in: &MafaInput, matched: &ClapArgMatches) -> bool {
+    if mafa_in.result_ttl == 0 {
+        return false;
+    }
+
+    let (comp, key) = match matched.subcommand() {
+        #[cfg(feature = "gtrans")]
+        Some(("gtrans", sub_m)) => {
+            match gtrans::GtransInput::from_ca_matched(sub_m).map(|v| v.result_key()) {
+                Ok(Some(key)) => ("gtrans", key),
+                _ => return false,
+            }
+        }
+
+        #[cfg(feature = "camd")]
+        Some(("camd", sub_m)) => match camd::CamdInput::from_ca_matched(sub_m) {
+            Ok(camd_in) => ("camd", camd_in.result_key()),
+            Err(_) => return false,
+        },
+
+        _ => return false,
+    };
+
+    mafad.result_get(comp, &key, mafa_in.result_ttl).is_some()
+}
+
 pub fn init_wda(mafa_in: &MafaInput) -> Result<WebDrvAstn<GeckoDriver>> {
     let wda_inst: WebDrvAstn<GeckoDriver>;
     let wda_setts = get_wda_setts(&mafa_in);
@@ -898,3 +2007,57 @@ pub fn init_wda(mafa_in: &MafaInput) -> Result<WebDrvAstn<GeckoDriver>> {
 
     Ok(wda_inst)
 }
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    fn mafa_in_of(args: &[&str]) -> Result<MafaInput> {
+        let matched = get_cmd()
+            .try_get_matches_from(args)
+            .map_err(|err_match| MafaError::ClapMatchError(err_match.render()))?;
+        MafaInput::from_ca_matched(&matched)
+    }
+
+    #[test]
+    fn term_width_1() {
+        // not a terminal, e.g., redirected to a file
+        if unsafe { libc::isatty(libc::STDOUT_FILENO) } == 0 {
+            assert_eq!(term_width(), None);
+        }
+    }
+
+    #[test]
+    fn wrap_width_1() {
+        let mafa_in = mafa_in_of(&["mafa", "--wrap-width", "auto"]).expect("buggy");
+        assert_eq!(mafa_in.wrap_width, 0);
+        assert_eq!(
+            mafa_in.wrap_width_now(),
+            term_width().map_or(DEF_WRAP_WIDTH, |w| w.max(MIN_WRAP_WIDTH))
+        );
+
+        let mafa_in = mafa_in_of(&["mafa", "--wrap-width", "100"]).expect("buggy");
+        assert_eq!(mafa_in.wrap_width, 100);
+        assert_eq!(mafa_in.wrap_width_now(), 100);
+
+        // too narrow
+        let mafa_in = mafa_in_of(&["mafa", "--wrap-width=10"]).expect("buggy");
+        assert_eq!(mafa_in.wrap_width_now(), DEF_WRAP_WIDTH);
+
+        assert!(matches!(
+            mafa_in_of(&["mafa", "--wrap-width", "wide"]),
+            Err(MafaError::InvalidWrapWidth)
+        ));
+    }
+
+    #[test]
+    fn output_1() {
+        let mafa_in = mafa_in_of(&["mafa", "--output", "json"]).expect("buggy");
+        assert_eq!(mafa_in.output, OutputFormat::Json);
+
+        assert!(matches!(
+            mafa_in_of(&["mafa", "--output", "jsno"]),
+            Err(MafaError::ClapMatchError(_))
+        ));
+    }
+}
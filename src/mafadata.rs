@@ -10,30 +10,81 @@
 use std::fs::create_dir_all;
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::io::Read;
-use std::io::Seek;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::error::MafaError;
 use crate::error::Result;
 
-#[cfg(target_family = "unix")]
-fn get_home_dir() -> String {
-    use std::env;
-    for (k, v) in env::vars() {
-        if k == "HOME" {
-            return v;
+///
+/// non-empty value of environment variable `k`
+fn env_nonempty(k: &str) -> Option<PathBuf> {
+    std::env::var_os(k)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+#[derive(Debug, PartialEq)]
+struct DataDirs {
+    ///
+    /// caches, locks, results, and the daemon socket
+    data: PathBuf,
+    ///
+    /// config.toml, never created by mafa
+    config: PathBuf,
+}
+
+///
+/// `MAFA_HOME` holds everything if set. Otherwise an existing `~/.mafa`
+/// tree keeps holding everything, so that data of earlier versions are not
+/// left behind. Otherwise caches go to `$XDG_CACHE_HOME/mafa`,
+/// configuration to `$XDG_CONFIG_HOME/mafa`, either falls back to
+/// `~/.mafa` if the respective variable is unset.
+fn locate_dirs(
+    getenv: impl Fn(&str) -> Option<PathBuf>,
+    is_dir: impl Fn(&Path) -> bool,
+    sver: &str,
+) -> Result<DataDirs> {
+    if let Some(mafa_home) = getenv("MAFA_HOME") {
+        return Ok(DataDirs {
+            data: mafa_home.join(sver),
+            config: mafa_home.join(sver),
+        });
+    }
+
+    let home_data_root = getenv("HOME").map(|v| v.join(".mafa"));
+    if let Some(home_data_root) = &home_data_root {
+        if is_dir(&home_data_root.join(sver)) {
+            return Ok(DataDirs {
+                data: home_data_root.join(sver),
+                config: home_data_root.join(sver),
+            });
         }
     }
-    return "".to_owned();
+
+    let data_root = getenv("XDG_CACHE_HOME")
+        .map(|v| v.join("mafa"))
+        .or(home_data_root.clone())
+        .ok_or(MafaError::InvalidDataDir)?;
+    let config_root = getenv("XDG_CONFIG_HOME")
+        .map(|v| v.join("mafa"))
+        .or(home_data_root)
+        .ok_or(MafaError::InvalidDataDir)?;
+
+    Ok(DataDirs {
+        data: data_root.join(sver),
+        config: config_root.join(sver),
+    })
 }
 
 #[cfg(target_family = "unix")]
 mod lock {
     use std::fs::File;
     use std::os::fd::AsRawFd;
+    use std::time::Duration;
+    use std::time::Instant;
 
     fn flock(file: &File, flag: libc::c_int) -> std::io::Result<()> {
         let ret = unsafe { libc::flock(file.as_raw_fd(), flag) };
@@ -44,223 +95,570 @@ mod lock {
         }
     }
 
-    pub fn acquire(lock: &File) -> Result<(), u8> {
-        flock(&lock, libc::LOCK_EX).unwrap();
+    ///
+    /// released once dropped
+    #[derive(Debug)]
+    pub struct Guard(File);
 
-        Ok(())
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            let _ = flock(&self.0, libc::LOCK_UN);
+        }
     }
 
-    pub fn release(lock: &File) -> Result<(), u8> {
-        flock(&lock, libc::LOCK_UN).unwrap();
+    ///
+    /// Poll the lock until `timeout`, `ErrorKind::TimedOut` if it is still
+    /// held by others then.
+    pub fn acquire(lock: File, timeout: Duration) -> std::io::Result<Guard> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match flock(&lock, libc::LOCK_EX | libc::LOCK_NB) {
+                Ok(_) => return Ok(Guard(lock)),
+                Err(err_io) if err_io.kind() == std::io::ErrorKind::WouldBlock => {
+                    if Instant::now() >= deadline {
+                        return Err(std::io::ErrorKind::TimedOut.into());
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(err_io) if err_io.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err_io) => return Err(err_io),
+            }
+        }
+    }
+}
 
-        Ok(())
+///
+/// long enough for any cache operation of a healthy mafa
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+///
+/// Write `data` into a temporary file next to `pbuf`, then rename it to
+/// `pbuf`, so that readers never see a partially written file, even if
+/// mafa is killed halfway.
+fn write_atomic(pbuf: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = pbuf.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".tmp{}", std::process::id()));
+    let tmp_pbuf = pbuf.with_file_name(tmp_name);
+
+    let written = File::create(&tmp_pbuf).and_then(|mut f| {
+        f.write_all(data)?;
+        f.sync_all()
+    });
+    if let Err(err_io) = written.and_then(|_| std::fs::rename(&tmp_pbuf, pbuf)) {
+        let _ = std::fs::remove_file(&tmp_pbuf);
+        return Err(err_io);
     }
+
+    Ok(())
 }
 
-use lock::acquire as lock_acquire;
-use lock::release as lock_release;
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct StoredResult {
+    ts: u64,
+    key: String,
+    raw: String,
+}
+
+pub(crate) fn secs_since_epoch() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+///
+/// stable across versions and platforms, unlike std's hashers
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
 
 #[derive(Debug)]
 pub struct MafaData {
-    home_pbuf: PathBuf,
-    data_root: &'static str,
-    sver: &'static str, // structure version
+    data_pbuf: PathBuf,
+    config_pbuf: PathBuf,
     lock_dir: &'static str,
     cache_dir: &'static str,
+    result_dir: &'static str,
 }
 
 impl MafaData {
-    pub fn init() -> MafaData {
-        let home_dir = get_home_dir();
-        let data_root = ".mafa";
-        let sver = "v1"; // currently v1 structure in use
+    pub fn init() -> Result<MafaData> {
+        Self::init_from(env_nonempty)
+    }
+
+    ///
+    /// data directory located with `getenv` instead of the environment, so
+    /// that tests are able to point it elsewhere
+    pub(crate) fn init_from(getenv: impl Fn(&str) -> Option<PathBuf>) -> Result<MafaData> {
+        // currently v1 structure in use, cache files carry their own
+        // version, see `cache`
+        let sver = "v1";
         let cache_dir = "cache";
         let lock_dir = "lock";
+        let result_dir = "result";
 
-        // manually delete data_root to reset all setting
+        // manually delete data directory to reset all setting
 
-        let home_pbuf = PathBuf::new().join(&home_dir);
+        let dirs = locate_dirs(getenv, |pbuf| pbuf.is_dir(), sver)?;
 
-        create_dir_all(home_pbuf.join(data_root).join(sver)).unwrap();
-        create_dir_all(home_pbuf.join(data_root).join(sver).join(cache_dir)).unwrap();
-        create_dir_all(home_pbuf.join(data_root).join(sver).join(lock_dir)).unwrap();
+        for sub_dir in [cache_dir, lock_dir, result_dir] {
+            let pbuf = dirs.data.join(sub_dir);
+            if let Err(err_io) = create_dir_all(&pbuf) {
+                dbgg!((&pbuf, &err_io));
+                return Err(MafaError::Io(pbuf, err_io));
+            }
+        }
 
-        MafaData {
-            home_pbuf: home_pbuf,
-            data_root: data_root,
-            sver,
+        Ok(MafaData {
+            data_pbuf: dirs.data,
+            config_pbuf: dirs.config,
             lock_dir,
             cache_dir: cache_dir,
+            result_dir,
+        })
+    }
+
+    ///
+    /// the config file may not exist, it is up to users to create one
+    pub fn pathto_config(&self) -> PathBuf {
+        self.config_pbuf.join("config.toml")
+    }
+
+    pub fn pathto_daemon_sock(&self) -> PathBuf {
+        self.data_pbuf.join("daemon.sock")
+    }
+
+    ///
+    /// the data directory and its subdirectories
+    pub fn all_dirs(&self) -> Vec<PathBuf> {
+        let mut all = vec![self.data_pbuf.clone()];
+        for sub_dir in [self.cache_dir, self.lock_dir, self.result_dir] {
+            all.push(self.data_pbuf.join(sub_dir));
         }
+
+        all
     }
 
     pub fn pathto_exist_cache(&self, cache_id: &str) -> Result<PathBuf> {
-        let pbuf = self
-            .home_pbuf
-            .join(self.data_root)
-            .join(self.sver)
-            .join(self.cache_dir)
-            .join(cache_id);
-
-        if let Ok(flag) = Path::new(&pbuf).try_exists() {
-            if !flag {
-                return Err(MafaError::MafaDataCacheNotFound);
-            }
-        } else {
-            return Err(MafaError::Buggy);
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        match Path::new(&pbuf).try_exists() {
+            Ok(true) => {}
+            Ok(false) => return Err(MafaError::MafaDataCacheNotFound(cache_id.to_string())),
+            Err(err_io) => return Err(MafaError::Io(pbuf, err_io)),
         }
 
         Ok(pbuf)
     }
 
     ///
-    /// current implementation does not need extra surrounded
-    /// lock, since locks here all are assumed empty, it is
-    /// completely safe for a large number of threads open
-    /// a empty lock simutaneously.
+    /// Lock on lock_name, held until the returned guard is dropped.
     ///
-    /// and another invariant makes it much more safe: most of
-    /// time, users are requesting a existing lock, not a new one.
-    fn cache_lock(&self, lock_name: &str) -> Result<File> {
-        let pbuf = self
-            .home_pbuf
-            .join(self.data_root)
-            .join(self.sver)
-            .join(self.lock_dir)
-            .join(lock_name);
-
-        if let Ok(flag) = Path::new(&pbuf).try_exists() {
-            if !flag {
-                let lock_f = OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .open(&pbuf)
-                    .or_else(|_| Err(MafaError::BugFound(3456)))?;
-
-                return Ok(lock_f);
-            }
-        } else {
-            return Err(MafaError::BugFound(1234));
-        }
+    /// Lock files are empty and never removed, it is safe for many
+    /// processes to create the same one simultaneously.
+    fn cache_lock(&self, lock_name: &str) -> Result<lock::Guard> {
+        let pbuf = self.data_pbuf.join(self.lock_dir).join(lock_name);
 
-        // if exist
         let lock_f = OpenOptions::new()
-            .read(true)
+            .create(true)
+            .truncate(false)
             .write(true)
             .open(&pbuf)
-            .or_else(|_| Err(MafaError::BugFound(3456)))?;
+            .map_err(|err_io| {
+                dbgg!((&pbuf, &err_io));
+                MafaError::Io(pbuf.clone(), err_io)
+            })?;
+
+        lock::acquire(lock_f, LOCK_TIMEOUT).map_err(|err_io| {
+            if err_io.kind() == std::io::ErrorKind::TimedOut {
+                MafaError::CacheLockTimeout
+            } else {
+                dbgg!((&pbuf, &err_io));
+                MafaError::Io(pbuf, err_io)
+            }
+        })
+    }
+
+    ///
+    /// Lock files held by other processes, each is given `timeout` to be
+    /// released.
+    pub fn held_locks(&self, timeout: Duration) -> Result<Vec<PathBuf>> {
+        let dir = self.data_pbuf.join(self.lock_dir);
+        let all_ent =
+            std::fs::read_dir(&dir).map_err(|err_io| MafaError::Io(dir.clone(), err_io))?;
+
+        let mut held = vec![];
+        for ent in all_ent {
+            let pbuf = ent
+                .map_err(|err_io| MafaError::Io(dir.clone(), err_io))?
+                .path();
+            let lock_f = OpenOptions::new()
+                .write(true)
+                .open(&pbuf)
+                .map_err(|err_io| MafaError::Io(pbuf.clone(), err_io))?;
 
-        Ok(lock_f)
+            match lock::acquire(lock_f, timeout) {
+                Ok(_lock_g) => {}
+                Err(err_io) if err_io.kind() == std::io::ErrorKind::TimedOut => held.push(pbuf),
+                Err(err_io) => return Err(MafaError::Io(pbuf, err_io)),
+            }
+        }
+
+        Ok(held)
     }
 
-    pub fn cache_append(
-        &self,
-        cache_id: &str,
-        data_if_exis: &str,
-        data_if_nexis: &str,
-    ) -> Result<()> {
-        let mut is_cache_exist = false;
-        let pbuf = self
-            .home_pbuf
-            .join(self.data_root)
-            .join(self.sver)
-            .join(self.cache_dir)
-            .join(cache_id);
-
-        if let Ok(flag) = Path::new(&pbuf).try_exists() {
-            if flag {
-                is_cache_exist = true;
+    fn cache_write(&self, cache_id: &str, data: &str) -> Result<()> {
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        write_atomic(&pbuf, data.as_bytes()).map_err(|err_io| {
+            dbgg!((&pbuf, &err_io));
+            MafaError::Io(pbuf, err_io)
+        })
+    }
+
+    ///
+    /// write data into cache_id, only if it does not exist
+    pub fn try_init_cache(&self, cache_id: &str, data: &str) -> Result<()> {
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        let _lock_g = self.cache_lock(cache_id)?;
+
+        match Path::new(&pbuf).try_exists() {
+            Ok(true) => Ok(()),
+            Ok(false) => self.cache_write(cache_id, data),
+            Err(err_io) => {
+                dbgg!((&pbuf, &err_io));
+                Err(MafaError::Io(pbuf, err_io))
             }
-        } else {
-            return Err(MafaError::Buggy);
         }
+    }
 
-        if is_cache_exist {
-            lock_acquire(&self.cache_lock(cache_id)?).expect("buggy");
-            let mut f = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(&pbuf)
-                .or_else(|_| Err(MafaError::BugFound(3456)))?;
-            let mut buf_olddata = [0u8; 4096];
-            let mut total_read = 0;
-            let mut nread = 1;
-            while nread != 0 {
-                nread = f.read(&mut buf_olddata).unwrap();
-                total_read += nread;
+    ///
+    /// whether cache exists or not, write data into cache_id, create
+    /// before write if not exist
+    pub fn init_cache(&self, cache_id: &str, data: &str) -> Result<()> {
+        let _lock_g = self.cache_lock(cache_id)?;
+
+        self.cache_write(cache_id, data)
+    }
+
+    ///
+    /// content of cache_id, None if not exist
+    pub fn cache_read(&self, cache_id: &str) -> Result<Option<String>> {
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        let _lock_g = self.cache_lock(cache_id)?;
+
+        let rawdata = match std::fs::read_to_string(&pbuf) {
+            Ok(v) => Some(v),
+            Err(err_io) if err_io.kind() == std::io::ErrorKind::NotFound => None,
+            // not UTF-8
+            Err(err_io) if err_io.kind() == std::io::ErrorKind::InvalidData => {
+                dbgg!((&pbuf, err_io));
+                return Err(MafaError::CacheCorrupted);
             }
-            f.rewind().unwrap();
-            f.write_all(data_if_exis.as_bytes()).unwrap();
-            f.write_all(&buf_olddata[0..total_read]).unwrap();
-            lock_release(&self.cache_lock(cache_id)?).expect("buggy");
-        } else {
-            self.init_cache(cache_id, data_if_nexis)?;
+            Err(err_io) => {
+                dbgg!((&pbuf, &err_io));
+                return Err(MafaError::Io(pbuf, err_io));
+            }
+        };
+
+        Ok(rawdata)
+    }
+
+    ///
+    /// Read cache_id and write it back while holding its lock. `modify` is
+    /// given the content, None if not exist, and returns the new content,
+    /// None to leave the cache untouched.
+    pub fn cache_modify<F>(&self, cache_id: &str, modify: F) -> Result<()>
+    where
+        F: FnOnce(Option<String>) -> Result<Option<String>>,
+    {
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        let _lock_g = self.cache_lock(cache_id)?;
+
+        let rawdata = match std::fs::read_to_string(&pbuf) {
+            Ok(v) => Some(v),
+            Err(err_io) if err_io.kind() == std::io::ErrorKind::NotFound => None,
+            // not UTF-8
+            Err(err_io) if err_io.kind() == std::io::ErrorKind::InvalidData => {
+                dbgg!((&pbuf, err_io));
+                return Err(MafaError::CacheCorrupted);
+            }
+            Err(err_io) => {
+                dbgg!((&pbuf, &err_io));
+                return Err(MafaError::Io(pbuf, err_io));
+            }
+        };
+
+        if let Some(data) = modify(rawdata)? {
+            self.cache_write(cache_id, &data)?;
         }
 
         Ok(())
     }
 
-    pub fn try_init_cache(&self, cache_id: &str, data: &str) -> Result<()> {
-        let pbuf = self
-            .home_pbuf
-            .join(self.data_root)
-            .join(self.sver)
-            .join(self.cache_dir)
-            .join(cache_id);
-
-        lock_acquire(&self.cache_lock(cache_id)?).expect("buggy");
-
-        if let Ok(flag) = Path::new(&pbuf).try_exists() {
-            if flag {
-                return Ok(());
+    ///
+    /// remove cache_id, if exist
+    pub fn cache_remove(&self, cache_id: &str) -> Result<()> {
+        let pbuf = self.data_pbuf.join(self.cache_dir).join(cache_id);
+
+        let _lock_g = self.cache_lock(cache_id)?;
+
+        if let Err(err_io) = std::fs::remove_file(&pbuf) {
+            if err_io.kind() != std::io::ErrorKind::NotFound {
+                dbgg!((&pbuf, &err_io));
+                return Err(MafaError::Io(pbuf, err_io));
             }
-        } else {
-            return Err(MafaError::Buggy);
         }
 
-        let mut f = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(&pbuf)
-            .or_else(|_| Err(MafaError::BugFound(3456)))?;
+        Ok(())
+    }
 
-        if let Err(_err_io) = f.write_all(&data.as_bytes()) {
-            dbgg!((&pbuf, _err_io));
-            return Err(MafaError::Buggy);
+    pub fn pathto_cache(&self, cache_id: &str) -> PathBuf {
+        self.data_pbuf.join(self.cache_dir).join(cache_id)
+    }
+
+    fn pathto_result(&self, comp: &str, key: &str) -> PathBuf {
+        self.data_pbuf
+            .join(self.result_dir)
+            .join(comp)
+            .join(format!("{:016x}", fnv1a64(key.as_bytes())))
+    }
+
+    ///
+    /// result of `comp` stored for `key`, unless it is older than
+    /// `ttl_secs`
+    pub fn result_get(&self, comp: &str, key: &str, ttl_secs: u64) -> Option<String> {
+        let rawdata = std::fs::read_to_string(self.pathto_result(comp, key)).ok()?;
+        let stored = serde_json::from_str::<StoredResult>(&rawdata).ok()?;
+
+        // hash collided
+        if stored.key != key {
+            return None;
         }
 
-        lock_release(&self.cache_lock(cache_id)?).expect("buggy");
+        let age = secs_since_epoch().saturating_sub(stored.ts);
+        if age >= ttl_secs {
+            return None;
+        }
+
+        Some(stored.raw)
+    }
+
+    ///
+    /// store the result of `comp` for `key`, evicting ones of `comp` older
+    /// than `ttl_secs`, unless it is 0
+    pub fn result_put(&self, comp: &str, key: &str, raw: &str, ttl_secs: u64) -> Result<()> {
+        let pbuf = self.pathto_result(comp, key);
+        if let Some(parent) = pbuf.parent() {
+            create_dir_all(parent).map_err(|err_io| MafaError::Io(parent.to_path_buf(), err_io))?;
+        }
+
+        let stored = StoredResult {
+            ts: secs_since_epoch(),
+            key: key.to_string(),
+            raw: raw.to_string(),
+        };
+        let wbuf = serde_json::to_string(&stored).map_err(MafaError::Json)?;
+
+        write_atomic(&pbuf, wbuf.as_bytes()).map_err(|err_io| MafaError::Io(pbuf, err_io))?;
+
+        if ttl_secs > 0 {
+            self.result_evict(comp, ttl_secs);
+        }
 
         Ok(())
     }
 
     ///
-    /// whether cache exists or not, write data into cache_id, create
-    /// before write if not exist
-    pub fn init_cache(&self, cache_id: &str, data: &str) -> Result<()> {
-        let pbuf = self
-            .home_pbuf
-            .join(self.data_root)
-            .join(self.sver)
-            .join(self.cache_dir)
-            .join(cache_id);
+    /// remove results of `comp` older than `ttl_secs`, the ones failing to
+    /// be removed are left for next time
+    fn result_evict(&self, comp: &str, ttl_secs: u64) {
+        let all_entry = match std::fs::read_dir(self.data_pbuf.join(self.result_dir).join(comp)) {
+            Ok(v) => v,
+            Err(_err_io) => {
+                dbgg!(_err_io);
+                return;
+            }
+        };
+
+        let ttl = Duration::from_secs(ttl_secs);
+        for entry in all_entry.flatten() {
+            let is_expired = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .map(|mtime| mtime.elapsed().map_or(false, |age| age >= ttl))
+                .unwrap_or(false);
+            if is_expired {
+                if let Err(_err_io) = std::fs::remove_file(entry.path()) {
+                    dbgg!((entry.path(), _err_io));
+                }
+            }
+        }
+    }
+}
 
-        lock_acquire(&self.cache_lock(cache_id)?).expect("buggy");
+#[cfg(test)]
+mod utst {
+    use super::*;
 
-        let mut f = OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(&pbuf)
-            .or_else(|_| Err(MafaError::BugFound(3456)))?;
+    fn envs_of<'a>(all: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<PathBuf> + 'a {
+        move |k| {
+            all.iter()
+                .find(|(ek, _)| *ek == k)
+                .map(|(_, v)| PathBuf::from(v))
+        }
+    }
+
+    #[test]
+    fn locate_dirs_1() {
+        let dirs = locate_dirs(envs_of(&[("HOME", "/home/u")]), |_| false, "v1").expect("buggy");
+        assert_eq!(dirs.data, PathBuf::from("/home/u/.mafa/v1"));
+        assert_eq!(dirs.config, PathBuf::from("/home/u/.mafa/v1"));
+
+        let dirs = locate_dirs(
+            envs_of(&[
+                ("HOME", "/home/u"),
+                ("XDG_CACHE_HOME", "/xdg/cache"),
+                ("XDG_CONFIG_HOME", "/xdg/config"),
+            ]),
+            |_| false,
+            "v1",
+        )
+        .expect("buggy");
+        assert_eq!(dirs.data, PathBuf::from("/xdg/cache/mafa/v1"));
+        assert_eq!(dirs.config, PathBuf::from("/xdg/config/mafa/v1"));
+
+        let dirs = locate_dirs(
+            envs_of(&[("HOME", "/home/u"), ("XDG_CONFIG_HOME", "/xdg/config")]),
+            |_| false,
+            "v1",
+        )
+        .expect("buggy");
+        assert_eq!(dirs.data, PathBuf::from("/home/u/.mafa/v1"));
+        assert_eq!(dirs.config, PathBuf::from("/xdg/config/mafa/v1"));
+
+        let dirs = locate_dirs(
+            envs_of(&[
+                ("HOME", "/home/u"),
+                ("XDG_CACHE_HOME", "/xdg/cache"),
+                ("MAFA_HOME", "/tmp/mafa"),
+            ]),
+            |_| false,
+            "v1",
+        )
+        .expect("buggy");
+        assert_eq!(dirs.data, PathBuf::from("/tmp/mafa/v1"));
+        assert_eq!(dirs.config, PathBuf::from("/tmp/mafa/v1"));
+
+        // existing ~/.mafa wins over XDG ones
+        let dirs = locate_dirs(
+            envs_of(&[
+                ("HOME", "/home/u"),
+                ("XDG_CACHE_HOME", "/xdg/cache"),
+                ("XDG_CONFIG_HOME", "/xdg/config"),
+            ]),
+            |pbuf| pbuf == Path::new("/home/u/.mafa/v1"),
+            "v1",
+        )
+        .expect("buggy");
+        assert_eq!(dirs.data, PathBuf::from("/home/u/.mafa/v1"));
+        assert_eq!(dirs.config, PathBuf::from("/home/u/.mafa/v1"));
+    }
 
-        if let Err(_err_io) = f.write_all(&data.as_bytes()) {
-            dbgg!((&pbuf, _err_io));
-            return Err(MafaError::Buggy);
+    #[test]
+    fn locate_dirs_2() {
+        match locate_dirs(envs_of(&[]), |_| false, "v1") {
+            Err(MafaError::InvalidDataDir) => {}
+            _ => panic!("buggy"),
         }
+        match locate_dirs(
+            envs_of(&[("XDG_CACHE_HOME", "/xdg/cache")]),
+            |_| false,
+            "v1",
+        ) {
+            Err(MafaError::InvalidDataDir) => {}
+            _ => panic!("buggy"),
+        }
+    }
 
-        lock_release(&self.cache_lock(cache_id)?).expect("buggy");
+    #[test]
+    fn write_atomic_1() {
+        let dir = std::env::temp_dir().join(format!("mafa-utst-{}", std::process::id()));
+        create_dir_all(&dir).expect("buggy");
+        let pbuf = dir.join("cache1");
+
+        let big = "x".repeat(10000);
+        write_atomic(&pbuf, big.as_bytes()).expect("buggy");
+        write_atomic(&pbuf, b"[11,1,1,3,3]\n-").expect("buggy");
+        assert_eq!(
+            std::fs::read_to_string(&pbuf).expect("buggy"),
+            "[11,1,1,3,3]\n-"
+        );
+        write_atomic(&pbuf, big.as_bytes()).expect("buggy");
+        assert_eq!(std::fs::read_to_string(&pbuf).expect("buggy"), big);
+        assert_eq!(std::fs::read_dir(&dir).expect("buggy").count(), 1);
+
+        std::fs::remove_dir_all(&dir).expect("buggy");
+    }
 
-        Ok(())
+    #[test]
+    fn lock_acquire_1() {
+        let pbuf = std::env::temp_dir().join(format!("mafa-utst-lock-{}", std::process::id()));
+        let open = || {
+            OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&pbuf)
+                .expect("buggy")
+        };
+
+        let lock_g = lock::acquire(open(), Duration::from_millis(100)).expect("buggy");
+        match lock::acquire(open(), Duration::from_millis(100)) {
+            Err(err_io) => assert_eq!(err_io.kind(), std::io::ErrorKind::TimedOut),
+            Ok(_) => panic!("buggy"),
+        }
+        drop(lock_g);
+        assert!(lock::acquire(open(), Duration::from_millis(100)).is_ok());
+
+        std::fs::remove_file(&pbuf).expect("buggy");
+    }
+
+    #[test]
+    fn result_put_1() {
+        let dir = std::env::temp_dir().join(format!("mafa-utst-result-{}", std::process::id()));
+        let mafad =
+            MafaData::init_from(|k| (k == "MAFA_HOME").then(|| dir.clone())).expect("buggy");
+
+        mafad
+            .result_put("camd", "old", "raw1", 3600)
+            .expect("buggy");
+        let two_hours_ago = std::time::SystemTime::now() - Duration::from_secs(7200);
+        File::options()
+            .write(true)
+            .open(mafad.pathto_result("camd", "old"))
+            .and_then(|f| f.set_modified(two_hours_ago))
+            .expect("buggy");
+
+        // kept with no TTL
+        mafad.result_put("camd", "new", "raw2", 0).expect("buggy");
+        assert!(mafad.pathto_result("camd", "old").exists());
+
+        mafad
+            .result_put("camd", "new", "raw2", 3600)
+            .expect("buggy");
+        assert!(!mafad.pathto_result("camd", "old").exists());
+        assert_eq!(
+            mafad.result_get("camd", "new", 3600),
+            Some("raw2".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).expect("buggy");
     }
 }
@@ -7,18 +7,30 @@
 // with the license.
 //
 
-use wda::GeckoDriver;
 use wda::WdaError;
-use wda::WebDrvAstn;
 
+use mafa::backend::BrowserBackend;
+use mafa::cassette::Recorder;
+use mafa::cassette::Replayer;
 use mafa::MafaClient;
 
+use std::io::Write;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 #[macro_use]
 mod private_macros;
 
+use mafa::config::MafaConfig;
+use mafa::daemon;
+use mafa::daemon::DaemonRequest;
+use mafa::daemon::DaemonResponse;
+#[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+use mafa::daemon::HeldCaches;
+use mafa::theme::Theme;
+
+#[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+use mafa::httpd::{HttpApi, HttpdInput};
 use mafa::mafadata::MafaData;
 
 #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
@@ -29,9 +41,12 @@ use mafa::{
 
 use mafa::ev_ntf::Category;
 use mafa::ev_ntf::EventNotifier;
+use mafa::ev_ntf::JsonLinesSink;
+use mafa::ev_ntf::LogFileSink;
 use mafa::ev_ntf::MafaEvent;
 
 use mafa::MafaInput;
+use mafa::OutputFormat;
 
 #[cfg(feature = "imode")]
 use rustyline::{error::ReadlineError, DefaultEditor};
@@ -48,162 +63,289 @@ use mafa::camd::CamdInput;
 fn main() {
     let mut exit_code = 0;
 
-    let mafad = MafaData::init();
-    let cmd_mafa = mafa::get_cmd();
     let ntf = EventNotifier::new();
     let ntf = Arc::new(Mutex::new(ntf));
+
+    let mafad = MafaData::init();
+
+    // config must be installed before any command is built
+    let cfg_loaded = match &mafad {
+        Ok(mafad) => MafaConfig::load(mafad)
+            .and_then(|cfg| Theme::from_config(&cfg).map(|theme| (cfg, theme)))
+            .map(|(cfg, theme)| {
+                mafa::config::install(cfg);
+                mafa::theme::install(theme);
+            }),
+        Err(_) => Ok(()),
+    };
+
+    let cmd_mafa = mafa::get_cmd();
     let m = cmd_mafa.try_get_matches();
 
+    // doctor reports the failures above as failed checks, while others
+    // cannot go without the data directory and the config
+    if let Ok(matched) = &m {
+        if let Some(("doctor", _)) = matched.subcommand() {
+            let code = run_doctor(
+                mafad.as_ref(),
+                cfg_loaded.as_ref().map(|_| ()),
+                matched,
+                Arc::clone(&ntf),
+            );
+
+            drop(ntf);
+
+            std::process::exit(code as i32);
+        }
+    }
+
+    let mafad = match mafad {
+        Ok(v) => v,
+        Err(err_data) => {
+            let code = err_data.exit_code();
+            ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+                cate: Category::Mafa,
+                err: err_data,
+            });
+
+            drop(ntf);
+
+            std::process::exit(code as i32);
+        }
+    };
+
+    if let Err(err_cfg) = cfg_loaded {
+        let code = err_cfg.exit_code();
+        ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+            cate: Category::Mafa,
+            err: err_cfg,
+        });
+
+        drop(mafad);
+        drop(ntf);
+
+        std::process::exit(code as i32);
+    }
+
     match m {
         Ok(matched) => match MafaInput::from_ca_matched(&matched) {
-            Ok(mafa_in) => {
+            Ok(mut mafa_in) => {
                 let mut ignore_subcmd = false;
 
-                if mafa_in.silent {
-                    ntf.lock().expect("bug").set_silent();
+                if !mafa::theme::color_wanted() {
+                    mafa_in.nocolor = true;
                 }
 
-                if mafa_in.nocolor {
-                    ntf.lock().expect("bug").set_nocolor();
-                }
+                if let Err(err_ntf) = apply_ntf_modes(&ntf, &mafa_in) {
+                    let code = err_ntf.exit_code();
+                    ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+                        cate: Category::Mafa,
+                        err: err_ntf,
+                    });
 
-                dbgg!(&mafa_in);
+                    drop(mafad);
+                    drop(ntf);
 
-                // init wda
-                ntf.lock().expect("bug").notify(MafaEvent::Initialize {
-                    cate: Category::Mafa,
-                    is_fin: false,
-                });
-                let wda_inst = mafa::init_wda(&mafa_in);
+                    std::process::exit(code as i32);
+                }
 
-                if let Err(e) = wda_inst {
-                    match e {
-                        MafaError::InvalidUseProfile
-                        | MafaError::FirefoxNotFound
-                        | MafaError::UnexpectedWda(_) => {
-                            ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
-                                cate: Category::Mafa,
-                                err: e,
-                            });
+                dbgg!(&mafa_in);
 
-                            exit_code = 7;
+                #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+                let is_cache_local = match matched.subcommand() {
+                    Some(("cache", sub_m)) => !mafa::cache::needs_browser(sub_m),
+                    _ => false,
+                };
+                #[cfg(not(any(feature = "gtrans", feature = "twtl", feature = "camd")))]
+                let is_cache_local = false;
+
+                #[cfg(any(feature = "gtrans", feature = "camd"))]
+                let is_stored = mafa::is_result_stored(&mafad, &mafa_in, &matched);
+                #[cfg(not(any(feature = "gtrans", feature = "camd")))]
+                let is_stored = false;
+
+                // config, caches, forwarding, replay, offline and stored
+                // results need no browser at all
+                if let Some(("config", _)) = matched.subcommand() {
+                    ntf.lock()
+                        .expect("buggy")
+                        .notify(MafaEvent::ExactUserRequest {
+                            cate: Category::Mafa,
+                            kind: EurKind::ConfigShow,
+                            output: mafa::config::show(&matched, mafa_in.output),
+                        });
+                } else if is_cache_local {
+                    exit_code = run_cache(&mafad, &mafa_in, &matched, Arc::clone(&ntf));
+                } else if let Some(resp) = try_forward(&mafad, &mafa_in, &matched) {
+                    print!("{}", resp.stdout);
+                    let _ = std::io::stdout().flush();
+                    eprint!("{}", resp.stderr);
+
+                    exit_code = resp.code;
+                } else if !mafa_in.replay.is_empty() {
+                    match Replayer::new(&mafa_in.replay) {
+                        Ok(replayer) => {
+                            exit_code =
+                                run_subcmd(&mafad, &mafa_in, &matched, &replayer, Arc::clone(&ntf));
                         }
-                        _ => {
-                            ntf.lock().expect("bug").notify(MafaEvent::HandlerMissed {
+                        Err(err_rep) => {
+                            let code = err_rep.exit_code();
+                            ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
                                 cate: Category::Mafa,
-                                err: e,
+                                err: err_rep,
                             });
 
-                            exit_code = 7;
+                            exit_code = code;
                         }
                     }
+                } else if mafa_in.offline || is_stored {
+                    exit_code = run_subcmd(
+                        &mafad,
+                        &mafa_in,
+                        &matched,
+                        &mafa::backend::Offline,
+                        Arc::clone(&ntf),
+                    );
                 } else {
-                    // finish things left
-                    let wda_inst = wda_inst.expect("bug");
+                    // init wda
                     ntf.lock().expect("bug").notify(MafaEvent::Initialize {
                         cate: Category::Mafa,
-                        is_fin: true,
+                        is_fin: false,
                     });
-
-                    // needs alive wda
-                    if mafa_in.list_profile {
-                        let list_got = format!(
-                            "------ Available Profiles ------
-{}
---------------------------------",
-                            wda_inst
-                                .existing_profiles()
-                                .expect("bug")
-                                .iter()
-                                .map(|v| format!("<{v}>"))
-                                .collect::<Vec<String>>()
-                                .join("\n")
-                        );
-
-                        ntf.lock()
-                            .expect("buggy")
-                            .notify(MafaEvent::ExactUserRequest {
-                                cate: Category::Mafa,
-                                kind: EurKind::ListProfile,
-                                output: list_got,
-                            });
-
-                        ignore_subcmd = true;
-                        exit_code = 0;
+                    #[cfg(feature = "imode")]
+                    if let Some(("i", _)) = matched.subcommand() {
+                        mafa::cancel::shield_children();
                     }
+                    let wda_inst = mafa::init_wda(&mafa_in);
+
+                    if let Err(e) = wda_inst {
+                        match e {
+                            MafaError::InvalidUseProfile
+                            | MafaError::FirefoxNotFound
+                            | MafaError::UnexpectedWda(_) => {
+                                let code = e.exit_code();
+                                ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+                                    cate: Category::Mafa,
+                                    err: e,
+                                });
 
-                    // subcommand
-                    if !ignore_subcmd {
-                        match matched.subcommand() {
-                            #[cfg(feature = "gtrans")]
-                            Some(("gtrans", sub_m)) => {
-                                let gtrans_in = GtransInput::from_ca_matched(sub_m);
-                                exit_code = workflow_gtrans(
-                                    &mafad,
-                                    &mafa_in,
-                                    gtrans_in,
-                                    &wda_inst,
-                                    Arc::clone(&ntf),
-                                );
+                                exit_code = code;
                             }
+                            _ => {
+                                let code = e.exit_code();
+                                ntf.lock().expect("bug").notify(MafaEvent::HandlerMissed {
+                                    cate: Category::Mafa,
+                                    err: e,
+                                });
 
-                            #[cfg(feature = "twtl")]
-                            Some(("twtl", sub_m)) => {
-                                let twtl_in = TwtlInput::from_ca_matched(sub_m);
-                                exit_code = workflow_twtl(
-                                    &mafad,
-                                    &mafa_in,
-                                    twtl_in,
-                                    &wda_inst,
-                                    Arc::clone(&ntf),
-                                );
+                                exit_code = code;
                             }
+                        }
+                    } else {
+                        // finish things left
+                        let wda_inst = wda_inst.expect("bug");
+                        ntf.lock().expect("bug").notify(MafaEvent::Initialize {
+                            cate: Category::Mafa,
+                            is_fin: true,
+                        });
+
+                        // needs alive wda
+                        if mafa_in.list_profile {
+                            let profiles = wda_inst.existing_profiles().expect("bug");
+                            let list_got = if let OutputFormat::Json = mafa_in.output {
+                                serde_json::to_string(&profiles).expect("bug")
+                            } else {
+                                format!(
+                                    "------ Available Profiles ------
+{}
+--------------------------------",
+                                    profiles
+                                        .iter()
+                                        .map(|v| format!("<{v}>"))
+                                        .collect::<Vec<String>>()
+                                        .join("\n")
+                                )
+                            };
+
+                            ntf.lock()
+                                .expect("buggy")
+                                .notify(MafaEvent::ExactUserRequest {
+                                    cate: Category::Mafa,
+                                    kind: EurKind::ListProfile,
+                                    output: list_got,
+                                });
+
+                            ignore_subcmd = true;
+                            exit_code = 0;
+                        }
 
-                            #[cfg(feature = "camd")]
-                            Some(("camd", sub_m)) => {
-                                let camd_in = CamdInput::from_ca_matched(sub_m);
-                                exit_code = workflow_camd(
+                        // subcommand
+                        if !ignore_subcmd {
+                            if !mafa_in.record.is_empty() {
+                                match Recorder::new(&wda_inst, &mafa_in.record) {
+                                    Ok(recorder) => {
+                                        exit_code = run_subcmd(
+                                            &mafad,
+                                            &mafa_in,
+                                            &matched,
+                                            &recorder,
+                                            Arc::clone(&ntf),
+                                        );
+                                    }
+                                    Err(err_rec) => {
+                                        let code = err_rec.exit_code();
+                                        ntf.lock().expect("bug").notify(
+                                            MafaEvent::FatalMafaError {
+                                                cate: Category::Mafa,
+                                                err: err_rec,
+                                            },
+                                        );
+
+                                        exit_code = code;
+                                    }
+                                }
+                            } else {
+                                exit_code = run_subcmd(
                                     &mafad,
                                     &mafa_in,
-                                    camd_in,
+                                    &matched,
                                     &wda_inst,
                                     Arc::clone(&ntf),
                                 );
                             }
+                        }
+                    }
+                }
 
-                            #[cfg(feature = "imode")]
-                            Some(("i", _)) => {
-                                exit_code =
-                                    enter_i_mode(&mafad, &mafa_in, &wda_inst, Arc::clone(&ntf));
-                            }
+                if !mafa_in.elap_trace.is_empty() {
+                    let mut ntf = ntf.lock().expect("bug");
+                    if let Err(err_trace) = ntf.write_trace(&mafa_in.elap_trace) {
+                        let code = err_trace.exit_code();
+                        ntf.notify(MafaEvent::FatalMafaError {
+                            cate: Category::Mafa,
+                            err: err_trace,
+                        });
 
-                            _ => {
-                                ntf.lock()
-                                    .expect("buggy")
-                                    .notify(MafaEvent::ExactUserRequest {
-                                    cate: Category::Mafa,
-                                    kind: EurKind::NoSubCmd,
-                                    output:
-                                        "no components supplied. Please check supported components by -h or --help"
-                                            .into(),
-                                });
-                            }
-                        }
+                        exit_code = code;
                     }
                 }
             }
             Err(err_in) => {
+                let code = err_in.exit_code();
                 ntf.lock()
                     .expect("buggy") // FIXME: handle gracefully
                     .notify(MafaEvent::FatalMafaError {
                         cate: Category::Mafa,
                         err: err_in,
                     });
-                exit_code = 5;
+                exit_code = code;
             }
         },
         Err(err_match) => {
             err_match.print().unwrap(); // this will print helper
+            if err_match.use_stderr() {
+                exit_code = mafa::error::exit_code::INVALID_INPUT;
+            }
         }
     }
 
@@ -213,13 +355,491 @@ fn main() {
     std::process::exit(exit_code as i32);
 }
 
+fn run_doctor(
+    mafad: core::result::Result<&MafaData, &MafaError>,
+    cfg_loaded: core::result::Result<(), &MafaError>,
+    matched: &clap::ArgMatches,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    let mut mafa_in = match MafaInput::from_ca_matched(matched) {
+        Ok(v) => v,
+        Err(err_in) => {
+            let code = err_in.exit_code();
+            ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+                cate: Category::Mafa,
+                err: err_in,
+            });
+            return code;
+        }
+    };
+
+    if !mafa::theme::color_wanted() {
+        mafa_in.nocolor = true;
+    }
+
+    if let Err(err_ntf) = apply_ntf_modes(&ntf, &mafa_in) {
+        let code = err_ntf.exit_code();
+        ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+            cate: Category::Mafa,
+            err: err_ntf,
+        });
+        return code;
+    }
+
+    // it launches its own browser
+    let report = mafa::doctor::run(mafad, cfg_loaded, &mafa_in);
+    ntf.lock()
+        .expect("buggy")
+        .notify(MafaEvent::ExactUserRequest {
+            cate: Category::Mafa,
+            kind: EurKind::DoctorReport,
+            output: report.to_output(mafa_in.output, mafa_in.nocolor),
+        });
+
+    report.exit_code()
+}
+
+fn apply_ntf_modes(
+    ntf: &Arc<Mutex<EventNotifier>>,
+    mafa_in: &MafaInput,
+) -> mafa::error::Result<()> {
+    if mafa_in.silent {
+        ntf.lock().expect("bug").set_silent();
+    }
+
+    if mafa_in.nocolor {
+        ntf.lock().expect("bug").set_nocolor();
+    } else {
+        ntf.lock().expect("bug").set_color();
+    }
+
+    if let OutputFormat::Json = mafa_in.output {
+        ntf.lock().expect("bug").set_json();
+    }
+
+    if mafa_in.events_json {
+        ntf.lock()
+            .expect("bug")
+            .add_sink(Box::new(JsonLinesSink::stderr()));
+    }
+
+    if !mafa_in.event_log.is_empty() {
+        let sink = LogFileSink::open(&mafa_in.event_log)?;
+        ntf.lock().expect("bug").add_sink(Box::new(sink));
+    }
+
+    Ok(())
+}
+
+///
+/// forward to the running daemon, if components are requested, and they
+/// need nothing but what the daemon can serve
+fn try_forward(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    matched: &clap::ArgMatches,
+) -> Option<DaemonResponse> {
+    if mafa_in.no_daemon
+        || mafa_in.list_profile
+        || !mafa_in.record.is_empty()
+        || !mafa_in.replay.is_empty()
+        || !mafa_in.elap_trace.is_empty()
+        || mafa_in.offline
+    {
+        return None;
+    }
+
+    match matched.subcommand() {
+        #[cfg(feature = "gtrans")]
+        Some(("gtrans", sub_m)) => {
+            // standard input is not forwardable
+            if let Ok(Some(from_file)) =
+                sub_m.try_get_one::<String>(mafa::gtrans::opts::FromFile::id())
+            {
+                if from_file == "-" {
+                    return None;
+                }
+            }
+        }
+
+        #[cfg(feature = "twtl")]
+        Some(("twtl", sub_m)) => {
+            // login needs user's interaction
+            if sub_m.get_flag(mafa::twtl::opts::TryLogin::id()) {
+                return None;
+            }
+        }
+
+        #[cfg(feature = "camd")]
+        Some(("camd", _)) => {}
+
+        _ => return None,
+    }
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // the daemon cannot tell whether our output is a terminal, nor how
+    // wide it is
+    if mafa_in.nocolor && !args.iter().any(|v| v == "--nocolor") {
+        args.insert(0, "--nocolor".to_string());
+    }
+    if mafa_in.wrap_width == 0 {
+        // replacing the "auto" given, if any, clap refuses a repeated one
+        let longopt = format!("--{}", mafa::opts::WrapWidth::longopt());
+        let subcmd = matched.subcommand_name().unwrap_or_default();
+        let n_global = args.iter().position(|v| v == subcmd).unwrap_or(args.len());
+        let mut global: Vec<String> = args.drain(..n_global).collect();
+        if let Some(i) = global.iter().position(|v| *v == longopt) {
+            global.drain(i..(i + 2).min(global.len()));
+        }
+        global.retain(|v| !v.starts_with(&format!("{}=", longopt)));
+        global.insert(0, format!("{}={}", longopt, mafa_in.wrap_width_now()));
+        args.splice(0..0, global);
+    }
+    let req = DaemonRequest::from_current(args);
+
+    daemon::forward(&mafad.pathto_daemon_sock(), &req)
+}
+
+fn serve_daemon<B: BrowserBackend>(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    wda_inst: &B,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    let sock = mafad.pathto_daemon_sock();
+
+    lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
+        cate: Category::Mafa,
+        kind: EurKind::DaemonStarted,
+        output: format!("Serving at {}, press Ctrl-C to quit", sock.display()),
+    });
+
+    #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+    let mut held = HeldCaches::default();
+    let served = daemon::serve(&sock, |req| {
+        daemon::within_requester(&req, || {
+            handle_daemon_request(
+                mafad,
+                mafa_in,
+                wda_inst,
+                &req,
+                #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+                &mut held,
+            )
+        })
+    });
+
+    if let Err(err_daemon) = served {
+        let code = err_daemon.exit_code();
+        lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
+            cate: Category::Mafa,
+            err: err_daemon,
+        });
+
+        return code;
+    }
+
+    0
+}
+
+fn handle_daemon_request<B: BrowserBackend>(
+    mafad: &MafaData,
+    daemon_in: &MafaInput,
+    wda_inst: &B,
+    req: &DaemonRequest,
+    #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))] held: &mut HeldCaches,
+) -> DaemonResponse {
+    let all_args = std::iter::once("mafa".to_string()).chain(req.args.iter().cloned());
+    let matched = match mafa::get_cmd().try_get_matches_from(all_args) {
+        Ok(v) => v,
+        Err(_) => return DaemonResponse::refuse("arguments not recognized"),
+    };
+    let req_in = match MafaInput::from_ca_matched(&matched) {
+        Ok(v) => v,
+        Err(_) => return DaemonResponse::refuse("arguments not valid"),
+    };
+
+    if !daemon::is_browser_compatible(daemon_in, &req_in) {
+        return DaemonResponse::refuse("browser options differ");
+    }
+
+    let comp = match matched.subcommand_name() {
+        Some(v @ "gtrans") | Some(v @ "twtl") | Some(v @ "camd") => v,
+        _ => return DaemonResponse::refuse("components only"),
+    };
+
+    let captured = daemon::capture(|| {
+        let ntf = Arc::new(Mutex::new(EventNotifier::new()));
+        if let Err(err_ntf) = apply_ntf_modes(&ntf, &req_in) {
+            let code = err_ntf.exit_code();
+            ntf.lock().expect("bug").notify(MafaEvent::FatalMafaError {
+                cate: Category::Mafa,
+                err: err_ntf,
+            });
+            return code;
+        }
+
+        // same as run_subcmd, but with caches held
+        match matched.subcommand() {
+            #[cfg(feature = "gtrans")]
+            Some(("gtrans", sub_m)) => workflow_gtrans(
+                mafad,
+                &req_in,
+                GtransInput::from_ca_matched(sub_m),
+                held.upaths(mafad, &req_in, comp),
+                wda_inst,
+                ntf,
+            ),
+
+            #[cfg(feature = "twtl")]
+            Some(("twtl", sub_m)) => workflow_twtl(
+                mafad,
+                &req_in,
+                TwtlInput::from_ca_matched(sub_m),
+                held.ulpaths(mafad, &req_in, comp),
+                wda_inst,
+                ntf,
+            ),
+
+            #[cfg(feature = "camd")]
+            Some(("camd", sub_m)) => workflow_camd(
+                mafad,
+                &req_in,
+                CamdInput::from_ca_matched(sub_m),
+                held.upaths(mafad, &req_in, comp),
+                wda_inst,
+                ntf,
+            ),
+
+            _ => run_subcmd(mafad, &req_in, &matched, wda_inst, ntf),
+        }
+    });
+
+    #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+    held.settle(
+        mafad,
+        comp,
+        matches!(captured, Ok((code, _, _)) if code == 0),
+    );
+    #[cfg(not(any(feature = "gtrans", feature = "twtl", feature = "camd")))]
+    let _ = comp;
+
+    match captured {
+        Ok((code, stdout, stderr)) => DaemonResponse {
+            code,
+            stdout,
+            stderr,
+            refused: None,
+        },
+        Err(_) => DaemonResponse::refuse("output not capturable"),
+    }
+}
+
+#[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+fn serve_httpd<B: BrowserBackend>(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    sub_m: &clap::ArgMatches,
+    wda_inst: &B,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    let httpd_in = HttpdInput::from_ca_matched(sub_m).expect("buggy");
+
+    lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
+        cate: Category::Mafa,
+        kind: EurKind::HttpdStarted,
+        output: format!(
+            "Serving at http://{}, press Ctrl-C to quit",
+            httpd_in.listen
+        ),
+    });
+
+    let api = HttpApi::new(mafad, mafa_in, wda_inst);
+    if let Err(err_httpd) = api.serve(&httpd_in.listen) {
+        let code = err_httpd.exit_code();
+        lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
+            cate: Category::Mafa,
+            err: err_httpd,
+        });
+
+        return code;
+    }
+
+    0
+}
+
+#[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+fn run_cache(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    matched: &clap::ArgMatches,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    let sub_m = matched.subcommand_matches("cache").expect("buggy");
+
+    match mafa::cache::handle(mafad, sub_m, mafa_in.output) {
+        Ok((eurk, ret)) => {
+            lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
+                cate: Category::Mafa,
+                kind: eurk,
+                output: ret,
+            });
+
+            0
+        }
+        Err(err_cache) => {
+            let code = err_cache.exit_code();
+            lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
+                cate: Category::Mafa,
+                err: err_cache,
+            });
+
+            code
+        }
+    }
+}
+
+#[cfg(not(any(feature = "gtrans", feature = "twtl", feature = "camd")))]
+fn run_cache(
+    _mafad: &MafaData,
+    _mafa_in: &MafaInput,
+    _matched: &clap::ArgMatches,
+    _ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    0
+}
+
+#[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+fn rebuild_cache<B: BrowserBackend>(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    sub_m: &clap::ArgMatches,
+    wda_inst: &B,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    let cache_id = mafa::cache::rebuild_id(sub_m).unwrap_or_default();
+
+    let rebuilt = match cache_id {
+        #[cfg(feature = "gtrans")]
+        "gtrans" => MafaClient::<GtransInput, mafa::gtrans::Upath, B>::new(
+            mafad,
+            Arc::clone(&ntf),
+            mafa_in,
+            GtransInput::default(),
+            wda_inst,
+        )
+        .rebuild_cache(),
+
+        #[cfg(feature = "camd")]
+        "camd" => MafaClient::<CamdInput, mafa::camd::Upath, B>::new(
+            mafad,
+            Arc::clone(&ntf),
+            mafa_in,
+            CamdInput::default(),
+            wda_inst,
+        )
+        .rebuild_cache(),
+
+        #[cfg(feature = "twtl")]
+        "twtl" => MafaClient::<TwtlInput, mafa::twtl::UlPath, B>::new(
+            mafad,
+            Arc::clone(&ntf),
+            mafa_in,
+            TwtlInput::default(),
+            wda_inst,
+        )
+        .rebuild_cache(),
+
+        // component not built in
+        _ => Err(MafaError::MafaDataCacheNotFound(cache_id.to_string())),
+    };
+
+    match rebuilt {
+        Ok(n_entries) => {
+            lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
+                cate: Category::Mafa,
+                kind: EurKind::CacheChanged,
+                output: format!("rebuilt: {} ({} entries)", cache_id, n_entries),
+            });
+
+            0
+        }
+        Err(err_rebuild) => {
+            let code = err_rebuild.exit_code();
+            lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
+                cate: Category::Mafa,
+                err: err_rebuild,
+            });
+
+            code
+        }
+    }
+}
+
+fn run_subcmd<B: BrowserBackend>(
+    mafad: &MafaData,
+    mafa_in: &MafaInput,
+    matched: &clap::ArgMatches,
+    wda_inst: &B,
+    ntf: Arc<Mutex<EventNotifier>>,
+) -> u8 {
+    match matched.subcommand() {
+        #[cfg(feature = "gtrans")]
+        Some(("gtrans", sub_m)) => {
+            let gtrans_in = GtransInput::from_ca_matched(sub_m);
+            workflow_gtrans(mafad, mafa_in, gtrans_in, None, wda_inst, Arc::clone(&ntf))
+        }
+
+        #[cfg(feature = "twtl")]
+        Some(("twtl", sub_m)) => {
+            let twtl_in = TwtlInput::from_ca_matched(sub_m);
+            workflow_twtl(mafad, mafa_in, twtl_in, None, wda_inst, Arc::clone(&ntf))
+        }
+
+        #[cfg(feature = "camd")]
+        Some(("camd", sub_m)) => {
+            let camd_in = CamdInput::from_ca_matched(sub_m);
+            workflow_camd(mafad, mafa_in, camd_in, None, wda_inst, Arc::clone(&ntf))
+        }
+
+        #[cfg(feature = "imode")]
+        Some(("i", _)) => enter_i_mode(mafad, mafa_in, wda_inst, Arc::clone(&ntf)),
+
+        Some(("daemon", _)) => serve_daemon(mafad, mafa_in, wda_inst, Arc::clone(&ntf)),
+
+        #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+        Some(("serve", sub_m)) => serve_httpd(mafad, mafa_in, sub_m, wda_inst, Arc::clone(&ntf)),
+
+        #[cfg(any(feature = "gtrans", feature = "twtl", feature = "camd"))]
+        Some(("cache", sub_m)) => rebuild_cache(mafad, mafa_in, sub_m, wda_inst, Arc::clone(&ntf)),
+
+        _ => {
+            ntf.lock()
+                .expect("buggy")
+                .notify(MafaEvent::ExactUserRequest {
+                    cate: Category::Mafa,
+                    kind: EurKind::NoSubCmd,
+                    output:
+                        "no components supplied. Please check supported components by -h or --help"
+                            .into(),
+                });
+
+            0
+        }
+    }
+}
+
 #[cfg(feature = "imode")]
-fn enter_i_mode(
+fn enter_i_mode<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> u8 {
+    // Ctrl-C at prompts is read by rustyline, otherwise it cancels the
+    // request in flight
+    mafa::cancel::install();
+
     let mut rl = DefaultEditor::new().unwrap();
     loop {
         let readline = rl.readline("[mafa] >> ");
@@ -229,10 +849,10 @@ fn enter_i_mode(
                 match line.as_str() {
                     #[cfg(feature = "gtrans")]
                     "gtrans" => {
-                        if let Err(_err_imode) =
+                        if let Err(err_imode) =
                             gtrans_i_mode(mafad, mafa_in, wda_inst, Arc::clone(&ntf))
                         {
-                            return 4;
+                            return err_imode.exit_code();
                         } else {
                             continue;
                         }
@@ -240,10 +860,10 @@ fn enter_i_mode(
 
                     #[cfg(feature = "twtl")]
                     "twtl" => {
-                        if let Err(_err_imode) =
+                        if let Err(err_imode) =
                             twtl_i_mode(mafad, mafa_in, wda_inst, Arc::clone(&ntf))
                         {
-                            return 4;
+                            return err_imode.exit_code();
                         } else {
                             continue;
                         }
@@ -251,10 +871,10 @@ fn enter_i_mode(
 
                     #[cfg(feature = "camd")]
                     "camd" => {
-                        if let Err(_err_imode) =
+                        if let Err(err_imode) =
                             camd_i_mode(mafad, mafa_in, wda_inst, Arc::clone(&ntf))
                         {
-                            return 4;
+                            return err_imode.exit_code();
                         } else {
                             continue;
                         }
@@ -306,18 +926,23 @@ fn enter_i_mode(
         }
     }
 
+    // where the latency goes across the whole session
+    if mafa_in.elap {
+        ntf.lock().expect("buggy").elap_stats();
+    }
+
     return 0;
 }
 
 #[cfg(all(feature = "imode", feature = "gtrans"))]
-fn gtrans_i_mode(
+fn gtrans_i_mode<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> Result<()> {
     let mut rl = DefaultEditor::new().unwrap();
-    let mut client: Option<MafaClient<GtransInput, mafa::gtrans::Upath>> = None;
+    let mut client: Option<MafaClient<GtransInput, mafa::gtrans::Upath, B>> = None;
     loop {
         let readline = rl.readline("[mafa-gtrans] >> ");
         match readline {
@@ -380,6 +1005,7 @@ fn gtrans_i_mode(
                     client.as_mut().expect("bug").set_sub_input(gtrans_in);
                 }
 
+                mafa::cancel::reset();
                 match client.as_mut().expect("bug").handle(None) {
                     Ok((eurk, ret)) => {
                         lock_or_err!(ntf).notify(MafaEvent::ExactUserRequest {
@@ -398,10 +1024,12 @@ fn gtrans_i_mode(
 
                     Err(err_hdl) => match err_hdl {
                         MafaError::AllCachesInvalid
+                        | MafaError::ResultNotStored
                         | MafaError::DataFetchedNotReachable
                         | MafaError::WebDrvCmdRejected(_, _)
                         | MafaError::UnexpectedWda(_)
-                        | MafaError::CacheRebuildFail(_) => {
+                        | MafaError::CacheRebuildFail(_)
+                        | MafaError::Cancelled => {
                             lock_or_err!(ntf).notify(MafaEvent::FatalMafaError {
                                 cate: Category::Gtrans,
                                 err: err_hdl,
@@ -437,14 +1065,14 @@ fn gtrans_i_mode(
 }
 
 #[cfg(all(feature = "imode", feature = "twtl"))]
-fn twtl_i_mode(
+fn twtl_i_mode<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> Result<()> {
     let mut rl = DefaultEditor::new().unwrap();
-    let mut client: Option<MafaClient<TwtlInput, mafa::twtl::UlPath>> = None;
+    let mut client: Option<MafaClient<TwtlInput, mafa::twtl::UlPath, B>> = None;
 
     loop {
         let readline = rl.readline("[mafa-twtl] >> ");
@@ -505,6 +1133,7 @@ fn twtl_i_mode(
                     client.as_mut().expect("bug").set_sub_input(twtl_in);
                 }
 
+                mafa::cancel::reset();
                 match client.as_mut().expect("bug").handle(None) {
                     Ok((ewrk, ret)) => {
                         lock_or_err!(ntf).notify(MafaEvent::ExactUserRequest {
@@ -529,7 +1158,8 @@ fn twtl_i_mode(
                         | MafaError::DataFetchedNotReachable
                         | MafaError::WebDrvCmdRejected(_, _)
                         | MafaError::UnexpectedWda(_)
-                        | MafaError::CacheRebuildFail(_) => {
+                        | MafaError::CacheRebuildFail(_)
+                        | MafaError::Cancelled => {
                             lock_or_err!(ntf).notify(MafaEvent::FatalMafaError {
                                 cate: Category::Twtl,
                                 err: err_hdl,
@@ -564,14 +1194,14 @@ fn twtl_i_mode(
 }
 
 #[cfg(all(feature = "imode", feature = "camd"))]
-fn camd_i_mode(
+fn camd_i_mode<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> Result<()> {
     let mut rl = DefaultEditor::new().unwrap();
-    let mut client: Option<MafaClient<CamdInput, mafa::camd::Upath>> = None;
+    let mut client: Option<MafaClient<CamdInput, mafa::camd::Upath, B>> = None;
 
     loop {
         let readline = rl.readline("[mafa-camd] >> ");
@@ -633,6 +1263,7 @@ fn camd_i_mode(
                     client.as_mut().expect("bug").set_sub_input(camd_in);
                 }
 
+                mafa::cancel::reset();
                 match client.as_mut().expect("bug").handle(None) {
                     Ok((eurk, ret)) => {
                         lock_or_err!(ntf).notify(MafaEvent::ExactUserRequest {
@@ -651,10 +1282,12 @@ fn camd_i_mode(
 
                     Err(err_hdl) => match err_hdl {
                         MafaError::AllCachesInvalid
+                        | MafaError::ResultNotStored
                         | MafaError::DataFetchedNotReachable
                         | MafaError::WebDrvCmdRejected(_, _)
                         | MafaError::UnexpectedWda(_)
-                        | MafaError::CacheRebuildFail(_) => {
+                        | MafaError::CacheRebuildFail(_)
+                        | MafaError::Cancelled => {
                             lock_or_err!(ntf).notify(MafaEvent::FatalMafaError {
                                 cate: Category::Camd,
                                 err: err_hdl,
@@ -690,11 +1323,12 @@ fn camd_i_mode(
 }
 
 #[cfg(feature = "gtrans")]
-fn workflow_gtrans(
+fn workflow_gtrans<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
     gtrans_in: Result<GtransInput>,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    pred_caches: Option<Vec<Vec<u8>>>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> u8 {
     if let Err(err_in) = gtrans_in {
@@ -704,21 +1338,23 @@ fn workflow_gtrans(
             | MafaError::InvalidSocks5Proxy
             | MafaError::InvalidSourceLang
             | MafaError::InvalidTargetLang => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Gtrans,
                     err: err_in,
                 });
 
-                return 1;
+                return code;
             }
 
             _ => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Gtrans,
                     err: err_in,
                 });
 
-                return 1;
+                return code;
             }
         }
     }
@@ -727,7 +1363,7 @@ fn workflow_gtrans(
 
     let mut client = MafaClient::new(mafad, Arc::clone(&ntf), mafa_in, gtrans_in, wda_inst);
 
-    match client.handle(None) {
+    match client.handle(pred_caches) {
         Ok((eurk, ret)) => {
             lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
                 cate: Category::Gtrans,
@@ -739,29 +1375,37 @@ fn workflow_gtrans(
                 lock_or_rtn!(ntf).elap(Category::Gtrans);
             }
 
+            if eurk == EurKind::GtransPartial {
+                return mafa::error::exit_code::PARTIAL;
+            }
+
             return 0;
         }
         Err(err_hdl) => match err_hdl {
             MafaError::AllCachesInvalid
+            | MafaError::ResultNotStored
             | MafaError::DataFetchedNotReachable
             | MafaError::WebDrvCmdRejected(_, _)
             | MafaError::UnexpectedWda(_)
-            | MafaError::CacheRebuildFail(_) => {
+            | MafaError::CacheRebuildFail(_)
+            | MafaError::InvalidFromFile(..) => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Gtrans,
                     err: err_hdl,
                 });
 
-                return 3;
+                return code;
             }
 
             _ => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Gtrans,
                     err: err_hdl,
                 });
 
-                return 3;
+                return code;
             }
         },
     }
@@ -770,11 +1414,12 @@ fn workflow_gtrans(
 }
 
 #[cfg(feature = "twtl")]
-fn workflow_twtl(
+fn workflow_twtl<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
     twtl_in: Result<TwtlInput>,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    pred_caches: Option<Vec<Vec<Vec<u8>>>>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> u8 {
     if let Err(err_in) = twtl_in {
@@ -784,18 +1429,20 @@ fn workflow_twtl(
             | MafaError::InvalidSocks5Proxy
             | MafaError::InvalidNumTweets
             | MafaError::InvalidWrapWidth => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Twtl,
                     err: err_in,
                 });
-                return 1;
+                return code;
             }
             _ => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Twtl,
                     err: err_in,
                 });
-                return 1;
+                return code;
             }
         }
     }
@@ -804,7 +1451,7 @@ fn workflow_twtl(
 
     let mut client = MafaClient::new(mafad, Arc::clone(&ntf), mafa_in, twtl_in, wda_inst);
 
-    match client.handle(None) {
+    match client.handle(pred_caches) {
         Ok((ewrk, ret)) => {
             lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
                 cate: Category::Twtl,
@@ -827,19 +1474,21 @@ fn workflow_twtl(
             | MafaError::WebDrvCmdRejected(_, _)
             | MafaError::UnexpectedWda(_)
             | MafaError::CacheRebuildFail(_) => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Twtl,
                     err: err_hdl,
                 });
-                return 3;
+                code
             }
 
             _ => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Twtl,
                     err: err_hdl,
                 });
-                return 3;
+                code
             }
         },
     }
@@ -848,11 +1497,12 @@ fn workflow_twtl(
 }
 
 #[cfg(feature = "camd")]
-fn workflow_camd(
+fn workflow_camd<B: BrowserBackend>(
     mafad: &MafaData,
     mafa_in: &MafaInput,
     camd_in: Result<CamdInput>,
-    wda_inst: &WebDrvAstn<GeckoDriver>,
+    pred_caches: Option<Vec<Vec<u8>>>,
+    wda_inst: &B,
     ntf: Arc<Mutex<EventNotifier>>,
 ) -> u8 {
     if let Err(err_in) = camd_in {
@@ -862,21 +1512,23 @@ fn workflow_camd(
             | MafaError::InvalidSocks5Proxy
             | MafaError::InvalidSourceLang
             | MafaError::InvalidTargetLang => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Camd,
                     err: err_in,
                 });
 
-                return 1;
+                return code;
             }
 
             _ => {
+                let code = err_in.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Camd,
                     err: err_in,
                 });
 
-                return 1;
+                return code;
             }
         }
     }
@@ -885,7 +1537,7 @@ fn workflow_camd(
 
     let mut client = MafaClient::new(mafad, Arc::clone(&ntf), mafa_in, camd_in, wda_inst);
 
-    match client.handle(None) {
+    match client.handle(pred_caches) {
         Ok((eurk, ret)) => {
             lock_or_rtn!(ntf).notify(MafaEvent::ExactUserRequest {
                 cate: Category::Camd,
@@ -901,25 +1553,28 @@ fn workflow_camd(
         }
         Err(err_hdl) => match err_hdl {
             MafaError::AllCachesInvalid
+            | MafaError::ResultNotStored
             | MafaError::DataFetchedNotReachable
             | MafaError::WebDrvCmdRejected(_, _)
             | MafaError::UnexpectedWda(_)
             | MafaError::CacheRebuildFail(_) => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::FatalMafaError {
                     cate: Category::Camd,
                     err: err_hdl,
                 });
 
-                return 3;
+                code
             }
 
             _ => {
+                let code = err_hdl.exit_code();
                 lock_or_rtn!(ntf).notify(MafaEvent::HandlerMissed {
                     cate: Category::Camd,
                     err: err_hdl,
                 });
 
-                return 3;
+                code
             }
         },
     }
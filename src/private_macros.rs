@@ -12,7 +12,7 @@ macro_rules! lock_or_rtn {
     ($lck:expr) => {{
         let grd = $lck.lock();
         if grd.is_err() {
-            return 7;
+            return mafa::error::exit_code::BUG;
         }
         let grd = grd.expect("buggy");
         grd
@@ -22,7 +22,8 @@ macro_rules! lock_or_rtn {
 #[allow(unused_macros)]
 macro_rules! lock_or_err {
     ($lck:expr) => {
-        $lck.lock().or_else(|_| Err(MafaError::BugFound(7890)))?
+        $lck.lock()
+            .or_else(|_| Err(MafaError::Bug("lock poisoned")))?
     };
 }
 
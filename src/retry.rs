@@ -0,0 +1,246 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! How many times, and how patiently, requests to the websites are
+//! retried.
+//!
+//! Every retry loop has its own default [`RetryPolicy`], fields given by
+//! options `--retry-max`, `--retry-delay`, `--retry-multiplier`,
+//! `--retry-jitter` and `--retry-deadline` (or their config keys and
+//! environment variables) replace the corresponding ones of all loops.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    ///
+    /// the delay of the first attempt
+    pub base_delay: Duration,
+    ///
+    /// the delay grows by this factor on each backoff
+    pub multiplier: f64,
+    ///
+    /// delays are randomly shifted by up to this fraction, 0 to 1
+    pub jitter: f64,
+    ///
+    /// no more attempts after this long since the first one
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn begin(&self) -> Attempts {
+        Attempts {
+            policy: *self,
+            left: self.max_attempts,
+            delay: self.base_delay,
+            since: Instant::now(),
+        }
+    }
+}
+
+///
+/// gtrans' navigation and result extraction, the delay is the time waited
+/// for the page before extracting
+pub(crate) const GTRANS_FETCH: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    base_delay: Duration::from_millis(100),
+    multiplier: 2.0,
+    jitter: 0.0,
+    deadline: None,
+};
+
+///
+/// camd's navigation and result extraction, same as [`GTRANS_FETCH`]
+pub(crate) const CAMD_FETCH: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    base_delay: Duration::from_millis(10),
+    multiplier: 2.0,
+    jitter: 0.0,
+    deadline: None,
+};
+
+///
+/// locating upaths when building caches of gtrans and camd
+pub(crate) const CACHE_BUILD: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    base_delay: Duration::from_millis(500),
+    multiplier: 2.0,
+    jitter: 0.0,
+    deadline: None,
+};
+
+///
+/// twtl's navigation to the timeline
+pub(crate) const TWTL_NAVIGATE: RetryPolicy = RetryPolicy {
+    max_attempts: 10,
+    base_delay: Duration::ZERO,
+    multiplier: 1.0,
+    jitter: 0.0,
+    deadline: None,
+};
+
+///
+/// twtl's scrolling with no new tweets loaded, the timeline is considered
+/// ended once attempts run out
+pub(crate) const TWTL_SCROLL: RetryPolicy = RetryPolicy {
+    max_attempts: 10,
+    base_delay: Duration::from_secs(1),
+    multiplier: 1.0,
+    jitter: 0.0,
+    deadline: None,
+};
+
+///
+/// Fields replacing those of the defaults, None for keeping the default.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RetryOverride {
+    pub max_attempts: Option<u32>,
+    pub base_delay: Option<Duration>,
+    pub multiplier: Option<f64>,
+    pub jitter: Option<f64>,
+    pub deadline: Option<Duration>,
+}
+
+impl RetryOverride {
+    pub fn apply(&self, def: RetryPolicy) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(def.max_attempts),
+            base_delay: self.base_delay.unwrap_or(def.base_delay),
+            multiplier: self.multiplier.unwrap_or(def.multiplier),
+            jitter: self.jitter.unwrap_or(def.jitter),
+            deadline: self.deadline.or(def.deadline),
+        }
+    }
+}
+
+fn scale(d: Duration, factor: f64) -> Duration {
+    Duration::try_from_secs_f64(d.as_secs_f64() * factor.max(0.0)).unwrap_or(Duration::MAX)
+}
+
+///
+/// The state of one retry loop.
+#[derive(Debug)]
+pub struct Attempts {
+    policy: RetryPolicy,
+    left: u32,
+    delay: Duration,
+    since: Instant,
+}
+
+impl Attempts {
+    fn time_left(&self) -> Option<Duration> {
+        self.policy
+            .deadline
+            .map(|v| v.saturating_sub(self.since.elapsed()))
+    }
+
+    ///
+    /// Whether another attempt is allowed, i.e., neither attempts nor
+    /// time has run out.
+    pub fn remain(&self) -> bool {
+        self.left > 0 && self.time_left() != Some(Duration::ZERO)
+    }
+
+    ///
+    /// The delay to wait in the current attempt, jittered, and no longer
+    /// than the time left.
+    pub fn delay(&self) -> Duration {
+        let mut delay = self.delay;
+
+        if self.policy.jitter > 0.0 {
+            // uniform in [-1, 1)
+            let r = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64 * 2.0 - 1.0;
+            delay = scale(delay, 1.0 + self.policy.jitter.min(1.0) * r);
+        }
+
+        match self.time_left() {
+            Some(left) => delay.min(left),
+            None => delay,
+        }
+    }
+
+    ///
+    /// Count one failed attempt, with the delay unchanged.
+    pub fn fail(&mut self) {
+        self.left = self.left.saturating_sub(1);
+    }
+
+    ///
+    /// Count one failed attempt, and back off the delay.
+    pub fn backoff(&mut self) {
+        self.fail();
+        self.delay = scale(self.delay, self.policy.multiplier);
+    }
+
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+
+    const DEF: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(100),
+        multiplier: 2.0,
+        jitter: 0.0,
+        deadline: None,
+    };
+
+    #[test]
+    fn attempts_1() {
+        let mut att = DEF.begin();
+        assert!(att.remain());
+        assert_eq!(att.delay(), Duration::from_millis(100));
+        att.fail();
+        assert_eq!(att.delay(), Duration::from_millis(100));
+        att.backoff();
+        assert_eq!(att.delay(), Duration::from_millis(200));
+        assert!(att.remain());
+        att.backoff();
+        assert!(!att.remain());
+    }
+
+    #[test]
+    fn attempts_2() {
+        let policy = RetryPolicy { jitter: 0.5, ..DEF };
+        let att = policy.begin();
+        for _ in 0..100 {
+            let delay = att.delay();
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+        }
+
+        let policy = RetryPolicy {
+            deadline: Some(Duration::ZERO),
+            ..DEF
+        };
+        assert!(!policy.begin().remain());
+    }
+
+    #[test]
+    fn override_1() {
+        let ovr = RetryOverride {
+            max_attempts: Some(10),
+            deadline: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let policy = ovr.apply(DEF);
+        assert_eq!(policy.max_attempts, 10);
+        assert_eq!(policy.base_delay, DEF.base_delay);
+        assert_eq!(policy.deadline, Some(Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright (C) 2023 Michael Lee <micl2e2@proton.me>
+//
+// Licensed under the GNU General Public License, Version 3.0 or any later
+// version <LICENSE-GPL or https://www.gnu.org/licenses/gpl-3.0.txt>.
+//
+// This file may not be copied, modified, or distributed except in compliance
+// with the license.
+//
+
+//!
+//! Colors of everything printed for humans.
+//!
+//! The `[theme]` table of the config file overrides the default styles,
+//! one key per role, each value is a list of words separated by spaces:
+//!
+//! ```toml
+//! [theme]
+//! palette = "256"
+//! header = "bold #00afff"
+//! username = "bold 39"
+//! timestamp = "green"
+//! error = "bold bright-red"
+//! link = "none"
+//! ```
+//!
+//! A word is one of the attributes `bold`, `dim`, `italic`, `underline`,
+//! a foreground color, or `on` followed by a background color. A color is
+//! one of the 16 names (`red`, `bright-red`, `gray`, etc.), an index of
+//! the 256-color palette (`0`-`255`) or a truecolor (`#rrggbb`). `none`
+//! leaves the role uncolored.
+//!
+//! `palette` is one of `16`, `256`, `truecolor` or `auto`, the default,
+//! which follows `COLORTERM` and `TERM`. Colors that do not fit in the
+//! palette are downgraded to their nearest ones.
+//!
+//! Color is disabled entirely by `--nocolor`, by a non-empty `NO_COLOR`
+//! environment variable, or when standard output is not a terminal.
+
+use once_cell::sync::OnceCell;
+
+use crate::config::MafaConfig;
+use crate::error::MafaError;
+use crate::error::Result;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Palette {
+    Ansi16,
+    Ansi256,
+    Truecolor,
+}
+
+impl Palette {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "16" => Some(Self::Ansi16),
+            "256" => Some(Self::Ansi256),
+            "truecolor" | "24bit" => Some(Self::Truecolor),
+            "auto" => Some(Self::detect()),
+            _ => None,
+        }
+    }
+
+    ///
+    /// What the terminal claims to support.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::Truecolor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Color {
+    ///
+    /// 0-7 for normal ones, 8-15 for bright ones
+    Named(u8),
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+// xterm's defaults
+const RGB_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn dist(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+fn nearest_level(v: u8) -> usize {
+    (0..CUBE_LEVELS.len())
+        .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - v as i32).abs())
+        .expect("buggy")
+}
+
+impl Color {
+    fn from_str(s: &str) -> Option<Self> {
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.is_ascii() {
+                return None;
+            }
+            let comp = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Self::Rgb(comp(0)?, comp(2)?, comp(4)?));
+        }
+
+        if let Ok(idx) = s.parse::<u8>() {
+            return Some(Self::Fixed(idx));
+        }
+
+        if s == "gray" || s == "grey" {
+            return Some(Self::Named(8));
+        }
+
+        let (base, name) = match s.strip_prefix("bright-") {
+            Some(name) => (8, name),
+            None => (0, s),
+        };
+        let idx = NAMES.iter().position(|v| *v == name)?;
+
+        Some(Self::Named(base + idx as u8))
+    }
+
+    fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Named(n) => RGB_16[n as usize % 16],
+            Self::Fixed(n) if n < 16 => RGB_16[n as usize],
+            Self::Fixed(n) if n < 232 => {
+                let n = n - 16;
+                (
+                    CUBE_LEVELS[(n / 36) as usize],
+                    CUBE_LEVELS[(n / 6 % 6) as usize],
+                    CUBE_LEVELS[(n % 6) as usize],
+                )
+            }
+            Self::Fixed(n) => {
+                let v = 8 + 10 * (n - 232);
+                (v, v, v)
+            }
+            Self::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    ///
+    /// The nearest color available in `palette`.
+    pub fn fit(self, palette: Palette) -> Self {
+        match (self, palette) {
+            (Self::Named(_), _) | (_, Palette::Truecolor) => self,
+            (Self::Fixed(n), Palette::Ansi16) if n < 16 => Self::Named(n),
+            (Self::Fixed(_), Palette::Ansi256) => self,
+            (Self::Rgb(r, g, b), Palette::Ansi256) => {
+                let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+                let in_cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+                let idx_cube = 16 + 36 * ri + 6 * gi + bi;
+
+                let avg = (r as u32 + g as u32 + b as u32) / 3;
+                let idx_gray = ((avg.max(8) - 8 + 5) / 10).min(23) as u8;
+                let v_gray = 8 + 10 * idx_gray;
+
+                if dist((r, g, b), (v_gray, v_gray, v_gray)) < dist((r, g, b), in_cube) {
+                    Self::Fixed(232 + idx_gray)
+                } else {
+                    Self::Fixed(idx_cube as u8)
+                }
+            }
+            (_, Palette::Ansi16) => {
+                let rgb = self.to_rgb();
+                let idx = (0..RGB_16.len())
+                    .min_by_key(|i| dist(rgb, RGB_16[*i]))
+                    .expect("buggy");
+                Self::Named(idx as u8)
+            }
+        }
+    }
+
+    fn sgr(self, is_bg: bool) -> String {
+        let base = if is_bg { 40 } else { 30 };
+        match self {
+            Self::Named(n) if n < 8 => format!("{}", base + n as u16),
+            Self::Named(n) => format!("{}", base + 60 + (n as u16 % 16 - 8)),
+            Self::Fixed(n) => format!("{};5;{}", base + 8, n),
+            Self::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+        }
+    }
+}
+
+const ATTR_BOLD: u8 = 1;
+const ATTR_DIM: u8 = 1 << 1;
+const ATTR_ITALIC: u8 = 1 << 2;
+const ATTR_UNDERLINE: u8 = 1 << 3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    attrs: u8,
+}
+
+impl Style {
+    pub const PLAIN: Style = Style {
+        fg: None,
+        bg: None,
+        attrs: 0,
+    };
+
+    const fn fg(color: Color) -> Self {
+        Style {
+            fg: Some(color),
+            bg: None,
+            attrs: 0,
+        }
+    }
+
+    const fn on(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    const fn bold(mut self) -> Self {
+        self.attrs |= ATTR_BOLD;
+        self
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        let mut style = Self::PLAIN;
+
+        let mut words = s.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "none" => {}
+                "bold" => style.attrs |= ATTR_BOLD,
+                "dim" => style.attrs |= ATTR_DIM,
+                "italic" => style.attrs |= ATTR_ITALIC,
+                "underline" => style.attrs |= ATTR_UNDERLINE,
+                "on" => style.bg = Some(Color::from_str(words.next()?)?),
+                other => style.fg = Some(Color::from_str(other)?),
+            }
+        }
+
+        Some(style)
+    }
+
+    fn fit(self, palette: Palette) -> Self {
+        Style {
+            fg: self.fg.map(|v| v.fit(palette)),
+            bg: self.bg.map(|v| v.fit(palette)),
+            attrs: self.attrs,
+        }
+    }
+
+    fn sgr(&self) -> String {
+        let mut all_param = vec![];
+        if let Some(fg) = self.fg {
+            all_param.push(fg.sgr(false));
+        }
+        if let Some(bg) = self.bg {
+            all_param.push(bg.sgr(true));
+        }
+        for (i, attr) in [ATTR_BOLD, ATTR_DIM, ATTR_ITALIC, ATTR_UNDERLINE]
+            .iter()
+            .enumerate()
+        {
+            if self.attrs & attr != 0 {
+                all_param.push((i + 1).to_string());
+            }
+        }
+
+        all_param.join(";")
+    }
+
+    ///
+    /// `s` wrapped in this style's escapes, or `s` itself if the style
+    /// is plain.
+    pub fn paint(&self, s: &str) -> String {
+        if *self == Self::PLAIN {
+            s.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", self.sgr(), s)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    ///
+    /// result headers of gtrans and camd
+    pub header: Style,
+    ///
+    /// camd's section dividers, e.g., examples
+    pub section: Style,
+    pub username: Style,
+    pub timestamp: Style,
+    pub pronun_us: Style,
+    pub pronun_uk: Style,
+    ///
+    /// meanings, example sources
+    pub emphasis: Style,
+    ///
+    /// retweets, replies, unrecognized raw data
+    pub note: Style,
+    pub quote: Style,
+    pub link: Style,
+    pub error: Style,
+    pub tip: Style,
+    ///
+    /// keys the user is asked to press
+    pub key: Style,
+}
+
+const ALL_ROLE: [&str; 13] = [
+    "header",
+    "section",
+    "username",
+    "timestamp",
+    "pronun-us",
+    "pronun-uk",
+    "emphasis",
+    "note",
+    "quote",
+    "link",
+    "error",
+    "tip",
+    "key",
+];
+
+static DEFAULT: Theme = Theme {
+    header: Style::fg(Color::Named(6)).bold(),
+    section: Style::fg(Color::Named(1)).bold(),
+    username: Style::fg(Color::Named(6)).bold(),
+    timestamp: Style::fg(Color::Named(2)),
+    pronun_us: Style::fg(Color::Named(6)).bold(),
+    pronun_uk: Style::fg(Color::Named(5)).bold(),
+    emphasis: Style::PLAIN.bold(),
+    note: Style::fg(Color::Named(3)),
+    quote: Style::fg(Color::Named(4)),
+    link: Style::fg(Color::Named(8)),
+    error: Style::fg(Color::Named(1)).bold(),
+    tip: Style::fg(Color::Named(2)),
+    key: Style::PLAIN.on(Color::Named(0)).bold(),
+};
+
+static PLAIN: Theme = Theme {
+    header: Style::PLAIN,
+    section: Style::PLAIN,
+    username: Style::PLAIN,
+    timestamp: Style::PLAIN,
+    pronun_us: Style::PLAIN,
+    pronun_uk: Style::PLAIN,
+    emphasis: Style::PLAIN,
+    note: Style::PLAIN,
+    quote: Style::PLAIN,
+    link: Style::PLAIN,
+    error: Style::PLAIN,
+    tip: Style::PLAIN,
+    key: Style::PLAIN,
+};
+
+impl Default for Theme {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl Theme {
+    fn role_mut(&mut self, role: &str) -> &mut Style {
+        match role {
+            "header" => &mut self.header,
+            "section" => &mut self.section,
+            "username" => &mut self.username,
+            "timestamp" => &mut self.timestamp,
+            "pronun-us" => &mut self.pronun_us,
+            "pronun-uk" => &mut self.pronun_uk,
+            "emphasis" => &mut self.emphasis,
+            "note" => &mut self.note,
+            "quote" => &mut self.quote,
+            "link" => &mut self.link,
+            "error" => &mut self.error,
+            "tip" => &mut self.tip,
+            "key" => &mut self.key,
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// The default theme with the `[theme]` table of `cfg` applied.
+    pub fn from_config(cfg: &MafaConfig) -> Result<Self> {
+        let palette = match cfg.get(Some("theme"), "palette") {
+            Some(v) => {
+                Palette::from_str(v).ok_or(MafaError::InvalidTheme("palette".to_string()))?
+            }
+            None => Palette::detect(),
+        };
+
+        let mut theme = Self::default();
+        for role in ALL_ROLE {
+            if let Some(v) = cfg.get(Some("theme"), role) {
+                *theme.role_mut(role) =
+                    Style::from_str(v).ok_or(MafaError::InvalidTheme(role.to_string()))?;
+            }
+            let fitted = theme.role_mut(role).fit(palette);
+            *theme.role_mut(role) = fitted;
+        }
+
+        Ok(theme)
+    }
+}
+
+static INSTALLED: OnceCell<Theme> = OnceCell::new();
+
+///
+/// Make `theme` the one used by all printing afterwards, only the first
+/// installed one takes effect.
+pub fn install(theme: Theme) {
+    let _ = INSTALLED.set(theme);
+}
+
+///
+/// The installed theme, or the plain one if `nocolor`.
+pub fn current(nocolor: bool) -> &'static Theme {
+    if nocolor {
+        &PLAIN
+    } else {
+        INSTALLED.get().unwrap_or(&DEFAULT)
+    }
+}
+
+///
+/// Whether color is acceptable for standard output, regardless of
+/// `--nocolor`.
+pub fn color_wanted() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+
+    !no_color && unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1
+}
+
+#[cfg(test)]
+mod utst {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn style_1() {
+        let th = current(false);
+        assert_eq!(th.header.paint("RESULT"), "\x1b[36;1mRESULT\x1b[0m");
+        assert_eq!(th.link.paint("[ x ]"), "\x1b[90m[ x ]\x1b[0m");
+        assert_eq!(th.emphasis.paint("a"), "\x1b[1ma\x1b[0m");
+        assert_eq!(th.key.paint("NOT"), "\x1b[40;1mNOT\x1b[0m");
+        assert_eq!(current(true).header.paint("RESULT"), "RESULT");
+    }
+
+    #[test]
+    fn style_2() {
+        let style = Style::from_str("bold underline #ff8700 on 236").expect("buggy");
+        assert_eq!(
+            style.paint("a"),
+            "\x1b[38;2;255;135;0;48;5;236;1;4ma\x1b[0m"
+        );
+        assert_eq!(
+            style.fit(Palette::Ansi256).paint("a"),
+            "\x1b[38;5;208;48;5;236;1;4ma\x1b[0m"
+        );
+        assert_eq!(
+            style.fit(Palette::Ansi16).paint("a"),
+            "\x1b[33;40;1;4ma\x1b[0m"
+        );
+        assert_eq!(Style::from_str("none"), Some(Style::PLAIN));
+        assert_eq!(Style::from_str("bright-purple"), None);
+        assert_eq!(Style::from_str("#12345"), None);
+        assert_eq!(Style::from_str("bold on"), None);
+    }
+
+    #[test]
+    fn from_config_1() {
+        let cfg = MafaConfig::from_str(
+            r#"
+[theme]
+palette = "16"
+header = "bright-yellow"
+link = "none"
+"#,
+        )
+        .expect("buggy");
+        let th = Theme::from_config(&cfg).expect("buggy");
+        assert_eq!(th.header.paint("a"), "\x1b[93ma\x1b[0m");
+        assert_eq!(th.link.paint("a"), "a");
+        assert_eq!(th.error, DEFAULT.error);
+
+        let cfg = MafaConfig::from_str("[theme]\nusername = \"blinking\"").expect("buggy");
+        assert!(matches!(
+            Theme::from_config(&cfg),
+            Err(MafaError::InvalidTheme(role)) if role == "username"
+        ));
+    }
+}
@@ -13,15 +13,17 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use crate::backend::BrowserBackend;
 use crate::mafadata::MafaData;
 use crate::MafaClient;
 
-use wda::BasicAutomation;
-use wda::GeckoDriver;
+use crate::cache;
+use crate::cache::CacheEntry;
+use crate::cancel;
+use crate::retry;
+
 use wda::WdaError as WdaErr;
-use wda::WdaSett;
 use wda::WdcError as WdcErr;
-use wda::WebDrvAstn;
 
 use crate::error::MafaError;
 use crate::error::Result;
@@ -32,9 +34,11 @@ use crate::ev_ntf::EventNotifier;
 use crate::ev_ntf::MafaEvent;
 
 use crate::MafaInput;
+use crate::OutputFormat;
 
 use crate::comm;
 use crate::comm::CacheMechanism;
+use crate::config;
 
 use clap::Arg as ClapArg;
 use clap::ArgAction as ClapArgAction;
@@ -63,7 +67,6 @@ impl SaveFormat {
 
 #[derive(Debug, Default)]
 pub struct TwtlInput {
-    imode: bool, // no from clap
     username: String,
     ntweets: u16,
     save_to: Option<PathBuf>,
@@ -116,13 +119,7 @@ impl TwtlInput {
         let m = cmd_twtl.try_get_matches_from(args);
 
         match m {
-            Ok(ca_matched) => {
-                let mut twtl_in = TwtlInput::from_ca_matched(&ca_matched)?;
-
-                twtl_in.imode = true;
-
-                Ok(twtl_in)
-            }
+            Ok(ca_matched) => TwtlInput::from_ca_matched(&ca_matched),
             // this will print helper
             Err(err_match) => Err(MafaError::ClapMatchError(err_match.render())),
         }
@@ -303,37 +300,53 @@ pub fn get_cmd() -> ClapCommand {
 
     let opt_ntweets = {
         type O = opts::NumTweets;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .short(O::shortopt())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .short(O::shortopt())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("twtl"),
+            O::longopt(),
+        )
     };
 
     let opt_saveto = {
         type O = opts::SaveTo;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("twtl"),
+            O::longopt(),
+        )
     };
 
     let opt_saveformat = {
         type O = opts::SaveFormat;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .default_value(O::def_val())
-            .help(O::helper())
-            .long_help(O::long_helper())
+        config::with_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .default_value(O::def_val())
+                .help(O::helper())
+                .long_help(O::long_helper()),
+            Some("twtl"),
+            O::longopt(),
+        )
     };
 
     let opt_trylogin = {
         type O = opts::TryLogin;
-        ClapArg::new(O::id())
-            .long(O::longopt())
-            .action(ClapArgAction::SetTrue)
-            .help(O::helper())
+        config::with_flag_defaults(
+            ClapArg::new(O::id())
+                .long(O::longopt())
+                .action(ClapArgAction::SetTrue)
+                .help(O::helper()),
+            Some("twtl"),
+            O::longopt(),
+        )
     };
 
     let cmd_twtl = ClapCommand::new("twtl")
@@ -353,45 +366,6 @@ pub struct UlPath {
     lower_idx: Vec<u8>,
 }
 
-#[derive(Debug)]
-struct UlpathCache(Vec<UlPath>);
-
-impl UlpathCache {
-    fn from_pbuf(pb: PathBuf) -> Result<Self> {
-        let rawdata = std::fs::read_to_string(&pb).unwrap(); // FIXME: no unwrap
-
-        let mut ret = vec![];
-
-        let bytes = rawdata.as_bytes();
-
-        let mut previ;
-        let mut curri = 0;
-
-        let mut caches = Vec::<&[u8]>::new();
-
-        for i in 0..bytes.len() {
-            if bytes[i] == 0xa {
-                previ = if curri == 0 { curri } else { curri + 1 };
-                curri = i;
-                caches.push(&bytes[previ..curri]);
-            }
-        }
-
-        for ele in caches {
-            let deser = serde_json::from_slice::<Vec<Vec<u8>>>(ele).unwrap();
-            if deser.len() != 2 {
-                return Err(MafaError::CacheCorrupted);
-            }
-            let mut ulp = UlPath::default();
-            ulp.upper_idx = deser[0].clone();
-            ulp.lower_idx = deser[1].clone();
-            ret.push(ulp);
-        }
-
-        Ok(Self(ret))
-    }
-}
-
 ///
 /// it is ensured that the returned pbuf is completely valid,
 /// if orig is not, give twtl-saved.json as default value.
@@ -422,7 +396,7 @@ fn ensure_save_to(s: &str, save_fmt: Option<SaveFormat>) -> Result<PathBuf> {
             }
             _ => {
                 dbgmsg!("io bad {:?}", err_io);
-                Err(MafaError::BugFound(2348))
+                Err(MafaError::Io(pbuf, err_io))
             }
         },
     }
@@ -449,11 +423,11 @@ fn normalized_username(v: &str) -> String {
     s
 }
 
-impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
+impl<'a, 'b, 'c, B: BrowserBackend> MafaClient<'a, 'b, 'c, TwtlInput, UlPath, B> {
     fn notify(&self, ev: MafaEvent) -> Result<()> {
         self.ntf
             .lock()
-            .map_err(|_| MafaError::BugFound(7890))?
+            .map_err(|_| MafaError::Bug("lock poisoned"))?
             .notify(ev);
 
         Ok(())
@@ -461,8 +435,15 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
 
     fn refresh_ulpath(&mut self, rebuild_cache: bool) -> Result<()> {
         if !rebuild_cache {
-            let caches_from_files = UlpathCache::from_pbuf(self.mafad.pathto_exist_cache("twtl")?)?;
-            self.caches = caches_from_files.0;
+            self.caches = cache::load(self.mafad, "twtl")?
+                .into_iter()
+                .map(|record| {
+                    record.entry.into_ulpath().map(|(upper, lower)| UlPath {
+                        upper_idx: upper,
+                        lower_idx: lower,
+                    })
+                })
+                .collect::<Result<Vec<UlPath>>>()?;
             return Ok(());
         }
 
@@ -509,66 +490,39 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
         let ulpath = serde_json::from_slice::<UlPath>(jsout.as_bytes()).expect("deser");
         dbgg!(&ulpath);
 
-        let u_part = serde_json::to_string(&ulpath.upper_idx).unwrap();
-        let l_part = serde_json::to_string(&ulpath.lower_idx).unwrap();
-        let comb = format!("[{},{}]\n", u_part, l_part);
-        dbgg!(&comb);
-
-        self.mafad
-            .cache_append("twtl", &comb, &format!("{}-", &comb))?;
+        cache::learn(
+            self.mafad,
+            "twtl",
+            CacheEntry::UlPath {
+                upper: ulpath.upper_idx.clone(),
+                lower: ulpath.lower_idx.clone(),
+            },
+            "mafa_rs",
+        )?;
 
         self.caches.push(ulpath);
 
         Ok(())
     }
 
-    fn cache_on_gh(&self, url: &str) -> Result<String> {
-        match self.wda.go_url(url) {
-            Ok(_) => {}
-            Err(err_navi) => {
-                if let WdaErr::WdcFail(WdcErr::BadDrvCmd(err, msg)) = err_navi {
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_navi));
-                }
-            }
-        }
-
-        let jsout: String;
-        let jsin = "return document.getElementsByTagName('pre')[0].innerText;";
-
-        match self.wda.eval(jsin, vec![]) {
-            Ok(ret) => jsout = ret,
-            Err(err_eval) => {
-                if let WdaErr::WdcFail(WdcErr::BadDrvCmd(err, msg)) = err_eval {
-                    dbgg!(123);
-                    return Err(MafaError::WebDrvCmdRejected(err, msg));
-                } else {
-                    return Err(MafaError::UnexpectedWda(err_eval));
-                }
-            }
-        }
-
-        // let jsout = jsout.replace("\"", "").replace("\\n", "\n");
-        let jsout = jsout.replace('"', "").replace("\\n", "\n");
-
-        dbgg!(&jsout);
-
-        Ok(jsout)
-    }
-
     fn try_rebuild_cache(&mut self) -> Result<()> {
         let mut rebuild_cache = false;
 
         if let CacheMechanism::Remote = self.input.cachm {
-            let remote_data = self
-                .cache_on_gh("https://raw.githubusercontent.com/micl2e2/mafa-cache/master/twtl")?;
+            let remote_data = cache::fetch_remote(
+                &self.input.cache_remote,
+                &self.input.cache_manifest,
+                "twtl",
+                |url| cache::fetch_via_browser(self.wda, url),
+            )?;
 
-            self.mafad.init_cache("twtl", &remote_data)?;
+            cache::init(self.mafad, "twtl", &remote_data, "remote")?;
         } else if let CacheMechanism::Local = self.input.cachm {
-            self.mafad.try_init_cache(
+            cache::try_init(
+                self.mafad,
                 "twtl",
                 "[[2,0,0,2,3,0,0,0,0,0,2,0,0,2,1,0,0,0],[0,0,0,0,0,1,1,1]]\n[[2,0,0,1,3,0,0,0,0,0,2,0,0,2,1,0,0,0],[0,0,0,0,0,1,1,1]]\n[[2,0,0,1,3,0,0,0,0,0,2,0,0,2,1,0],[0,0,0,0,0,1,1,1]]\n-",
+                "local",
             )?;
             // number of NL is the number of website changes
         } else if let CacheMechanism::No = self.input.cachm {
@@ -592,6 +546,39 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
         Ok(())
     }
 
+    ///
+    /// Rebuild the cache by visiting the website, regardless of the cache
+    /// mechanism, the rebuilt entry goes first. Returns the number of
+    /// entries in use.
+    pub fn rebuild_cache(&mut self) -> Result<usize> {
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Twtl,
+            is_fin: false,
+        })?;
+        self.refresh_ulpath(true)?;
+        self.notify(MafaEvent::BuildCache {
+            cate: Category::Twtl,
+            is_fin: true,
+        })?;
+
+        self.refresh_ulpath(false)?;
+
+        Ok(self.caches.len())
+    }
+
+    ///
+    /// not being recorded is not fatal
+    fn record_outcome(&self, ulpaths_i: usize, is_ok: bool) {
+        let ulpath = &self.caches[ulpaths_i];
+        let entry = CacheEntry::UlPath {
+            upper: ulpath.upper_idx.clone(),
+            lower: ulpath.lower_idx.clone(),
+        };
+        if let Err(_err_rec) = cache::record_outcome(self.mafad, "twtl", &entry, is_ok) {
+            dbgg!(_err_rec);
+        }
+    }
+
     fn handle_login(&self) -> Result<(EurKind, String)> {
         let url = "https://twitter.com/i/flow/login";
         if let Err(err_navi) = self.wda.go_url(url) {
@@ -607,10 +594,16 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
         self.notify(MafaEvent::WaitSecsMayInterrupt {
             cate: Category::Twtl,
             count: wait_in_secs,
-            safe: !self.sub_input.imode,
+            safe: true,
         })?;
 
-        std::thread::sleep(std::time::Duration::from_secs(wait_in_secs));
+        // in interactive mode, Ctrl-C ends the wait but keeps the browser,
+        // which means the login is finished rather than cancelled
+        match cancel::sleep(std::time::Duration::from_secs(wait_in_secs)) {
+            Ok(_) => {}
+            Err(MafaError::Cancelled) => cancel::reset(),
+            Err(err) => return Err(err),
+        }
 
         Ok((EurKind::TwtlTryLogin, "_".to_string()))
     }
@@ -657,24 +650,37 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
 
         let nocolor = self.input.nocolor;
         let asciiful = self.input.ascii;
-        let wrap_width = self.input.wrap_width;
+        let wrap_width = self.input.wrap_width_now();
         let wrap_may_break = self.input.wrap_may_break;
 
         let mut twov_list = Vec::<TweetOverview>::new();
         let mut all_output = String::from("");
         let mut i_valid = 0;
 
+        let is_json = self.input.output == OutputFormat::Json;
+
         for i in 0..tweets.len() {
             let one_tweet = &tweets[i];
             let may_twov = TweetOverview::from_str(one_tweet);
             if let Ok(twov) = may_twov {
-                all_output +=
-                    &twov.pretty_print(i_valid + 1, nocolor, asciiful, wrap_width, wrap_may_break);
+                if !is_json {
+                    all_output += &twov.pretty_print(
+                        i_valid + 1,
+                        nocolor,
+                        asciiful,
+                        wrap_width,
+                        wrap_may_break,
+                    );
+                }
                 twov_list.push(twov);
                 i_valid += 1;
             }
         }
 
+        if is_json {
+            all_output = serde_json::to_string(&twov_list).map_err(MafaError::Json)?;
+        }
+
         self.try_save_tweets(twov_list)?;
 
         Ok((EurKind::TwtlResult, all_output))
@@ -778,8 +784,10 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
         let url = format!("https://twitter.com/{}", tuid);
 
         let mut is_url_reached = false;
-        let mut try_times = 10;
-        while try_times > 0 {
+        let mut attempts = self.input.retry.apply(retry::TWTL_NAVIGATE).begin();
+        while attempts.remain() {
+            cancel::check()?;
+
             if let Err(err_navi) = self.wda.go_url(&url) {
                 if let WdaErr::WdcFail(WdcErr::BadDrvCmd(err, msg)) = err_navi {
                     if err.contains("timeout") {
@@ -787,8 +795,9 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
                             cate: Category::Twtl,
                             is_fin: false,
                         })?;
-                        try_times -= 1;
-                        dbgg!(try_times);
+                        attempts.fail();
+                        cancel::sleep(attempts.delay())?;
+                        dbgg!(attempts.left());
                         continue;
                     } else {
                         return Err(MafaError::WebDrvCmdRejected(err, msg));
@@ -816,17 +825,20 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
         let mut tweets_got_final = Vec::<String>::new();
         let mut tweets_got_uoset = std::collections::BTreeSet::<String>::new();
 
-        let mut reach_tl_end_counter = 0u8;
+        let scroll_policy = self.input.retry.apply(retry::TWTL_SCROLL);
+        let mut stalls = scroll_policy.begin();
         let mut prev_nreq = 0u16;
 
         let mut ulpaths_i = 0;
         let ulpaths_len = self.caches.len();
 
         if ulpaths_len == 0 {
-            return Err(MafaError::BugFound(2347));
+            return Err(MafaError::Bug("caches not loaded"));
         }
 
         while nleft_tweets > 0 {
+            cancel::check()?;
+
             let mut jsin = "window['ulpath']=arguments[0];".to_string();
             // script/twtl_v1-tweets.js
             jsin += "console.log=function(){};function get_loaded_nth(e){let n=e.childNodes.length;if(n==0)return 0;let t=0;for(let l=0;l<n;l++){let o=e.childNodes[l];if(o==undefined){console.log('nth child null',n,e.childNodes);return 0}if(o.innerText!=null&&o.innerText!=undefined)t+=1;else return t}return t}var send_back=arguments[arguments.length-1];clearInterval(window['get_tweets']);window['get_tweets']=setInterval((function(){var e=window['ulpath'];var n=document.body;for(let t=0;t<e.upper_idx.length;t++){let l=e.upper_idx[t];if(n.childNodes.length>l){n=n.childNodes[l]}}window.ulpath.parent_of_fork_nodes=n;let t=get_loaded_nth(n);console.log('loaded_n',t);if(t>0){var l=t;let e=[];console.log('nchild is',l);for(let t=0;t<l;t++){let l=n.childNodes[t];let d=l.innerHTML.match('/status/([0-9]+)/');var o='twtl_v1'+String.fromCharCode(10);if(d!=null&&d!=undefined&&d.length==2)o+=d[1]+String.fromCharCode(10);else o+='UNKNOWNID'+String.fromCharCode(10);o+=l.innerText;e.push(o)}console.log(e);send_back(e);clearInterval(window['get_tweets'])}else{}}),1e3);";
@@ -838,6 +850,10 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
                 Err(err_eval) => {
                     if let WdaErr::WdcFail(WdcErr::BadDrvCmd(err, msg)) = err_eval {
                         if err.contains("timeout") {
+                            // timing out after some tweets may only mean the end
+                            if tweets_got_final.is_empty() {
+                                self.record_outcome(ulpaths_i, false);
+                            }
                             ulpaths_i += 1;
                             if ulpaths_i < ulpaths_len {
                                 self.notify(MafaEvent::TryNextCache {
@@ -880,6 +896,10 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
                 is_fin: true,
             })?;
 
+            if tweets_got_final.is_empty() {
+                self.record_outcome(ulpaths_i, true);
+            }
+
             let tweets = serde_json::from_slice::<Vec<String>>(&jsout.as_bytes()).expect("deser");
             let n_got = tweets.len();
 
@@ -905,14 +925,14 @@ impl<'a, 'b, 'c> MafaClient<'a, 'b, 'c, TwtlInput, UlPath> {
             })?;
 
             if nleft_tweets == prev_nreq {
-                reach_tl_end_counter += 1;
-                std::thread::sleep(std::time::Duration::from_secs(1));
+                stalls.backoff();
+                cancel::sleep(stalls.delay())?;
             } else {
                 prev_nreq = nleft_tweets;
-                reach_tl_end_counter = 0;
+                stalls = scroll_policy.begin();
             }
 
-            if reach_tl_end_counter > 9 {
+            if !stalls.remain() {
                 dbgmsg!("maybe reach end, left: {}", nleft_tweets);
                 break;
             }
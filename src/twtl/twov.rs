@@ -13,6 +13,7 @@ use unicode_width::UnicodeWidthStr;
 
 use crate::comm;
 use crate::error::{MafaError, Result};
+use crate::theme;
 
 #[derive(Debug, Default, serde::Serialize)]
 struct QuoteTweet<'a> {
@@ -75,7 +76,8 @@ impl<'a> TweetOverview<'a> {
             return Err(MafaError::TweetNotRecoginized(2));
         }
 
-        let re_twid = regex::Regex::new("^\n[0-9]{19}$").map_err(|_| MafaError::BugFound(1122))?;
+        let re_twid =
+            regex::Regex::new("^\n[0-9]{19}$").map_err(|_| MafaError::Bug("invalid regex"))?;
 
         if !re_twid.is_match(tw_as_lines[1].0) {
             dbgg!((6, orig));
@@ -118,9 +120,10 @@ impl<'a> TweetOverview<'a> {
 
         let mut li_usrname = 0;
         let re_usrname =
-            regex::Regex::new("^\n@[A-Za-z0-9_]+$").map_err(|_| MafaError::BugFound(1122))?;
+            regex::Regex::new("^\n@[A-Za-z0-9_]+$").map_err(|_| MafaError::Bug("invalid regex"))?;
         let mut li_disname = 0;
-        let re_rt = regex::Regex::new("^\n.* Retweeted$").map_err(|_| MafaError::BugFound(1122))?;
+        let re_rt =
+            regex::Regex::new("^\n.* Retweeted$").map_err(|_| MafaError::Bug("invalid regex"))?;
         let mut li_rt = 0;
 
         for i in 0..li_tstamp {
@@ -149,10 +152,10 @@ impl<'a> TweetOverview<'a> {
         let re_noisecnt = regex::Regex::new(
         r"^\n(([0-9]{3})|([0-9]{2})|([0-9]{1})|([1-9]{1},[0-9]{3})|(([1-9]{1}|[0-9]{2}|[0-9]{3})(.[0-9]){0,1}[KMB]))$",
     )
-    .map_err(|_| MafaError::BugFound(1122))?;
+    .map_err(|_| MafaError::Bug("invalid regex"))?;
 
         let re_imagealt =
-            regex::Regex::new(r"^\n(ALT|GIF)$").map_err(|_| MafaError::BugFound(1122))?;
+            regex::Regex::new(r"^\n(ALT|GIF)$").map_err(|_| MafaError::Bug("invalid regex"))?;
 
         // li_ctn_lastl
         loop {
@@ -169,7 +172,7 @@ impl<'a> TweetOverview<'a> {
         }
 
         let re_quote_header =
-            regex::Regex::new(r"^\nQuote Tweet$").map_err(|_| MafaError::BugFound(1122))?;
+            regex::Regex::new(r"^\nQuote Tweet$").map_err(|_| MafaError::Bug("invalid regex"))?;
 
         // selftw_lastl
         let mut li_selftw_lastl = 0; // lastl is inclusive
@@ -413,15 +416,20 @@ impl<'a> TweetOverview<'a> {
         };
         dbgg!(&header_part);
 
+        let theme = theme::current(nocolor);
         let colorful_header_part = if asciiful {
             format!(
-                " {} | \x1b[36;1m{}\x1b[0m | \x1b[32m{}\x1b[0m |",
-                given_id, comb_name, self.post_tstamp
+                " {} | {} | {} |",
+                given_id,
+                theme.username.paint(&comb_name),
+                theme.timestamp.paint(&self.post_tstamp)
             )
         } else {
             format!(
-                " {} │ \x1b[36;1m{}\x1b[0m │ \x1b[32m{}\x1b[0m │",
-                given_id, comb_name, self.post_tstamp
+                " {} │ {} │ {} │",
+                given_id,
+                theme.username.paint(&comb_name),
+                theme.timestamp.paint(&self.post_tstamp)
             )
         };
 
@@ -442,11 +450,7 @@ impl<'a> TweetOverview<'a> {
         output += line_tail_comp.0;
         output += "\n";
 
-        output += if nocolor {
-            &header_part
-        } else {
-            &colorful_header_part
-        };
+        output += &colorful_header_part;
         output += "\n";
 
         // bottom line
@@ -462,16 +466,14 @@ impl<'a> TweetOverview<'a> {
 
         // rt ply just before content
         let before_ctn = if let Some(v) = &self.retweeter {
-            String::from(if nocolor { "" } else { "\x1b[33m" })
-                + (if asciiful { "RT " } else { "⥁ " })
-                + v
-                + (if nocolor { "" } else { "\x1b[0m" })
+            theme
+                .note
+                .paint(&(String::from(if asciiful { "RT " } else { "⥁ " }) + v))
                 + "\n\n"
         } else if let Some(v) = &self.reply_to {
-            String::from(if nocolor { "" } else { "\x1b[33m" })
-                + (if asciiful { "RPL " } else { "⤇ " })
-                + v
-                + (if nocolor { "" } else { "\x1b[0m" })
+            theme
+                .note
+                .paint(&(String::from(if asciiful { "RPL " } else { "⤇ " }) + v))
                 + "\n\n"
         } else {
             String::from("")
@@ -497,34 +499,19 @@ impl<'a> TweetOverview<'a> {
         if let Some(quote) = &self.quote {
             let mut quote_all = String::from("");
             quote_all += "\n";
-            quote_all += if asciiful {
-                if nocolor {
-                    "\"\"\""
-                } else {
-                    "\x1b[34m\"\"\"\x1b[0m"
-                }
+            quote_all += &theme.quote.paint(if asciiful {
+                "\"\"\""
             } else {
-                if nocolor {
-                    "\u{201c}\u{201c}\u{201c}"
-                } else {
-                    "\x1b[34m\u{201c}\u{201c}\u{201c}\x1b[0m"
-                }
-            };
+                "\u{201c}\u{201c}\u{201c}"
+            });
             quote_all += "\n";
             quote_all += &quote.ctn;
-            quote_all += if asciiful {
-                if nocolor {
-                    "\n\"\"\""
-                } else {
-                    "\n\x1b[34m\"\"\"\x1b[0m"
-                }
+            quote_all += "\n";
+            quote_all += &theme.quote.paint(if asciiful {
+                "\"\"\""
             } else {
-                if nocolor {
-                    "\n\u{201d}\u{201d}\u{201d}"
-                } else {
-                    "\n\x1b[34m\u{201d}\u{201d}\u{201d}\x1b[0m"
-                }
-            };
+                "\u{201d}\u{201d}\u{201d}"
+            });
             quote_all += " - ";
             quote_all += &quote.dispname;
             quote_all += "(";
@@ -549,17 +536,10 @@ impl<'a> TweetOverview<'a> {
         output += "\n";
 
         // link part
-        let linkpart = if nocolor {
-            format!(
-                "[ http://twitter.com/{}/status/{} ]",
-                self.username, self.twid
-            )
-        } else {
-            format!(
-                "\x1b[90m[ http://twitter.com/{}/status/{} ]\x1b[0m",
-                self.username, self.twid
-            )
-        };
+        let linkpart = theme.link.paint(&format!(
+            "[ http://twitter.com/{}/status/{} ]",
+            self.username, self.twid
+        ));
         output += &linkpart;
         output += "\n";
         output += "\n";
@@ -29,7 +29,7 @@ mod gtrans_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    // let mafad = MafaData::init();
+                    // let mafad = MafaData::init().expect("must ok");
                     // let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
                     // let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in);
@@ -64,7 +64,7 @@ mod gtrans_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    // let mafad = MafaData::init();
+                    // let mafad = MafaData::init().expect("must ok");
                     // let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     // let wda_inst = mafa::init_wda(&mafa_in).expect("bug");
                     let gtrans_in = GtransInput::from_ca_matched(sub_m);
@@ -118,7 +118,7 @@ mod gtrans_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     // no way to gurantee the cache data the is read from a file
                     // thus put it in memory
                     let likely_failed_cache = vec![vec![1, 1, 1], vec![2, 2, 2]];
@@ -174,7 +174,7 @@ mod gtrans_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
                     let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in).expect("bug");
@@ -0,0 +1,239 @@
+#[cfg(feature = "gtrans")]
+mod gtrans_fake {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use mafa::backend::BackendResult;
+    use mafa::backend::BrowserBackend;
+    use mafa::backend::Offline;
+    use mafa::error::MafaError;
+    use mafa::ev_ntf::EurKind;
+    use mafa::ev_ntf::EventNotifier;
+    use mafa::gtrans::GtransInput;
+    use mafa::mafadata::MafaData;
+    use mafa::MafaClient;
+    use mafa::MafaInput;
+
+    #[derive(Debug)]
+    struct FakeBrowser {
+        innertxt: String,
+    }
+
+    impl BrowserBackend for FakeBrowser {
+        fn go_url(&self, _url: &str) -> BackendResult<()> {
+            Ok(())
+        }
+
+        fn eval(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(String::from(""))
+        }
+
+        fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(self.innertxt.clone())
+        }
+
+        fn get_url(&self) -> BackendResult<String> {
+            Ok(String::from("https://translate.google.com/"))
+        }
+
+        fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn _1() {
+        // translate result, served by a fake browser
+
+        let matched = mafa::get_cmd().try_get_matches_from(vec![
+            "mafa", "--output", "json", "gtrans", "--tl", "fr", "thank", "you",
+        ]);
+        let matched = matched.expect("must ok");
+
+        match MafaInput::from_ca_matched(&matched) {
+            Ok(mafa_in) => match matched.subcommand() {
+                Some(("gtrans", sub_m)) => {
+                    let mafad = MafaData::init().expect("must ok");
+                    let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+                    let ntf = Arc::new(Mutex::new(EventNotifier::new()));
+                    let fake = FakeBrowser {
+                        innertxt: String::from("\"merci\""),
+                    };
+                    let mut ag = MafaClient::new(&mafad, ntf, &mafa_in, gtrans_in, &fake);
+                    match ag.handle(Some(vec![vec![4, 0, 1]])) {
+                        Ok((ewrk, ret)) => {
+                            assert_eq!(ewrk, EurKind::GtransResult);
+                            assert_eq!(
+                                ret,
+                                r#"{"source_lang":"auto","target_lang":"fr","words":"thank you","translation":"merci","pronunciation":""}"#
+                            );
+                        }
+                        Err(e) => panic!("unexpected error {:?}", e),
+                    }
+                }
+                _ => {
+                    panic!("buggy");
+                }
+            },
+            Err(_) => {
+                panic!("buggy");
+            }
+        }
+    }
+
+    #[test]
+    fn _2() {
+        // stored result, reused with no browser at all
+
+        let mafad = MafaData::init().expect("must ok");
+        let ntf = Arc::new(Mutex::new(EventNotifier::new()));
+
+        let matched = mafa::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "--output",
+                "json",
+                "--result-ttl",
+                "3600",
+                "gtrans",
+                "--tl",
+                "fr",
+                "stored",
+                "thanks",
+            ])
+            .expect("must ok");
+        let mafa_in = MafaInput::from_ca_matched(&matched).expect("must ok");
+        let sub_m = matched.subcommand_matches("gtrans").expect("must ok");
+        let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+        let fake = FakeBrowser {
+            innertxt: String::from("\"merci\""),
+        };
+        let mut ag = MafaClient::new(&mafad, Arc::clone(&ntf), &mafa_in, gtrans_in, &fake);
+        let (_, fetched) = ag.handle(Some(vec![vec![4, 0, 1]])).expect("must ok");
+
+        // whitespaces make no difference
+        let matched = mafa::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "--output",
+                "json",
+                "--offline",
+                "gtrans",
+                "--tl",
+                "fr",
+                "stored  thanks",
+            ])
+            .expect("must ok");
+        let mafa_in = MafaInput::from_ca_matched(&matched).expect("must ok");
+        let sub_m = matched.subcommand_matches("gtrans").expect("must ok");
+        let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+        let mut ag = MafaClient::new(&mafad, Arc::clone(&ntf), &mafa_in, gtrans_in, &Offline);
+        match ag.handle(None) {
+            Ok((ewrk, ret)) => {
+                assert_eq!(ewrk, EurKind::GtransResult);
+                assert_eq!(
+                    ret,
+                    fetched.replace("\"stored thanks\"", "\"stored  thanks\"")
+                );
+            }
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+
+        // never fetched
+        let matched = mafa::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "--offline",
+                "gtrans",
+                "--tl",
+                "fr",
+                "never",
+                "fetched",
+                "words",
+            ])
+            .expect("must ok");
+        let mafa_in = MafaInput::from_ca_matched(&matched).expect("must ok");
+        let sub_m = matched.subcommand_matches("gtrans").expect("must ok");
+        let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+        let mut ag = MafaClient::new(&mafad, ntf, &mafa_in, gtrans_in, &Offline);
+        match ag.handle(None) {
+            Err(MafaError::ResultNotStored) => {}
+            _ => panic!("buggy"),
+        }
+    }
+
+    #[derive(Debug)]
+    struct SeqBrowser {
+        all_innertxt: Mutex<Vec<&'static str>>,
+    }
+
+    impl BrowserBackend for SeqBrowser {
+        fn go_url(&self, _url: &str) -> BackendResult<()> {
+            Ok(())
+        }
+
+        fn eval(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(String::from(""))
+        }
+
+        fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(self
+                .all_innertxt
+                .lock()
+                .expect("buggy")
+                .remove(0)
+                .to_string())
+        }
+
+        fn get_url(&self) -> BackendResult<String> {
+            Ok(String::from("https://translate.google.com/"))
+        }
+
+        fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn _3() {
+        // an unparsable result fails its own entry only
+
+        let from_file = std::env::temp_dir().join(format!("gtrans_fake_3.{}", std::process::id()));
+        std::fs::write(&from_file, "thank you\nbroken\ngood bye\n").expect("must ok");
+
+        let matched = mafa::get_cmd()
+            .try_get_matches_from(vec![
+                "mafa",
+                "--output",
+                "json",
+                "gtrans",
+                "--tl",
+                "fr",
+                "--from-file",
+                from_file.to_str().expect("must ok"),
+            ])
+            .expect("must ok");
+        let mafa_in = MafaInput::from_ca_matched(&matched).expect("must ok");
+        let sub_m = matched.subcommand_matches("gtrans").expect("must ok");
+        let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
+        let mafad = MafaData::init().expect("must ok");
+        let ntf = Arc::new(Mutex::new(EventNotifier::new()));
+        let fake = SeqBrowser {
+            all_innertxt: Mutex::new(vec!["\"merci\"", "\"cass", "\"au revoir\""]),
+        };
+        let mut ag = MafaClient::new(&mafad, ntf, &mafa_in, gtrans_in, &fake);
+        let handled = ag.handle(Some(vec![vec![4, 0, 1]]));
+        std::fs::remove_file(&from_file).expect("must ok");
+
+        let (ewrk, ret) = handled.expect("must ok");
+        assert_eq!(ewrk, EurKind::GtransPartial);
+        let batch = serde_json::from_str::<serde_json::Value>(&ret).expect("must ok");
+        assert_eq!(batch["results"].as_array().expect("must ok").len(), 2);
+        assert_eq!(batch["results"][1]["translation"], "au revoir");
+        assert_eq!(batch["failures"][0]["line"], 2);
+        assert_eq!(
+            batch["failures"][0]["error"],
+            "page layout not matched with the cache"
+        );
+    }
+}
@@ -35,7 +35,7 @@ mod gtrans_res {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
                     let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in).expect("bug");
@@ -70,7 +70,7 @@ mod gtrans_res {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("gtrans", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     let gtrans_in = GtransInput::from_ca_matched(sub_m).expect("must ok");
                     let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in).expect("bug");
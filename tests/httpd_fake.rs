@@ -0,0 +1,93 @@
+#[cfg(feature = "gtrans")]
+mod httpd_fake {
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+
+    use mafa::backend::BackendResult;
+    use mafa::backend::BrowserBackend;
+    use mafa::httpd::HttpApi;
+    use mafa::mafadata::MafaData;
+    use mafa::MafaInput;
+
+    #[derive(Debug)]
+    struct FakeBrowser;
+
+    impl BrowserBackend for FakeBrowser {
+        fn go_url(&self, _url: &str) -> BackendResult<()> {
+            Ok(())
+        }
+
+        fn eval(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(String::from(""))
+        }
+
+        fn eval_async(&self, _script: &str, _args: Vec<&str>) -> BackendResult<String> {
+            Ok(String::from(""))
+        }
+
+        fn get_url(&self) -> BackendResult<String> {
+            Ok(String::from(""))
+        }
+
+        fn page_src(&self, _save_to: Option<&str>) -> BackendResult<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    fn roundtrip(rawreq: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("must ok");
+        let addr = listener.local_addr().expect("must ok");
+
+        let rawreq = rawreq.to_string();
+        let requester = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).expect("must ok");
+            stream.write_all(rawreq.as_bytes()).expect("must ok");
+            let mut rawresp = String::new();
+            stream.read_to_string(&mut rawresp).expect("must ok");
+            rawresp
+        });
+
+        let mafad = MafaData::init().expect("must ok");
+        let mafa_in = MafaInput::from_ca_matched(
+            &mafa::get_cmd()
+                .try_get_matches_from(vec!["mafa"])
+                .expect("must ok"),
+        )
+        .expect("must ok");
+        let api = HttpApi::new(&mafad, &mafa_in, &FakeBrowser);
+        let (stream, _) = listener.accept().expect("must ok");
+        api.handle_conn(stream);
+
+        requester.join().expect("must ok")
+    }
+
+    #[test]
+    fn _1() {
+        // served without browser interaction
+        let rawresp = roundtrip("GET /gtrans?list-lang=true HTTP/1.1\r\n\r\n");
+        assert!(rawresp.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(rawresp.contains(r#"{"name":"French","code":"fr"}"#));
+    }
+
+    #[test]
+    fn _2() {
+        let rawresp = roundtrip("GET /gtrans?tl=xyz&words=hello HTTP/1.1\r\n\r\n");
+        assert!(rawresp.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(rawresp.ends_with(
+            r#"{"error":{"kind":"InvalidTargetLang","message":"invalid target language"}}"#
+        ));
+
+        let rawresp = roundtrip(
+            "POST /gtrans HTTP/1.1\r\nContent-Length: 27\r\n\r\n{\"from-file\":\"/etc/passwd\"}",
+        );
+        assert!(rawresp.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let rawresp = roundtrip("GET /nope HTTP/1.1\r\n\r\n");
+        assert!(rawresp.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let rawresp = roundtrip("DELETE /gtrans HTTP/1.1\r\n\r\n");
+        assert!(rawresp.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    }
+}
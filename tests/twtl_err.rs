@@ -29,7 +29,7 @@ mod twtl_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("twtl", sub_m)) => {
-                    // let mafad = MafaData::init();
+                    // let mafad = MafaData::init().expect("must ok");
                     // let twtl_in = TwtlInput::from_ca_matched(sub_m).expect("must ok");
                     // let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in);
@@ -80,7 +80,7 @@ mod twtl_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("twtl", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     // no way to gurantee the cache data the is read from a file
                     // thus put it in memory
                     let likely_failed_cache = vec![
@@ -136,7 +136,7 @@ mod twtl_err {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("twtl", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     let twtl_in = TwtlInput::from_ca_matched(sub_m).expect("must ok");
                     let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in).expect("bug");
@@ -35,7 +35,7 @@ mod twtl_res {
         match MafaInput::from_ca_matched(&matched) {
             Ok(mafa_in) => match matched.subcommand() {
                 Some(("twtl", sub_m)) => {
-                    let mafad = MafaData::init();
+                    let mafad = MafaData::init().expect("must ok");
                     let twtl_in = TwtlInput::from_ca_matched(sub_m).expect("must ok");
                     let ntf = Arc::new(Mutex::new(EventNotifier::new()));
                     let wda_inst = mafa::init_wda(&mafa_in).expect("bug");